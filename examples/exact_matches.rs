@@ -343,7 +343,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 include_withdrawn: false,
                 include_less_specifics: false,
                 include_more_specifics: false,
-                mui: None
+                mui: None,
+                debug_path: false,
+                stop_at_first_less_specific: false,
             },
             guard
         );
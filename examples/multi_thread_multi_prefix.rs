@@ -70,7 +70,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                                     include_withdrawn: true,
                                     include_less_specifics: true,
                                     include_more_specifics: true,
-                                    mui: None
+                                    mui: None,
+                                    debug_path: false,
+                                    stop_at_first_less_specific: false,
                                 },
                                 guard,
                             ).prefix_meta;
@@ -20,6 +20,20 @@ pub fn stride_sizes(attr: TokenStream, input: TokenStream) -> TokenStream {
         syn::Expr::Path(t) => t,
         _ => panic!("Expected Family Type"),
     };
+    // The optional number of bits for the first level of the buckets for
+    // each (prefix) length, i.e. the root arrays will have 1 << root_bits
+    // slots. Defaults to 4.
+    let root_bits = match attrs.get(2) {
+        Some(syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Int(i),
+            ..
+        })) => match i.base10_parse::<u8>() {
+            Ok(b) if (1..=16).contains(&b) => b,
+            _ => panic!("Expected a number of root bits between 1 and 16"),
+        },
+        Some(_) => panic!("Expected an integer for the number of root bits"),
+        None => 4,
+    };
     let prefixes_all_len;
     let all_len;
     let prefixes_buckets_name: syn::Ident;
@@ -53,7 +67,7 @@ pub fn stride_sizes(attr: TokenStream, input: TokenStream) -> TokenStream {
                 ][len as usize]
             }
         };
-        crate::maps::node_buckets_map_v4()
+        crate::maps::node_buckets_map_v4(root_bits)
     } else {
         all_len = (0..=128_u8).collect::<Vec<_>>();
         prefixes_all_len = (0..=128_u8)
@@ -84,7 +98,7 @@ pub fn stride_sizes(attr: TokenStream, input: TokenStream) -> TokenStream {
                     ][len as usize]
             }
         };
-        crate::maps::node_buckets_map_v6()
+        crate::maps::node_buckets_map_v6(root_bits)
     };
 
     let mut strides_num: Vec<u8> = vec![];
@@ -197,9 +211,9 @@ pub fn stride_sizes(attr: TokenStream, input: TokenStream) -> TokenStream {
     };
 
     let prefix_buckets_map = if ip_af.path.is_ident("IPv4") {
-        crate::maps::prefix_buckets_map_v4()
+        crate::maps::prefix_buckets_map_v4(root_bits)
     } else {
-        crate::maps::prefix_buckets_map_v6()
+        crate::maps::prefix_buckets_map_v6(root_bits)
     };
 
     let prefix_buckets_impl = quote! {
@@ -207,7 +221,7 @@ pub fn stride_sizes(attr: TokenStream, input: TokenStream) -> TokenStream {
         impl<AF: AddressFamily, M: Meta> PrefixBuckets<#ip_af, M> for #prefixes_buckets_name<AF, M> {
             fn init() -> #prefixes_buckets_name<AF, M> {
                 #prefixes_buckets_name {
-                    #( #prefixes_all_len: PrefixSet::init(1 << #prefixes_buckets_name::<AF, M>::get_bits_for_len(#all_len, 0)), )*
+                    #( #prefixes_all_len: PrefixSet::init(#prefixes_buckets_name::<AF, M>::get_bits_for_len(#all_len, 0)), )*
                     _af: PhantomData,
                     _m: PhantomData,
                 }
@@ -304,7 +318,7 @@ pub fn stride_sizes(attr: TokenStream, input: TokenStream) -> TokenStream {
     };
 
     let type_alias = quote! {
-        type #type_name<M> = TreeBitMap<#ip_af, M, #buckets_name<#ip_af>, #prefixes_buckets_name<#ip_af, M>>;
+        type #type_name<M, H = DefaultNodeHasher> = TreeBitMap<#ip_af, M, #buckets_name<#ip_af>, #prefixes_buckets_name<#ip_af, M>, H>;
     };
 
     let result = quote! {
@@ -347,6 +361,19 @@ pub fn stride_sizes(attr: TokenStream, input: TokenStream) -> TokenStream {
 /// of them falls short of the total number of bits for the address
 /// family.
 ///
+/// Optionally the number of bits for the root arrays of the IPv4 and the
+/// IPv6 tree, respectively, can be passed in as the third and fourth
+/// element. Every prefix length and every node length has a root array of
+/// `1 << root_bits` slots (or fewer for short lengths), subsequent levels
+/// have 16 slots. Larger root arrays reduce the number of collisions for
+/// dense lengths, at the cost of memory. The number of bits should be
+/// between 1 and 16, and defaults to 4.
+///
+/// ```ignore
+/// #[create_store(([5, 5, 4, 3, 3, 3, 3, 3, 3, 3], [4; 32], 12, 8))]
+/// struct WideRootStore;
+/// ```
+///
 /// # Example
 /// ```ignore
 /// use rotonda_store::prelude::*;
@@ -372,6 +399,10 @@ pub fn create_store(attr: TokenStream, item: TokenStream) -> TokenStream {
     let attrs = attr.elems.iter().collect::<Vec<_>>();
     let strides4 = attrs[0].clone();
     let strides6 = attrs[1].clone();
+    // The optional root bits overrides are passed on as is, the
+    // stride_sizes macro checks them.
+    let root_bits4 = attrs.get(2).map(|b| quote! { , #b });
+    let root_bits6 = attrs.get(3).map(|b| quote! { , #b });
     let strides4_name = format_ident!("{}IPv4", store_name);
     let strides6_name = format_ident!("{}IPv6", store_name);
 
@@ -379,10 +410,10 @@ pub fn create_store(attr: TokenStream, item: TokenStream) -> TokenStream {
         use ::std::marker::PhantomData;
         use ::inetnum::addr::Prefix;
 
-        #[stride_sizes((IPv4, #strides4))]
+        #[stride_sizes((IPv4, #strides4 #root_bits4))]
         struct #strides4_name;
 
-        #[stride_sizes((IPv6, #strides6))]
+        #[stride_sizes((IPv6, #strides6 #root_bits6))]
         struct #strides6_name;
     };
 
@@ -418,6 +449,15 @@ pub fn create_store(attr: TokenStream, item: TokenStream) -> TokenStream {
         > {
             v4: #strides4_name<M>,
             v6: #strides6_name<M>,
+            config: StoreConfig,
+            // The senders for the subscribers, together with a flag that is
+            // set once their receiver is gone.
+            subscribers: std::sync::RwLock<
+                Vec<(
+                    std::sync::mpsc::Sender<StoreEvent<M>>,
+                    std::sync::atomic::AtomicBool,
+                )>
+            >,
         }
 
         impl<
@@ -429,6 +469,80 @@ pub fn create_store(attr: TokenStream, item: TokenStream) -> TokenStream {
             }
         }
 
+        /// Creates a store with the default configuration, and inserts all
+        /// the (prefix, record) pairs into it, without updating the path
+        /// selections.
+        ///
+        /// Panics if the store can't be created. Pairs that can't be
+        /// inserted are skipped.
+        ///
+        /// # Example
+        /// ```
+        /// use rotonda_store::prelude::*;
+        /// use rotonda_store::prelude::multi::*;
+        /// use rotonda_store::meta_examples::PrefixAs;
+        ///
+        /// let store: MultiThreadedStore<PrefixAs> = [
+        ///     ("185.49.140.0/22", 211321),
+        ///     ("2a04:b900::/29", 211321),
+        /// ].into_iter().map(|(pfx, asn)| (
+        ///     pfx.parse::<Prefix>().unwrap(),
+        ///     Record::new(0, 0, RouteStatus::Active, PrefixAs(asn))
+        /// )).collect();
+        ///
+        /// assert_eq!(store.prefixes_count(), 2);
+        /// ```
+        impl<
+                M: Meta
+            > FromIterator<(Prefix, Record<M>)> for #store_name<M>
+        {
+            fn from_iter<I: IntoIterator<Item = (Prefix, Record<M>)>>(
+                iter: I
+            ) -> Self {
+                let store = Self::new().expect("failed to create store");
+                for (prefix, record) in iter {
+                    let _ = store.insert(&prefix, record, None);
+                }
+                store
+            }
+        }
+
+        /// Inserts all the (prefix, record) pairs into the store, without
+        /// updating the path selections.
+        ///
+        /// Pairs that can't be inserted are skipped, use `try_extend` to
+        /// stop at the first pair that fails instead.
+        ///
+        /// # Example
+        /// ```
+        /// use rotonda_store::prelude::*;
+        /// use rotonda_store::prelude::multi::*;
+        /// use rotonda_store::meta_examples::PrefixAs;
+        ///
+        /// let mut store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+        /// store.extend(
+        ///     ["185.49.140.0/22", "2a04:b900::/29"].into_iter().map(|pfx| (
+        ///         pfx.parse::<Prefix>().unwrap(),
+        ///         Record::new(0, 0, RouteStatus::Active, PrefixAs(211321))
+        ///     ))
+        /// );
+        ///
+        /// assert_eq!(store.prefixes_count(), 2);
+        /// ```
+        impl<
+                M: Meta
+            > Extend<(Prefix, Record<M>)> for #store_name<M>
+        {
+            fn extend<I: IntoIterator<Item = (Prefix, Record<M>)>>(
+                &mut self,
+                iter: I
+            ) {
+                for (prefix, record) in iter {
+                    let _ = self.insert(&prefix, record, None);
+                }
+            }
+        }
+
         impl<
                 M: Meta
             > #store_name<M>
@@ -484,11 +598,181 @@ pub fn create_store(attr: TokenStream, item: TokenStream) -> TokenStream {
             ///      }).map(|t| t.join()).collect();
             /// ```
             pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+                Self::new_with_config(StoreConfig::default())
+            }
+
+            /// Creates a new empty store with a tree for IPv4 and one for
+            /// IPv6, that uses the given [StoreConfig].
+            ///
+            /// The tree for an address family that is disabled in the
+            /// `address_families` of the config stays empty. Inserting
+            /// prefixes in it, or changing the status of its records,
+            /// returns an `AddressFamilyDisabled` error, and iterators and
+            /// queries for it return nothing.
+            ///
+            /// Returns an `InvalidStoreConfig` error if the config doesn't
+            /// pass [StoreConfig::validate].
+            ///
+            /// # Example
+            /// ```
+            /// use std::net::Ipv4Addr;
+            ///
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::NoMeta;
+            ///
+            /// let store = MultiThreadedStore::<NoMeta>::new_with_config(
+            ///     StoreConfig {
+            ///         accepted_len_range_v4: Some((8, 24)),
+            ///         ..Default::default()
+            ///     }
+            /// ).unwrap();
+            ///
+            /// let record = Record::new(0, 0, RouteStatus::Active, NoMeta::Empty);
+            /// let pfx_addr = Ipv4Addr::new(185, 49, 140, 0).into();
+            ///
+            /// assert!(store.insert(
+            ///     &Prefix::new(pfx_addr, 22).unwrap(), record.clone(), None
+            /// ).is_ok());
+            /// assert_eq!(
+            ///     store.insert(
+            ///         &Prefix::new(pfx_addr, 28).unwrap(), record, None
+            ///     ).err(),
+            ///     Some(PrefixStoreError::PrefixLengthNotAccepted)
+            /// );
+            /// assert_eq!(store.prefixes_count(), 1);
+            /// ```
+            pub fn new_with_config(
+                config: StoreConfig
+            ) -> Result<Self, Box<dyn std::error::Error>> {
+                config.validate()?;
+                let meta_interner = if config.intern_meta {
+                    Some(std::sync::Arc::new(MetaInterner::new()))
+                } else {
+                    None
+                };
+                let mut v4 = #strides4_name::new()?;
+                v4.store.max_retries_before_yield =
+                    config.max_retries_before_yield;
+                v4.store.meta_interner = meta_interner.clone();
+                let mut v6 = #strides6_name::new()?;
+                v6.store.max_retries_before_yield =
+                    config.max_retries_before_yield;
+                v6.store.meta_interner = meta_interner;
                 Ok(Self {
-                    v4: #strides4_name::new()?,
-                    v6: #strides6_name::new()?,
+                    v4,
+                    v6,
+                    config,
+                    subscribers: std::sync::RwLock::new(vec![]),
                 })
             }
+
+            /// Returns the [StoreConfig] this store was created with.
+            pub fn config(&self) -> &StoreConfig {
+                &self.config
+            }
+
+            /// Returns a receiver for the [StoreEvent]s of this store.
+            ///
+            /// Events are only sent if the store was created with
+            /// `emit_events` set in its [StoreConfig], otherwise the
+            /// receiver never receives anything. They are sent by `insert`
+            /// and the `mark_mui_*` methods, after the mutation succeeded,
+            /// and only if the mutation actually changed something, e.g.
+            /// withdrawing a record that is already withdrawn doesn't send
+            /// an event. Note that the records withdrawn by `sweep_stale`,
+            /// the records removed by `retain` and the modifications done
+            /// through `with_prefix_records` are not sent.
+            ///
+            /// The events are sent after the lock on the records of the
+            /// prefix is released. If multiple threads change the same
+            /// prefix concurrently, their events may arrive in a different
+            /// order than the one their changes were applied in. The events
+            /// for the changes made by a single thread always arrive in
+            /// order. A subscriber that needs the resulting state should
+            /// read it back from the store.
+            ///
+            /// The channel is unbounded, so a receiver that isn't drained
+            /// will grow without limits. Drop the receiver to unsubscribe.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new_with_config(
+            ///     StoreConfig { emit_events: true, ..Default::default() }
+            /// ).unwrap();
+            /// let events = store.subscribe();
+            ///
+            /// let pfx = Prefix::new("185.49.140.0".parse().unwrap(), 22).unwrap();
+            /// let record = Record::new(1, 10, RouteStatus::Active, PrefixAs(211321));
+            /// store.insert(&pfx, record.clone(), None).unwrap();
+            /// store.insert(&pfx, record, None).unwrap();
+            /// store.mark_mui_as_withdrawn_for_prefix(&pfx, 1).unwrap();
+            ///
+            /// assert!(matches!(
+            ///     events.try_recv().unwrap(),
+            ///     StoreEvent::Inserted { prefix, .. } if prefix == pfx
+            /// ));
+            /// assert!(matches!(
+            ///     events.try_recv().unwrap(),
+            ///     StoreEvent::Replaced { prefix, .. } if prefix == pfx
+            /// ));
+            /// assert!(matches!(
+            ///     events.try_recv().unwrap(),
+            ///     StoreEvent::Withdrawn { prefix, mui: 1 } if prefix == pfx
+            /// ));
+            /// assert!(events.try_recv().is_err());
+            /// ```
+            pub fn subscribe(&self) -> std::sync::mpsc::Receiver<StoreEvent<M>> {
+                let (tx, rx) = std::sync::mpsc::channel();
+                self.subscribers
+                    .write()
+                    .unwrap()
+                    .push((tx, std::sync::atomic::AtomicBool::new(false)));
+                rx
+            }
+
+            // The magic bytes and the version of the format of
+            // `dump_binary`.
+            const BINARY_DUMP_MAGIC: &'static [u8; 4] = b"RTSB";
+            const BINARY_DUMP_VERSION: u8 = 1;
+
+            // Return an AddressFamilyDisabled error if the address family
+            // of `prefix` is disabled in the config.
+            fn check_af(&self, prefix: &Prefix) -> Result<(), PrefixStoreError> {
+                if self.config.accepts_af(prefix) {
+                    Ok(())
+                } else {
+                    Err(PrefixStoreError::AddressFamilyDisabled)
+                }
+            }
+
+            // Send the event to all subscribers, and drop the subscribers
+            // that went away. Sending only takes a read lock on the
+            // subscribers, so concurrent writers don't wait for each other
+            // here. The write lock is only taken to drop subscribers.
+            fn emit(&self, event: StoreEvent<M>) {
+                if !self.config.emit_events {
+                    return;
+                }
+
+                let mut gone = false;
+                for (tx, closed) in self.subscribers.read().unwrap().iter() {
+                    if tx.send(event.clone()).is_err() {
+                        closed.store(true, std::sync::atomic::Ordering::Relaxed);
+                        gone = true;
+                    }
+                }
+
+                if gone {
+                    self.subscribers.write().unwrap().retain(|(_, closed)| {
+                        !closed.load(std::sync::atomic::Ordering::Relaxed)
+                    });
+                }
+            }
         }
 
         impl<'a, M: Meta,
@@ -571,7 +855,9 @@ pub fn create_store(attr: TokenStream, item: TokenStream) -> TokenStream {
             ///         include_withdrawn: false,
             ///         include_less_specifics: false,
             ///         include_more_specifics: false,
-            ///         mui: None
+            ///         mui: None,
+            ///         debug_path: false,
+            ///         stop_at_first_less_specific: false,
             ///     },
             ///     guard
             /// );
@@ -585,7 +871,9 @@ pub fn create_store(attr: TokenStream, item: TokenStream) -> TokenStream {
             ///             include_withdrawn: false,
             ///             include_less_specifics: false,
             ///             include_more_specifics: false,
-            ///             mui: None
+            ///             mui: None,
+            ///             debug_path: false,
+            ///             stop_at_first_less_specific: false,
             ///         },
             ///         guard
             ///     );
@@ -624,6 +912,288 @@ pub fn create_store(attr: TokenStream, item: TokenStream) -> TokenStream {
                 }
             }
 
+            /// Run `match_prefix` on the blocking thread pool of the Tokio
+            /// runtime the returned future is awaited in.
+            ///
+            /// The `Guard` that `match_prefix` needs is not `Send`, so it
+            /// can't be held across an await point. This method pins the
+            /// guard inside the blocking task instead, which is why it takes
+            /// the search prefix and the options by value, and the store as
+            /// an `Arc`. Returns an error if the blocking task panicked.
+            /// Only available with the `tokio` feature.
+            ///
+            /// # Example
+            /// ```
+            /// use std::sync::Arc;
+            ///
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = Arc::new(MultiThreadedStore::<PrefixAs>::new().unwrap());
+            /// let pfx = Prefix::new("185.49.140.0".parse().unwrap(), 22).unwrap();
+            /// store.insert(
+            ///     &pfx,
+            ///     Record::new(0, 0, RouteStatus::Active, PrefixAs(211321)),
+            ///     None
+            /// ).unwrap();
+            ///
+            /// let rt = tokio::runtime::Builder::new_current_thread()
+            ///     .build()
+            ///     .unwrap();
+            /// let res = rt.block_on(store.match_prefix_blocking(
+            ///     pfx,
+            ///     MatchOptions {
+            ///         match_type: MatchType::ExactMatch,
+            ///         include_withdrawn: false,
+            ///         include_less_specifics: false,
+            ///         include_more_specifics: false,
+            ///         mui: None,
+            ///         debug_path: false,
+            ///         stop_at_first_less_specific: false,
+            ///     },
+            /// )).unwrap();
+            ///
+            /// assert_eq!(res.prefix, Some(pfx));
+            /// ```
+            #[cfg(feature = "tokio")]
+            pub async fn match_prefix_blocking(
+                self: &std::sync::Arc<Self>,
+                search_pfx: Prefix,
+                options: MatchOptions,
+            ) -> Result<QueryResult<M>, tokio::task::JoinError>
+            where
+                M: 'static,
+            {
+                let store = std::sync::Arc::clone(self);
+                tokio::task::spawn_blocking(move || {
+                    let guard = &epoch::pin();
+                    store.match_prefix(&search_pfx, &options, guard)
+                }).await
+            }
+
+            /// Return the exact match for `search_pfx`, and its cover,
+            /// i.e. the longest less-specific of `search_pfx` in the store,
+            /// with one query.
+            ///
+            /// This is the same as a `match_prefix` with an `ExactMatch`
+            /// that includes the first less-specific only. The cover is
+            /// also returned if there is no exact match. Withdrawn records
+            /// are left out of both, like `match_prefix` does if
+            /// `include_withdrawn` is not set, so a prefix that only has
+            /// withdrawn records is not an exact match.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let guard = &epoch::pin();
+            /// for pfx in ["185.49.140.0/22", "185.49.140.10/32"] {
+            ///     let record = Record::new(1, 0, RouteStatus::Active, PrefixAs(211321));
+            ///     store.insert(&pfx.parse().unwrap(), record, None).unwrap();
+            /// }
+            ///
+            /// let (exact, cover) = store.exact_and_cover(
+            ///     &"185.49.140.10/32".parse().unwrap(),
+            ///     guard
+            /// );
+            /// assert_eq!(exact.unwrap().prefix, "185.49.140.10/32".parse().unwrap());
+            /// assert_eq!(cover.unwrap().prefix, "185.49.140.0/22".parse().unwrap());
+            ///
+            /// let (exact, cover) = store.exact_and_cover(
+            ///     &"185.49.140.11/32".parse().unwrap(),
+            ///     guard
+            /// );
+            /// assert!(exact.is_none());
+            /// assert_eq!(cover.unwrap().prefix, "185.49.140.0/22".parse().unwrap());
+            /// ```
+            pub fn exact_and_cover(
+                &'a self,
+                search_pfx: &Prefix,
+                guard: &'a Guard,
+            ) -> (Option<PrefixRecord<M>>, Option<PrefixRecord<M>>) {
+                let res = self.match_prefix(
+                    search_pfx,
+                    &MatchOptions {
+                        match_type: MatchType::ExactMatch,
+                        include_withdrawn: false,
+                        include_less_specifics: true,
+                        include_more_specifics: false,
+                        mui: None,
+                        debug_path: false,
+                        stop_at_first_less_specific: true,
+                    },
+                    guard
+                );
+
+                // A prefix that only has withdrawn records is still found,
+                // but with an empty meta, and it isn't an exact match then.
+                let exact = match res.prefix {
+                    Some(p) if res.match_type == MatchType::ExactMatch
+                        && !res.prefix_meta.is_empty() =>
+                    {
+                        Some(PrefixRecord::new(p, res.prefix_meta))
+                    }
+                    _ => None,
+                };
+
+                (
+                    exact,
+                    res.less_specifics.and_then(|ls| ls.iter().next()),
+                )
+            }
+
+            /// Search for a prefix that is already in the form of a
+            /// `PrefixId<IPv4>`, skipping the conversion from a `Prefix`
+            /// that `match_prefix` performs on every call.
+            ///
+            /// Apart from that this behaves exactly like `match_prefix`.
+            ///
+            /// # Example
+            /// ```
+            /// use std::net::Ipv4Addr;
+            ///
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let guard = &epoch::pin();
+            ///
+            /// let pfx = Prefix::new("185.49.140.0".parse().unwrap(), 22).unwrap();
+            /// store.insert(
+            ///     &pfx,
+            ///     Record::new(0, 0, RouteStatus::Active, PrefixAs(211321)),
+            ///     None
+            /// );
+            ///
+            /// let id = PrefixId::<IPv4>::new(
+            ///     u32::from("185.49.140.0".parse::<Ipv4Addr>().unwrap()),
+            ///     22
+            /// );
+            /// let res = store.match_prefix_id_v4(
+            ///     id,
+            ///     &MatchOptions {
+            ///         match_type: MatchType::ExactMatch,
+            ///         include_withdrawn: false,
+            ///         include_less_specifics: false,
+            ///         include_more_specifics: false,
+            ///         mui: None,
+            ///         debug_path: false,
+            ///         stop_at_first_less_specific: false,
+            ///     },
+            ///     guard
+            /// );
+            ///
+            /// assert_eq!(res.prefix, Some(pfx));
+            /// ```
+            pub fn match_prefix_id_v4(
+                &'a self,
+                search_pfx: PrefixId<IPv4>,
+                options: &MatchOptions,
+                guard: &'a Guard,
+            ) -> QueryResult<M> {
+                self.v4.match_prefix_by_store_direct(
+                    search_pfx,
+                    options,
+                    options.mui,
+                    guard
+                )
+            }
+
+            /// Search for a prefix that is already in the form of a
+            /// `PrefixId<IPv6>`, skipping the conversion from a `Prefix`
+            /// that `match_prefix` performs on every call.
+            ///
+            /// Apart from that this behaves exactly like `match_prefix`.
+            pub fn match_prefix_id_v6(
+                &'a self,
+                search_pfx: PrefixId<IPv6>,
+                options: &MatchOptions,
+                guard: &'a Guard,
+            ) -> QueryResult<M> {
+                self.v6.match_prefix_by_store_direct(
+                    search_pfx,
+                    options,
+                    options.mui,
+                    guard
+                )
+            }
+
+            /// Estimate the size of the result of a `match_prefix` query
+            /// for `search_pfx` with `options`, without running it.
+            ///
+            /// The estimate is calculated from the number of prefixes for
+            /// each prefix length in the store only, so it is cheap. It can
+            /// be used to reject or queue queries that would return a huge
+            /// number of prefixes. See [QueryCostEstimate] for how the
+            /// numbers are approximated.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// for pfx in [
+            ///     "0.0.0.0/0",
+            ///     "185.49.140.0/22",
+            ///     "185.49.140.0/24",
+            ///     "185.49.141.0/24",
+            /// ] {
+            ///     let record = Record::new(1, 0, RouteStatus::Active, PrefixAs(211321));
+            ///     store.insert(&pfx.parse().unwrap(), record, None).unwrap();
+            /// }
+            ///
+            /// let options = MatchOptions {
+            ///     match_type: MatchType::ExactMatch,
+            ///     include_withdrawn: false,
+            ///     include_less_specifics: true,
+            ///     include_more_specifics: true,
+            ///     mui: None,
+            ///     debug_path: false,
+            ///     stop_at_first_less_specific: false,
+            /// };
+            ///
+            /// let estimate = store.estimate_query_cost(
+            ///     &"0.0.0.0/0".parse().unwrap(),
+            ///     &options
+            /// );
+            /// assert_eq!(estimate.less_specifics, 0);
+            /// assert_eq!(estimate.more_specifics, 3);
+            ///
+            /// let estimate = store.estimate_query_cost(
+            ///     &"185.49.140.0/22".parse().unwrap(),
+            ///     &options
+            /// );
+            /// assert_eq!(estimate.less_specifics, 1);
+            /// assert!(estimate.more_specifics >= 1);
+            /// ```
+            pub fn estimate_query_cost(
+                &self,
+                search_pfx: &Prefix,
+                options: &MatchOptions,
+            ) -> QueryCostEstimate {
+                match search_pfx.addr() {
+                    std::net::IpAddr::V4(_) => {
+                        self.v4.store.estimate_query_cost(
+                            PrefixId::<IPv4>::from(*search_pfx),
+                            options,
+                        )
+                    }
+                    std::net::IpAddr::V6(_) => {
+                        self.v6.store.estimate_query_cost(
+                            PrefixId::<IPv6>::from(*search_pfx),
+                            options,
+                        )
+                    }
+                }
+            }
+
             /// Return the record that belongs to the pre-calculated and
             /// stored best path for a given prefix.
             ///
@@ -676,6 +1246,24 @@ pub fn create_store(attr: TokenStream, item: TokenStream) -> TokenStream {
                 }
             }
 
+            /// Return the records that belong to the pre-calculated and
+            /// stored best paths for all the given prefixes, in the same
+            /// order as `prefixes`.
+            ///
+            /// Each element of the result is what `best_path` returns for
+            /// the prefix at the same position. All the lookups share the
+            /// one `guard` of the caller, which saves pinning the epoch for
+            /// every prefix when rendering a whole forwarding table.
+            pub fn best_paths(&'a self,
+                prefixes: &[Prefix],
+                guard: &Guard
+            ) -> Vec<Option<Result<Record<M>, PrefixStoreError>>> {
+                prefixes
+                    .iter()
+                    .map(|pfx| self.best_path(pfx, guard))
+                    .collect()
+            }
+
             /// Calculate and store the best path for the specified Prefix.
             ///
             /// If the result of the calculation is successful it will be
@@ -724,54 +1312,325 @@ pub fn create_store(attr: TokenStream, item: TokenStream) -> TokenStream {
                 }
             }
 
-            pub fn is_ps_outdated(
+            /// Calculate and store the best and backup path for the
+            /// specified prefix, like
+            /// `calculate_and_store_best_and_backup_path`, but select them
+            /// with a [BestPathPolicy], instead of the ordering the `Meta`
+            /// of the records provides. Records that the policy considers
+            /// equal are ordered by their multi_uniq_id, lowest first.
+            ///
+            /// Returns the multi_uniq_id of the best and the backup path,
+            /// or a `PrefixStoreError::PrefixNotFound` error if the prefix
+            /// does not exist in the store.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let guard = &epoch::pin();
+            /// let pfx = Prefix::new("185.49.140.0".parse().unwrap(), 22).unwrap();
+            ///
+            /// for (mui, asn) in [(1, 65003), (2, 65001), (3, 65002)] {
+            ///     let record = Record::new(mui, 0, RouteStatus::Active, PrefixAs(asn));
+            ///     store.insert(&pfx, record, None).unwrap();
+            /// }
+            ///
+            /// // Prefer the lowest AS number.
+            /// let lowest_asn = |a: &Record<PrefixAs>, b: &Record<PrefixAs>| {
+            ///     a.meta.0.cmp(&b.meta.0)
+            /// };
+            ///
+            /// assert_eq!(
+            ///     store.calculate_best_path_with(&pfx, &lowest_asn, guard),
+            ///     Ok((Some(2), Some(3)))
+            /// );
+            /// assert_eq!(
+            ///     store.best_path(&pfx, guard).unwrap().unwrap().multi_uniq_id,
+            ///     2
+            /// );
+            /// ```
+            pub fn calculate_best_path_with(
                 &self,
                 search_pfx: &Prefix,
+                policy: &dyn BestPathPolicy<M>,
                 guard: &Guard
-            ) -> Result<bool, PrefixStoreError> {
+            ) -> Result<(Option<u32>, Option<u32>), PrefixStoreError> {
                 match search_pfx.addr() {
-                    std::net::IpAddr::V4(addr) => self.v4.store
+                    std::net::IpAddr::V4(_) => self.v4.store
                         .non_recursive_retrieve_prefix(
-                            PrefixId::<IPv4>::new(
-                                addr.into(),
-                                search_pfx.len(),
-                            ),
-                            // guard
+                            PrefixId::<IPv4>::from(*search_pfx),
                         ).0.map_or(
-                            Err(PrefixStoreError::StoreNotReadyError),
-                            |p| Ok(p.is_ps_outdated(guard))
+                            Err(PrefixStoreError::PrefixNotFound),
+                            |p_rec| p_rec.calculate_and_store_best_backup_with(
+                                policy, guard),
                         ),
-                    std::net::IpAddr::V6(addr) => self.v6.store
+                    std::net::IpAddr::V6(_) => self.v6.store
                         .non_recursive_retrieve_prefix(
-                            PrefixId::<IPv6>::new(
-                                addr.into(),
-                                search_pfx.len(),
-                            ),
-                            // guard
+                            PrefixId::<IPv6>::from(*search_pfx),
                         ).0.map_or(
-                            Err(PrefixStoreError::StoreNotReadyError),
-                            |p| Ok(p.is_ps_outdated(guard))
-                        )
+                            Err(PrefixStoreError::PrefixNotFound),
+                            |p_rec| p_rec.calculate_and_store_best_backup_with(
+                                policy, guard),
+                        ),
                 }
             }
 
-            /// Return a [QueryResult] that contains all the more-specific
-            /// prefixes of the `search_pfx` in the store, including the
-            /// meta-data of these prefixes.
+            /// Calculate and store the multi_uniq_ids of all the records
+            /// of the specified prefix that are tied for the best path,
+            /// for equal-cost multi-path (ECMP) forwarding.
             ///
-            /// The `search_pfx` argument can be either a IPv4 or an IPv6
-            /// prefix. The `search_pfx` itself doesn't have to be present
-            /// in the store for an iterator to be non-empty, i.e. if
-            /// more-specific prefixes exist for a non-existent
-            /// `search_pfx` the iterator will yield these more-specific
-            /// prefixes.
+            /// The records are ordered like
+            /// `calculate_and_store_best_and_backup_path` orders them, but
+            /// instead of picking the record with the lowest
+            /// multi_uniq_id out of the records that are tied for the best
+            /// path, all of them are returned, in ascending order. They
+            /// are stored besides the best and backup path, which are left
+            /// alone, and can be retrieved with `ecmp_paths`. Like the best
+            /// and backup path, they are not updated by inserts.
             ///
-            /// The `guard` should be a `&epoch::pin()`. It allows the
-            /// QuerySet to contain references to the meta-data objects,
-            /// instead of cloning them into it.
-            pub fn more_specifics_from(&'a self,
-                search_pfx: &Prefix,
-                mui: Option<u32>,
+            /// Returns a `PrefixStoreError::PrefixNotFound` error if the
+            /// prefix does not exist in the store.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let guard = &epoch::pin();
+            /// let pfx = Prefix::new("185.49.140.0".parse().unwrap(), 22).unwrap();
+            ///
+            /// for (mui, asn) in [(1, 65002), (2, 65001), (3, 65001)] {
+            ///     let record = Record::new(mui, 0, RouteStatus::Active, PrefixAs(asn));
+            ///     store.insert(&pfx, record, None).unwrap();
+            /// }
+            ///
+            /// assert!(store.ecmp_paths(&pfx, guard).is_none());
+            /// assert_eq!(store.calculate_and_store_ecmp(&pfx, &(), guard), Ok(vec![2, 3]));
+            ///
+            /// let paths = store.ecmp_paths(&pfx, guard).unwrap();
+            /// assert_eq!(
+            ///     paths.iter().map(|r| r.multi_uniq_id).collect::<Vec<_>>(),
+            ///     vec![2, 3]
+            /// );
+            /// ```
+            pub fn calculate_and_store_ecmp(
+                &self,
+                search_pfx: &Prefix,
+                tbi: &<M as Meta>::TBI,
+                guard: &Guard
+            ) -> Result<Vec<u32>, PrefixStoreError> {
+                match search_pfx.addr() {
+                    std::net::IpAddr::V4(_) => self.v4.store
+                        .non_recursive_retrieve_prefix(
+                            PrefixId::<IPv4>::from(*search_pfx),
+                        ).0.map_or(
+                            Err(PrefixStoreError::PrefixNotFound),
+                            |p_rec| Ok(p_rec.calculate_and_store_ecmp(
+                                tbi, guard)),
+                        ),
+                    std::net::IpAddr::V6(_) => self.v6.store
+                        .non_recursive_retrieve_prefix(
+                            PrefixId::<IPv6>::from(*search_pfx),
+                        ).0.map_or(
+                            Err(PrefixStoreError::PrefixNotFound),
+                            |p_rec| Ok(p_rec.calculate_and_store_ecmp(
+                                tbi, guard)),
+                        ),
+                }
+            }
+
+            /// Return the records of the specified prefix that were tied
+            /// for the best path the last time `calculate_and_store_ecmp`
+            /// was called for it, in ascending order of their
+            /// multi_uniq_id.
+            ///
+            /// Records that are no longer active are left out. Returns
+            /// `None` if the prefix does not exist in the store, or if
+            /// `calculate_and_store_ecmp` was never called for it.
+            pub fn ecmp_paths(
+                &'a self,
+                search_pfx: &Prefix,
+                guard: &Guard
+            ) -> Option<Vec<Record<M>>> {
+                match search_pfx.addr() {
+                    std::net::IpAddr::V4(_) => self.v4.store
+                        .non_recursive_retrieve_prefix(
+                            PrefixId::<IPv4>::from(*search_pfx),
+                        ).0.and_then(|p_rec| p_rec
+                            .get_ecmp_muis(guard)
+                            .map(|muis| muis.into_iter().filter_map(|mui|
+                                p_rec.record_map.get_record_for_active_mui(mui)
+                            ).collect())
+                        ),
+                    std::net::IpAddr::V6(_) => self.v6.store
+                        .non_recursive_retrieve_prefix(
+                            PrefixId::<IPv6>::from(*search_pfx),
+                        ).0.and_then(|p_rec| p_rec
+                            .get_ecmp_muis(guard)
+                            .map(|muis| muis.into_iter().filter_map(|mui|
+                                p_rec.record_map.get_record_for_active_mui(mui)
+                            ).collect())
+                        ),
+                }
+            }
+
+            pub fn is_ps_outdated(
+                &self,
+                search_pfx: &Prefix,
+                guard: &Guard
+            ) -> Result<bool, PrefixStoreError> {
+                match search_pfx.addr() {
+                    std::net::IpAddr::V4(addr) => self.v4.store
+                        .non_recursive_retrieve_prefix(
+                            PrefixId::<IPv4>::new(
+                                addr.into(),
+                                search_pfx.len(),
+                            ),
+                            // guard
+                        ).0.map_or(
+                            Err(PrefixStoreError::StoreNotReadyError),
+                            |p| Ok(p.is_ps_outdated(guard))
+                        ),
+                    std::net::IpAddr::V6(addr) => self.v6.store
+                        .non_recursive_retrieve_prefix(
+                            PrefixId::<IPv6>::new(
+                                addr.into(),
+                                search_pfx.len(),
+                            ),
+                            // guard
+                        ).0.map_or(
+                            Err(PrefixStoreError::StoreNotReadyError),
+                            |p| Ok(p.is_ps_outdated(guard))
+                        )
+                }
+            }
+
+            /// Returns an unordered iterator over all the prefixes, for
+            /// both IPv4 and IPv6, whose stored best and backup path are
+            /// outdated, i.e. the records for the prefix have changed
+            /// since the last time the path selection was calculated.
+            ///
+            /// Prefixes for which the path selection was never calculated
+            /// are only included if records were added to them after their
+            /// creation, or if the store is configured with
+            /// [AutoBestPath::Deferred].
+            ///
+            /// The yielded prefixes can be fed into
+            /// `calculate_and_store_best_and_backup_path`.
+            pub fn outdated_best_paths(
+                &'a self,
+                guard: &'a Guard
+            ) -> impl Iterator<Item=Prefix> + 'a {
+                self.v4.store.outdated_prefixes_iter(guard)
+                    .chain(self.v6.store.outdated_prefixes_iter(guard))
+            }
+
+            /// Calculate and store the best and backup path for all the
+            /// prefixes, for both IPv4 and IPv6, whose stored best and
+            /// backup path are outdated, i.e. the prefixes that
+            /// `outdated_best_paths` returns.
+            ///
+            /// This is meant to be called after a bulk load into a store
+            /// configured with [AutoBestPath::Deferred], that leaves the
+            /// best and backup paths of all the prefixes inserted into
+            /// outdated. Note that this visits all the prefixes in the
+            /// store.
+            ///
+            /// Returns the number of prefixes that were updated. An Error
+            /// result indicates an inconsistency in the store.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new_with_config(
+            ///     StoreConfig {
+            ///         auto_best_path: AutoBestPath::Deferred,
+            ///         ..Default::default()
+            ///     }
+            /// ).unwrap();
+            /// let guard = &epoch::pin();
+            ///
+            /// for pfx in ["185.49.140.0/22", "2a04:b900::/29"] {
+            ///     let record = Record::new(1, 0, RouteStatus::Active, PrefixAs(211321));
+            ///     store.insert(&pfx.parse().unwrap(), record, Some(())).unwrap();
+            /// }
+            /// assert_eq!(store.outdated_best_paths(guard).count(), 2);
+            ///
+            /// assert_eq!(store.recalculate_outdated_best_paths(&(), guard).unwrap(), 2);
+            /// assert_eq!(store.outdated_best_paths(guard).count(), 0);
+            /// ```
+            pub fn recalculate_outdated_best_paths(
+                &'a self,
+                tbi: &<M as Meta>::TBI,
+                guard: &'a Guard
+            ) -> Result<usize, PrefixStoreError> {
+                Ok(
+                    self.v4.store.recalculate_outdated_best_paths(
+                        tbi, guard
+                    )? +
+                    self.v6.store.recalculate_outdated_best_paths(
+                        tbi, guard
+                    )?
+                )
+            }
+
+            /// Calculate and store the best and backup path for all the
+            /// prefixes that have a record for the specified
+            /// `multi_uniq_id`, for both IPv4 and IPv6.
+            ///
+            /// The prefixes are looked up through the `multi_uniq_id`
+            /// indexes on the nodes in the tree, so this is a lot cheaper
+            /// than recalculating all the prefixes in the store. Records
+            /// for this mui with a Withdrawn status are included, since
+            /// their prefixes may need a new best path as well.
+            ///
+            /// Returns the number of prefixes that were updated. An Error
+            /// result indicates an inconsistency in the store.
+            pub fn recalculate_best_paths_for_mui(
+                &'a self,
+                mui: u32,
+                tbi: &<M as Meta>::TBI,
+                guard: &'a Guard
+            ) -> Result<usize, PrefixStoreError> {
+                Ok(
+                    self.v4.store.recalculate_best_paths_for_mui(
+                        mui, tbi, guard
+                    )? +
+                    self.v6.store.recalculate_best_paths_for_mui(
+                        mui, tbi, guard
+                    )?
+                )
+            }
+
+            /// Return a [QueryResult] that contains all the more-specific
+            /// prefixes of the `search_pfx` in the store, including the
+            /// meta-data of these prefixes.
+            ///
+            /// The `search_pfx` argument can be either a IPv4 or an IPv6
+            /// prefix. The `search_pfx` itself doesn't have to be present
+            /// in the store for an iterator to be non-empty, i.e. if
+            /// more-specific prefixes exist for a non-existent
+            /// `search_pfx` the iterator will yield these more-specific
+            /// prefixes.
+            ///
+            /// The `guard` should be a `&epoch::pin()`. It allows the
+            /// QuerySet to contain references to the meta-data objects,
+            /// instead of cloning them into it.
+            ///
+            /// To visit the more-specific prefixes one at a time, without
+            /// collecting them into a [QueryResult], use
+            /// `more_specifics_iter_from`.
+            pub fn more_specifics_from(&'a self,
+                search_pfx: &Prefix,
+                mui: Option<u32>,
                 include_withdrawn: bool,
                 guard: &'a Guard,
             ) -> QueryResult<M> {
@@ -930,6 +1789,55 @@ pub fn create_store(attr: TokenStream, item: TokenStream) -> TokenStream {
                     left.into_iter().flatten().chain(right.into_iter().flatten())
                 }
 
+            /// Returns an iterator over all the less-specific prefixes
+            /// of the `search_prefix`, like `less_specifics_iter_from`,
+            /// together with their length.
+            ///
+            /// Unlike `less_specifics_iter_from`, the order of this iterator
+            /// is guaranteed: the less-specifics are returned from the
+            /// longest to the shortest, so the first one is the immediate
+            /// cover of the `search_pfx`. The default route (0/0) is never
+            /// returned.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            /// use rotonda_store::prelude::multi::*;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let guard = epoch::pin();
+            ///
+            /// for pfx in ["185.49.128.0/20", "185.49.140.0/22", "185.0.0.0/8"] {
+            ///     let record = Record::new(0, 0, RouteStatus::Active, PrefixAs(211321));
+            ///     store.insert(&pfx.parse().unwrap(), record, None).unwrap();
+            /// }
+            ///
+            /// let lens = store.less_specifics_detailed_iter_from(
+            ///     &"185.49.140.0/24".parse().unwrap(),
+            ///     None,
+            ///     false,
+            ///     &guard
+            /// ).map(|(_, len)| len).collect::<Vec<_>>();
+            /// assert_eq!(lens, vec![22, 20, 8]);
+            /// ```
+            pub fn less_specifics_detailed_iter_from(&'a self,
+                search_pfx: &Prefix,
+                mui: Option<u32>,
+                include_withdrawn: bool,
+                guard: &'a Guard,
+            ) -> impl Iterator<Item=(PrefixRecord<M>, u8)> + 'a {
+                self.less_specifics_iter_from(
+                    search_pfx,
+                    mui,
+                    include_withdrawn,
+                    guard
+                ).map(|p| {
+                    let len = p.prefix.len();
+                    (p, len)
+                })
+            }
+
             /// Returns an iterator over all the more-specifics prefixes
             /// of the `search_prefix`, if present in the store, including
             /// the meta-data of these prefixes.
@@ -984,11 +1892,7 @@ pub fn create_store(attr: TokenStream, item: TokenStream) -> TokenStream {
 
                 let (left, right) = match search_pfx.addr() {
                     std::net::IpAddr::V4(addr) => {
-                        let bmin = unsafe {
-                            self.v4.store.withdrawn_muis_bmin.load(
-                                Ordering::Acquire, guard
-                            ).deref()
-                        };
+                        let bmin = self.v4.store.withdrawn_muis_snapshot(guard);
                         if mui.is_some() && bmin.contains(mui.unwrap()) {
                                 (None, None)
                             } else {
@@ -1008,11 +1912,7 @@ pub fn create_store(attr: TokenStream, item: TokenStream) -> TokenStream {
                             }
                         }
                     std::net::IpAddr::V6(addr) => {
-                        let bmin = unsafe {
-                            self.v6.store.withdrawn_muis_bmin.load(
-                                Ordering::Acquire, guard
-                            ).deref()
-                        };
+                        let bmin = self.v6.store.withdrawn_muis_snapshot(guard);
                         if mui.is_some() && bmin.contains(mui.unwrap()) {
                             (None, None)
                         } else {
@@ -1035,113 +1935,15 @@ pub fn create_store(attr: TokenStream, item: TokenStream) -> TokenStream {
                 left.into_iter().flatten().chain(right.into_iter().flatten())
             }
 
-            pub fn iter_records_for_mui_v4(
-                &'a self,
-                mui: u32,
-                include_withdrawn: bool,
-                guard: &'a Guard
-            ) -> impl Iterator<Item=PrefixRecord<M>> +'a {
-
-                let bmin = unsafe {
-                    self.v4.store.withdrawn_muis_bmin.load(
-                        Ordering::Acquire, guard
-                    ).deref()
-                };
-
-                if bmin.contains(mui) && !include_withdrawn {
-                    None
-                } else {
-                    Some(
-                        self.v4.store.more_specific_prefix_iter_from(
-                                PrefixId::<IPv4>::new(
-                                    0,
-                                    0,
-                                ),
-                                Some(mui),
-                                include_withdrawn,
-                                guard
-                            ).map(|p| PrefixRecord::from(p))
-                    )
-                }.into_iter().flatten()
-            }
-
-            pub fn iter_records_for_mui_v6(
-                &'a self,
-                mui: u32,
-                include_withdrawn: bool,
-                guard: &'a Guard
-            ) -> impl Iterator<Item=PrefixRecord<M>> +'a {
-
-                let bmin = unsafe {
-                    self.v4.store.withdrawn_muis_bmin.load(
-                        Ordering::Acquire, guard
-                    ).deref()
-                };
-
-                if bmin.contains(mui) && !include_withdrawn {
-                    None
-                } else {
-                    Some(
-                        self.v6.store.more_specific_prefix_iter_from(
-                                PrefixId::<IPv6>::new(
-                                    0,
-                                    0,
-                                ),
-                                Some(mui),
-                                include_withdrawn,
-                                guard
-                            ).map(|p| PrefixRecord::from(p))
-                    )
-                }.into_iter().flatten()
-            }
-
-            /// Insert or replace a Record into the Store
-            ///
-            /// The specified Record will replace an existing record in the
-            /// store if the multi-map for the specified prefix already has an
-            /// entry for the `multi_uniq_id`, otherwise it will be added to
-            /// the multi-map.
-            ///
-            /// If the `update_path_sections` argument is used the best path
-            /// selection will be run on the resulting multi-map after insert
-            /// and stored for the specified prefix.
-            ///
-            /// Returns some metrics about the resulting insert.
-            pub fn insert(
-                &self,
-                prefix: &Prefix,
-                record: Record<M>,
-                update_path_selections: Option<M::TBI>
-            ) -> Result<UpsertReport, PrefixStoreError> {
-                match prefix.addr() {
-                    std::net::IpAddr::V4(addr) => {
-                        self.v4.insert(
-                            PrefixId::<IPv4>::from(*prefix),
-                            record,
-                            update_path_selections,
-                        )
-                    }
-                    std::net::IpAddr::V6(addr) => {
-                        self.v6.insert(
-                            PrefixId::<IPv6>::from(*prefix),
-                            record,
-                            update_path_selections,
-                        )
-                    }
-                }
-            }
-
-            /// Returns an unordered iterator over all prefixes, with any
-            /// status (including Withdrawn), for both IPv4 and IPv6,
-            /// currently in the store, including meta-data.
-            ///
-            /// Although the iterator is unordered within an address-family,
-            /// it first iterates over all IPv4 addresses and then over all
-            /// IPv6 addresses.
+            /// Returns an iterator over the more-specific prefixes of the
+            /// `search_pfx`, with a prefix length up to and including
+            /// `max_len`, including the meta-data of these prefixes.
             ///
-            /// The `guard` should be a `&epoch::pin()`. It allows the
-            /// iterator to create and return references to the meta-data
-            /// objects to the caller (instead of cloning them).
+            /// This behaves like `more_specifics_iter_from`, but the
+            /// traversal of the tree bitmap stops at `max_len`, so the
+            /// parts of the tree that only host longer prefixes are never
+            /// visited. If `max_len` is not greater than the length of
+            /// `search_pfx` the iterator is empty.
             ///
             /// # Example
             /// ```
@@ -1159,40 +1961,1352 @@ pub fn create_store(attr: TokenStream, item: TokenStream) -> TokenStream {
             ///         .into();
             /// let our_asn = Record::new(0, 0, RouteStatus::Active, PrefixAs(211321));
             ///
-            /// store.insert(&Prefix::new(pfx_addr, 22).unwrap(), our_asn.clone(), None);
-            /// store.insert(&Prefix::new(pfx_addr, 23).unwrap(), our_asn.clone(), None);
             /// store.insert(&Prefix::new(pfx_addr, 24).unwrap(), our_asn.clone(), None);
-            /// store.insert(&Prefix::new(pfx_addr, 25).unwrap(), our_asn, None);
+            /// store.insert(&Prefix::new(pfx_addr, 32).unwrap(), our_asn, None);
             ///
-            /// let mut iter = store.prefixes_iter();
+            /// let more_specifics = store.more_specifics_within(
+            ///     &Prefix::new(pfx_addr, 23).unwrap(),
+            ///     24,
+            ///     None,
+            ///     false,
+            ///     &guard
+            /// ).collect::<Vec<_>>();
             ///
-            /// assert_eq!(iter.next().unwrap().prefix,
-            ///     Prefix::new(pfx_addr, 22).unwrap());
-            /// assert_eq!(iter.next().unwrap().prefix,
-            ///     Prefix::new(pfx_addr, 23).unwrap());
-            /// assert_eq!(iter.next().unwrap().prefix,
-            ///     Prefix::new(pfx_addr, 24).unwrap());
-            /// assert_eq!(iter.next().unwrap().prefix,
-            ///     Prefix::new(pfx_addr, 25).unwrap());
+            /// assert_eq!(more_specifics.len(), 1);
+            /// assert_eq!(more_specifics[0].prefix, Prefix::new(pfx_addr, 24).unwrap());
             /// ```
-            pub fn prefixes_iter(
+            pub fn more_specifics_within(&'a self,
+                search_pfx: &Prefix,
+                max_len: u8,
+                mui: Option<u32>,
+                include_withdrawn: bool,
+                guard: &'a Guard,
+            ) -> impl Iterator<Item=PrefixRecord<M>> + 'a {
+
+                let (left, right) = match search_pfx.addr() {
+                    std::net::IpAddr::V4(addr) => {
+                        let bmin = self.v4.store.withdrawn_muis_snapshot(guard);
+                        if mui.is_some() && bmin.contains(mui.unwrap()) {
+                            (None, None)
+                        } else {
+                            (
+                                Some(self.v4.store.more_specific_prefix_iter_with_max_len(
+                                        PrefixId::<IPv4>::new(
+                                            addr.into(),
+                                            search_pfx.len(),
+                                        ),
+                                        Some(max_len),
+                                        mui,
+                                        include_withdrawn,
+                                        guard
+                                    ).map(|p| PrefixRecord::from(p))
+                                ),
+                                None
+                            )
+                        }
+                    }
+                    std::net::IpAddr::V6(addr) => {
+                        let bmin = self.v6.store.withdrawn_muis_snapshot(guard);
+                        if mui.is_some() && bmin.contains(mui.unwrap()) {
+                            (None, None)
+                        } else {
+                            (
+                                None,
+                                Some(self.v6.store.more_specific_prefix_iter_with_max_len(
+                                        PrefixId::<IPv6>::new(
+                                            addr.into(),
+                                            search_pfx.len(),
+                                        ),
+                                        Some(max_len),
+                                        mui,
+                                        include_withdrawn,
+                                        guard
+                                    ).map(|p| PrefixRecord::from(p))
+                                )
+                            )
+                        }
+                    }
+                };
+                left.into_iter().flatten().chain(right.into_iter().flatten())
+            }
+
+            /// Returns the number of more-specific prefixes of `search_pfx`
+            /// in the store, without retrieving their records.
+            ///
+            /// This is a lot cheaper than counting the results of
+            /// `more_specifics_iter_from`. If `mui` is specified, only the
+            /// prefixes that have a record for this `multi_uniq_id` are
+            /// counted, and the parts of the tree that don't carry it are
+            /// not visited. Note that the status of the records is not
+            /// taken into account, so prefixes with only withdrawn records
+            /// are counted too.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let guard = &epoch::pin();
+            ///
+            /// for (pfx, mui) in [
+            ///     ("185.49.140.0/22", 1),
+            ///     ("185.49.140.0/24", 1),
+            ///     ("185.49.141.0/24", 2),
+            ///     ("185.49.142.128/25", 1),
+            /// ] {
+            ///     let record = Record::new(mui, 0, RouteStatus::Active, PrefixAs(211321));
+            ///     store.insert(&pfx.parse().unwrap(), record, None).unwrap();
+            /// }
+            ///
+            /// let pfx = "185.49.140.0/22".parse().unwrap();
+            /// assert_eq!(store.count_more_specifics(&pfx, None, guard), 3);
+            /// assert_eq!(store.count_more_specifics(&pfx, Some(1), guard), 2);
+            /// assert_eq!(store.count_more_specifics(&pfx, Some(3), guard), 0);
+            /// ```
+            pub fn count_more_specifics(
+                &'a self,
+                search_pfx: &Prefix,
+                mui: Option<u32>,
+                guard: &'a Guard,
+            ) -> usize {
+                match search_pfx.addr() {
+                    std::net::IpAddr::V4(_) => {
+                        self.v4.store.more_specifics_count(
+                            PrefixId::<IPv4>::from(*search_pfx),
+                            mui,
+                            guard
+                        )
+                    }
+                    std::net::IpAddr::V6(_) => {
+                        self.v6.store.more_specifics_count(
+                            PrefixId::<IPv6>::from(*search_pfx),
+                            mui,
+                            guard
+                        )
+                    }
+                }
+            }
+
+            /// Returns an iterator over all the prefixes in the store that
+            /// contain `addr`, from the most specific to the least specific,
+            /// including the host prefix (/32 or /128) for `addr` itself, if
+            /// it is in the store.
+            ///
+            /// Only prefixes with active records, that are not globally
+            /// withdrawn, are returned. The first prefix returned is the
+            /// longest matching prefix for `addr`.
+            ///
+            /// # Example
+            /// ```
+            /// use std::net::Ipv4Addr;
+            ///
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let guard = &epoch::pin();
+            ///
+            /// let addr = Ipv4Addr::new(185, 49, 140, 10);
+            /// let record = Record::new(1, 0, RouteStatus::Active, PrefixAs(211321));
+            /// for len in [16, 22, 24, 32] {
+            ///     store.insert(
+            ///         &Prefix::new_relaxed(addr.into(), len).unwrap(),
+            ///         record.clone(),
+            ///         None
+            ///     ).unwrap();
+            /// }
+            /// // Does not contain the address.
+            /// store.insert(
+            ///     &Prefix::new(Ipv4Addr::new(185, 49, 141, 0).into(), 24).unwrap(),
+            ///     record,
+            ///     None
+            /// ).unwrap();
+            ///
+            /// let lens = store.covering_prefixes(addr.into(), guard)
+            ///     .map(|p| p.prefix.len())
+            ///     .collect::<Vec<_>>();
+            ///
+            /// assert_eq!(lens, vec![32, 24, 22, 16]);
+            /// ```
+            pub fn covering_prefixes(
+                &'a self,
+                addr: std::net::IpAddr,
+                guard: &'a Guard,
+            ) -> impl Iterator<Item=PrefixRecord<M>> + 'a {
+                let (left, right) = match addr {
+                    std::net::IpAddr::V4(_) => (
+                        Some(self.v4.store.covering_prefix_iter(
+                            <IPv4 as AddressFamily>::from_ipaddr(addr),
+                            guard
+                        ).map(|p| PrefixRecord::from(p))),
+                        None
+                    ),
+                    std::net::IpAddr::V6(_) => (
+                        None,
+                        Some(self.v6.store.covering_prefix_iter(
+                            <IPv6 as AddressFamily>::from_ipaddr(addr),
+                            guard
+                        ).map(|p| PrefixRecord::from(p)))
+                    ),
+                };
+                left.into_iter().flatten().chain(right.into_iter().flatten())
+            }
+
+            /// Validate the origin AS `asn` of a route for `prefix` against
+            /// the records in the store, as described in RFC 6811.
+            ///
+            /// All the active records, whose `multi_uniq_id` isn't globally
+            /// withdrawn, for `prefix` and its less-specifics are covering
+            /// records. If there are none, the route is `NotFound`. It is
+            /// `Valid` if at least one of them has `asn` as its origin and a
+            /// maximum length of at least the length of `prefix`, and
+            /// `Invalid` otherwise. A record for AS0 never matches a route.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::Vrp;
+            ///
+            /// let store = MultiThreadedStore::<Vrp>::new().unwrap();
+            /// let guard = &epoch::pin();
+            ///
+            /// let roa: Prefix = "185.49.140.0/22".parse().unwrap();
+            /// let vrp = Vrp { asn: 211321, max_len: 23 };
+            /// store.insert(&roa, Record::new(1, 0, RouteStatus::Active, vrp), None).unwrap();
+            ///
+            /// let pfx: Prefix = "185.49.140.0/23".parse().unwrap();
+            /// assert_eq!(store.validate_origin(&pfx, 211321, guard), RpkiState::Valid);
+            /// // Another origin AS.
+            /// assert_eq!(store.validate_origin(&pfx, 65000, guard), RpkiState::Invalid);
+            ///
+            /// // Too specific.
+            /// let pfx: Prefix = "185.49.140.0/24".parse().unwrap();
+            /// assert_eq!(store.validate_origin(&pfx, 211321, guard), RpkiState::Invalid);
+            ///
+            /// // Not covered.
+            /// let pfx: Prefix = "185.49.0.0/16".parse().unwrap();
+            /// assert_eq!(store.validate_origin(&pfx, 211321, guard), RpkiState::NotFound);
+            /// ```
+            pub fn validate_origin(
+                &'a self,
+                prefix: &Prefix,
+                asn: u32,
+                guard: &'a Guard,
+            ) -> RpkiState
+            where
+                M: MaxLenOrigin,
+            {
+                let mut state = RpkiState::NotFound;
+
+                // The prefixes that contain the network address of `prefix`
+                // and are not longer than it, are the ones covering it.
+                for pfx_rec in self
+                    .covering_prefixes(prefix.addr(), guard)
+                    .filter(|p| p.prefix.len() <= prefix.len())
+                {
+                    for rec in pfx_rec.meta {
+                        if asn != 0
+                            && rec.meta.origin() == asn
+                            && prefix.len() <= rec.meta.max_len()
+                        {
+                            return RpkiState::Valid;
+                        }
+                        state = RpkiState::Invalid;
+                    }
+                }
+
+                state
+            }
+
+            /// Returns an iterator over the pairs of (less-specific,
+            /// more-specific) prefixes in the store, for both IPv4 and IPv6,
+            /// where the less-specific is the nearest prefix covering the
+            /// more-specific, and the `conflict` callback returns true for
+            /// at least one combination of a record of the less-specific
+            /// (the first argument) and a record of the more-specific (the
+            /// second argument).
+            ///
+            /// Only active records, whose `multi_uniq_id` isn't globally
+            /// withdrawn, are considered. Prefixes without these records
+            /// are skipped, so the nearest less-specific is the nearest one
+            /// that has them. The iterator is unordered.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let guard = &epoch::pin();
+            ///
+            /// for (pfx, asn) in [
+            ///     ("10.0.0.0/8", 65001),
+            ///     ("10.1.0.0/16", 65001),
+            ///     ("10.1.2.0/24", 65002),
+            ///     ("10.2.0.0/16", 65003),
+            /// ] {
+            ///     let record = Record::new(1, 0, RouteStatus::Active, PrefixAs(asn));
+            ///     store.insert(&pfx.parse().unwrap(), record, None).unwrap();
+            /// }
+            ///
+            /// let mut overlaps = store
+            ///     .find_overlaps(|l, m| l.meta != m.meta, guard)
+            ///     .collect::<Vec<_>>();
+            /// overlaps.sort();
+            ///
+            /// assert_eq!(overlaps, vec![
+            ///     ("10.1.0.0/16".parse().unwrap(), "10.1.2.0/24".parse().unwrap()),
+            ///     ("10.0.0.0/8".parse().unwrap(), "10.2.0.0/16".parse().unwrap()),
+            /// ]);
+            /// ```
+            pub fn find_overlaps(
+                &'a self,
+                conflict: impl Fn(&Record<M>, &Record<M>) -> bool + 'a,
+                guard: &'a Guard,
+            ) -> impl Iterator<Item=(Prefix, Prefix)> + 'a {
+                let conflict = std::rc::Rc::new(conflict);
+                let conflict_v6 = conflict.clone();
+
+                self.v4.store.overlaps_iter(
+                    move |l, m| conflict(l, m),
+                    guard
+                )
+                .map(|(l, m)| (l.into_pub(), m.into_pub()))
+                .chain(
+                    self.v6.store.overlaps_iter(
+                        move |l, m| conflict_v6(l, m),
+                        guard
+                    )
+                    .map(|(l, m)| (l.into_pub(), m.into_pub()))
+                )
+            }
+
+            /// Returns the minimal set of prefixes that covers exactly the
+            /// same address space as `root` and all its more-specifics in
+            /// the store, i.e. CIDR aggregation of these prefixes.
+            ///
+            /// Only prefixes with active records are considered. A prefix
+            /// absorbs the more-specifics it covers, and two sibling
+            /// prefixes are merged into their covering prefix, but only if
+            /// the `mergeable` callback returns true for all combinations
+            /// of the metas of the records of the prefixes involved. A
+            /// more-specific is only absorbed by its nearest less-specific,
+            /// so a more-specific with different attributes is kept.
+            ///
+            /// The returned prefixes are sorted.
+            ///
+            /// # Example
+            /// ```
+            /// use std::net::Ipv4Addr;
+            ///
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let guard = &epoch::pin();
+            ///
+            /// let as1 = Record::new(0, 0, RouteStatus::Active, PrefixAs(65001));
+            /// let as2 = Record::new(0, 0, RouteStatus::Active, PrefixAs(65002));
+            ///
+            /// for (addr, len, rec) in [
+            ///     (Ipv4Addr::new(10, 0, 0, 0), 25, &as1),
+            ///     (Ipv4Addr::new(10, 0, 0, 128), 25, &as1),
+            ///     (Ipv4Addr::new(10, 0, 1, 0), 24, &as1),
+            ///     (Ipv4Addr::new(10, 0, 2, 0), 24, &as2),
+            /// ] {
+            ///     store.insert(
+            ///         &Prefix::new(addr.into(), len).unwrap(),
+            ///         rec.clone(),
+            ///         None
+            ///     ).unwrap();
+            /// }
+            ///
+            /// let aggs = store.aggregate(
+            ///     &Prefix::new(Ipv4Addr::new(10, 0, 0, 0).into(), 16).unwrap(),
+            ///     |a, b| a == b,
+            ///     guard
+            /// );
+            ///
+            /// assert_eq!(aggs, vec![
+            ///     Prefix::new(Ipv4Addr::new(10, 0, 0, 0).into(), 23).unwrap(),
+            ///     Prefix::new(Ipv4Addr::new(10, 0, 2, 0).into(), 24).unwrap(),
+            /// ]);
+            /// ```
+            pub fn aggregate(
+                &'a self,
+                root: &Prefix,
+                mergeable: impl Fn(&M, &M) -> bool,
+                guard: &'a Guard,
+            ) -> Vec<Prefix> {
+                let mut aggs = match root.addr() {
+                    std::net::IpAddr::V4(_) => self.v4.aggregate(
+                        PrefixId::<IPv4>::from(*root),
+                        mergeable,
+                        guard
+                    ).into_iter().map(|p| p.into_pub()).collect::<Vec<_>>(),
+                    std::net::IpAddr::V6(_) => self.v6.aggregate(
+                        PrefixId::<IPv6>::from(*root),
+                        mergeable,
+                        guard
+                    ).into_iter().map(|p| p.into_pub()).collect::<Vec<_>>(),
+                };
+                aggs.sort();
+                aggs
+            }
+
+            /// Returns an unordered iterator over all the prefixes, for
+            /// both IPv4 and IPv6, that have a record for the specified
+            /// `mui`, with only the record for that `mui`.
+            ///
+            /// If the `mui` is globally withdrawn for an address family,
+            /// nothing is returned for that family, unless
+            /// `include_withdrawn` is set. Records with a Withdrawn status
+            /// are only returned if `include_withdrawn` is set.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let guard = &epoch::pin();
+            /// for pfx in ["185.49.140.0/22", "2a04:b900::/29"] {
+            ///     for mui in [1, 2] {
+            ///         let record = Record::new(mui, 0, RouteStatus::Active, PrefixAs(211321));
+            ///         store.insert(&pfx.parse().unwrap(), record, None).unwrap();
+            ///     }
+            /// }
+            ///
+            /// assert_eq!(store.iter_records_for_mui(1, false, guard).count(), 2);
+            ///
+            /// // Withdrawing the mui for IPv6 only leaves the IPv4 prefix.
+            /// store.mark_mui_as_withdrawn_v6(1).unwrap();
+            /// let pfxs = store.iter_records_for_mui(1, false, guard)
+            ///     .map(|r| r.prefix)
+            ///     .collect::<Vec<_>>();
+            /// assert_eq!(pfxs, vec!["185.49.140.0/22".parse().unwrap()]);
+            /// assert_eq!(store.iter_records_for_mui(1, true, guard).count(), 2);
+            /// ```
+            pub fn iter_records_for_mui(
+                &'a self,
+                mui: u32,
+                include_withdrawn: bool,
+                guard: &'a Guard
+            ) -> impl Iterator<Item=PrefixRecord<M>> +'a {
+                self.iter_records_for_mui_v4(mui, include_withdrawn, guard)
+                    .chain(
+                        self.iter_records_for_mui_v6(
+                            mui, include_withdrawn, guard
+                        )
+                    )
+            }
+
+            pub fn iter_records_for_mui_v4(
+                &'a self,
+                mui: u32,
+                include_withdrawn: bool,
+                guard: &'a Guard
+            ) -> impl Iterator<Item=PrefixRecord<M>> +'a {
+
+                let bmin = self.v4.store.withdrawn_muis_snapshot(guard);
+
+                if bmin.contains(mui) && !include_withdrawn {
+                    None
+                } else {
+                    Some(
+                        self.v4.store.prefixes_iter_for_mui(
+                                mui,
+                                include_withdrawn,
+                                guard
+                            ).map(|p| PrefixRecord::from(p))
+                    )
+                }.into_iter().flatten()
+            }
+
+            pub fn iter_records_for_mui_v6(
+                &'a self,
+                mui: u32,
+                include_withdrawn: bool,
+                guard: &'a Guard
+            ) -> impl Iterator<Item=PrefixRecord<M>> +'a {
+
+                let bmin = self.v6.store.withdrawn_muis_snapshot(guard);
+
+                if bmin.contains(mui) && !include_withdrawn {
+                    None
+                } else {
+                    Some(
+                        self.v6.store.prefixes_iter_for_mui(
+                                mui,
+                                include_withdrawn,
+                                guard
+                            ).map(|p| PrefixRecord::from(p))
+                    )
+                }.into_iter().flatten()
+            }
+
+            /// Insert or replace a Record into the Store
+            ///
+            /// The specified Record will replace an existing record in the
+            /// store if the multi-map for the specified prefix already has an
+            /// entry for the `multi_uniq_id`, otherwise it will be added to
+            /// the multi-map.
+            ///
+            /// If the `update_path_sections` argument is used the best path
+            /// selection will be run on the resulting multi-map after insert
+            /// and stored for the specified prefix, unless the
+            /// `auto_best_path` of the [StoreConfig] of the store is `Off` or
+            /// `Deferred`, see [AutoBestPath].
+            ///
+            /// If the [StoreConfig] of the store limits the accepted prefix
+            /// lengths for the address family of `prefix`, and its length is
+            /// outside that range, a `PrefixLengthNotAccepted` error is
+            /// returned without touching the store.
+            ///
+            /// If `ignore_withdrawal_of_unknown` is set in the [StoreConfig],
+            /// a record with a `Withdrawn` status for a (prefix, mui)
+            /// combination that isn't in the store is ignored, and the
+            /// returned report has a `NoOp` outcome.
+            ///
+            /// Returns some metrics about the resulting insert.
+            pub fn insert(
+                &self,
+                prefix: &Prefix,
+                record: Record<M>,
+                update_path_selections: Option<M::TBI>
+            ) -> Result<UpsertReport, PrefixStoreError> {
+                self.upsert(prefix, record, update_path_selections, true)
+            }
+
+            /// Insert all the (prefix, record) pairs into the store, like
+            /// `insert` would, with the same `update_path_selections` for
+            /// all of them.
+            ///
+            /// Stops at the first pair that can't be inserted, and returns
+            /// its error. The pairs before it stay in the store. Returns the
+            /// number of inserted pairs otherwise.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new_with_config(
+            ///     StoreConfig {
+            ///         address_families: AfSet::Ipv4Only,
+            ///         ..Default::default()
+            ///     }
+            /// ).unwrap();
+            ///
+            /// let routes = ["185.49.140.0/22", "2a04:b900::/29", "10.0.0.0/8"]
+            ///     .into_iter()
+            ///     .map(|pfx| (
+            ///         pfx.parse::<Prefix>().unwrap(),
+            ///         Record::new(0, 0, RouteStatus::Active, PrefixAs(211321))
+            ///     ));
+            ///
+            /// assert_eq!(
+            ///     store.try_extend(routes, None),
+            ///     Err(PrefixStoreError::AddressFamilyDisabled)
+            /// );
+            /// assert_eq!(store.prefixes_count(), 1);
+            /// ```
+            pub fn try_extend<I: IntoIterator<Item = (Prefix, Record<M>)>>(
+                &self,
+                iter: I,
+                update_path_selections: Option<M::TBI>
+            ) -> Result<usize, PrefixStoreError> {
+                let mut count = 0;
+                for (prefix, record) in iter {
+                    self.insert(&prefix, record, update_path_selections)?;
+                    count += 1;
+                }
+                Ok(count)
+            }
+
+            /// Insert a Record into the Store, but only if the multi-map for
+            /// the specified prefix doesn't have an entry for its
+            /// `multi_uniq_id` yet.
+            ///
+            /// If there already is a record for the (prefix, mui)
+            /// combination, a `RecordAlreadyExists` error is returned and
+            /// the store is left unchanged. The check and the insert happen
+            /// while the multi-map of the prefix is locked, so of two
+            /// concurrent `try_insert`s for the same (prefix, mui) exactly
+            /// one succeeds. Otherwise this behaves like `insert` without
+            /// path selection.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let guard = &epoch::pin();
+            /// let pfx = Prefix::new("185.49.140.0".parse().unwrap(), 22).unwrap();
+            ///
+            /// let record = Record::new(1, 0, RouteStatus::Active, PrefixAs(211321));
+            /// let report = store.try_insert(&pfx, record).unwrap();
+            /// assert_eq!(report.outcome, UpsertOutcome::Inserted);
+            ///
+            /// let record = Record::new(1, 1, RouteStatus::Active, PrefixAs(211322));
+            /// assert_eq!(
+            ///     store.try_insert(&pfx, record).unwrap_err(),
+            ///     PrefixStoreError::RecordAlreadyExists
+            /// );
+            ///
+            /// // The original record is still there.
+            /// let res = store.match_prefix(
+            ///     &pfx,
+            ///     &MatchOptions {
+            ///         match_type: MatchType::ExactMatch,
+            ///         include_withdrawn: false,
+            ///         include_less_specifics: false,
+            ///         include_more_specifics: false,
+            ///         mui: None,
+            ///         debug_path: false,
+            ///         stop_at_first_less_specific: false,
+            ///     },
+            ///     guard,
+            /// );
+            /// assert_eq!(res.prefix_meta[0].meta, PrefixAs(211321));
+            ///
+            /// // Another mui for the same prefix can still be inserted.
+            /// let record = Record::new(2, 1, RouteStatus::Active, PrefixAs(211322));
+            /// assert!(store.try_insert(&pfx, record).is_ok());
+            /// ```
+            pub fn try_insert(
+                &self,
+                prefix: &Prefix,
+                record: Record<M>,
+            ) -> Result<UpsertReport, PrefixStoreError> {
+                self.upsert(prefix, record, None, false)
+            }
+
+            fn upsert(
+                &self,
+                prefix: &Prefix,
+                record: Record<M>,
+                update_path_selections: Option<M::TBI>,
+                replace: bool,
+            ) -> Result<UpsertReport, PrefixStoreError> {
+                self.check_af(prefix)?;
+                if !self.config.accepts_len(prefix) {
+                    return Err(PrefixStoreError::PrefixLengthNotAccepted);
+                }
+
+                let skip_unknown_withdrawal =
+                    self.config.ignore_withdrawal_of_unknown
+                    && record.status == RouteStatus::Withdrawn;
+
+                // A withdrawal for a prefix that isn't in the store is
+                // ignored right away, so that the prefix isn't created for
+                // it. A `try_insert` of a withdrawal can't change anything
+                // either: it doesn't store it for an unknown mui, and
+                // doesn't replace the record of a known one. For the other
+                // withdrawals, whether the mui is known is checked under the
+                // lock on the records of the prefix, by the insert itself.
+                if skip_unknown_withdrawal {
+                    let preview = self.preview_insert(prefix, &record)?;
+                    if !replace && preview.mui_exists {
+                        return Err(PrefixStoreError::RecordAlreadyExists);
+                    }
+                    if !replace || !preview.prefix_exists {
+                        return Ok(UpsertReport {
+                            cas_count: 0,
+                            backoff_count: 0,
+                            prefix_new: false,
+                            mui_new: false,
+                            mui_count: preview.mui_count,
+                            outcome: UpsertOutcome::NoOp,
+                        });
+                    }
+                }
+
+                let event_record = if self.config.emit_events {
+                    Some(record.clone())
+                } else {
+                    None
+                };
+
+                let update_path_selections = match self.config.auto_best_path
+                {
+                    AutoBestPath::OnInsert => update_path_selections,
+                    AutoBestPath::Off | AutoBestPath::Deferred => None,
+                };
+
+                let report = match (prefix.addr(), replace) {
+                    (std::net::IpAddr::V4(addr), true)
+                        if skip_unknown_withdrawal =>
+                    {
+                        self.v4.insert_skip_unknown_withdrawal(
+                            PrefixId::<IPv4>::from(*prefix),
+                            record,
+                            update_path_selections,
+                        )
+                    }
+                    (std::net::IpAddr::V4(addr), true) => {
+                        self.v4.insert(
+                            PrefixId::<IPv4>::from(*prefix),
+                            record,
+                            update_path_selections,
+                        )
+                    }
+                    (std::net::IpAddr::V4(addr), false) => {
+                        self.v4.try_insert(
+                            PrefixId::<IPv4>::from(*prefix),
+                            record,
+                            update_path_selections,
+                        )
+                    }
+                    (std::net::IpAddr::V6(addr), true)
+                        if skip_unknown_withdrawal =>
+                    {
+                        self.v6.insert_skip_unknown_withdrawal(
+                            PrefixId::<IPv6>::from(*prefix),
+                            record,
+                            update_path_selections,
+                        )
+                    }
+                    (std::net::IpAddr::V6(addr), true) => {
+                        self.v6.insert(
+                            PrefixId::<IPv6>::from(*prefix),
+                            record,
+                            update_path_selections,
+                        )
+                    }
+                    (std::net::IpAddr::V6(addr), false) => {
+                        self.v6.try_insert(
+                            PrefixId::<IPv6>::from(*prefix),
+                            record,
+                            update_path_selections,
+                        )
+                    }
+                }?;
+
+                // An existing prefix is marked as outdated by the insert
+                // already, a new one isn't.
+                if self.config.auto_best_path == AutoBestPath::Deferred
+                    && report.prefix_new
+                {
+                    let guard = &epoch::pin();
+                    match prefix.addr() {
+                        std::net::IpAddr::V4(addr) => {
+                            self.v4.store.set_ps_outdated_for_prefix(
+                                PrefixId::<IPv4>::from(*prefix),
+                                guard,
+                            )?
+                        }
+                        std::net::IpAddr::V6(addr) => {
+                            self.v6.store.set_ps_outdated_for_prefix(
+                                PrefixId::<IPv6>::from(*prefix),
+                                guard,
+                            )?
+                        }
+                    }
+                }
+
+                if let Some(record) = event_record {
+                    match report.outcome {
+                        UpsertOutcome::Inserted => {
+                            self.emit(StoreEvent::Inserted {
+                                prefix: *prefix, record
+                            })
+                        }
+                        UpsertOutcome::Replaced => {
+                            self.emit(StoreEvent::Replaced {
+                                prefix: *prefix, record
+                            })
+                        }
+                        // The withdrawal for an unknown mui was ignored.
+                        UpsertOutcome::NoOp => {}
+                    }
+                }
+
+                Ok(report)
+            }
+
+            /// Reports what inserting `record` for `prefix` would do,
+            /// without changing anything in the store.
+            ///
+            /// The returned `InsertPreview` tells whether the prefix is
+            /// already in the store, whether it already has a record for the
+            /// mui of `record` (with any status) and, therefore, whether an
+            /// `insert` would insert or replace that record, or ignore it,
+            /// if it is a withdrawal for an unknown (prefix, mui) and the
+            /// store is configured with `ignore_withdrawal_of_unknown`.
+            /// Returns the same errors as `insert` if the store doesn't
+            /// accept the prefix.
+            ///
+            /// Note that the preview reflects the store at the time it was
+            /// created, a concurrent `insert` may change the outcome.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let pfx = Prefix::new("185.49.140.0".parse().unwrap(), 22).unwrap();
+            /// let record = Record::new(1, 0, RouteStatus::Active, PrefixAs(211321));
+            ///
+            /// let preview = store.preview_insert(&pfx, &record).unwrap();
+            /// assert!(!preview.prefix_exists);
+            /// assert_eq!(preview.outcome, UpsertOutcome::Inserted);
+            ///
+            /// store.insert(&pfx, record.clone(), None).unwrap();
+            ///
+            /// let preview = store.preview_insert(&pfx, &record).unwrap();
+            /// assert!(preview.prefix_exists && preview.mui_exists);
+            /// assert_eq!(preview.outcome, UpsertOutcome::Replaced);
+            ///
+            /// let other_mui = Record::new(2, 0, RouteStatus::Active, PrefixAs(211321));
+            /// let preview = store.preview_insert(&pfx, &other_mui).unwrap();
+            /// assert!(preview.prefix_exists && !preview.mui_exists);
+            /// assert_eq!(preview.outcome, UpsertOutcome::Inserted);
+            /// ```
+            pub fn preview_insert(
+                &'a self,
+                prefix: &Prefix,
+                record: &Record<M>,
+            ) -> Result<InsertPreview, PrefixStoreError> {
+                self.check_af(prefix)?;
+                if !self.config.accepts_len(prefix) {
+                    return Err(PrefixStoreError::PrefixLengthNotAccepted);
+                }
+
+                let mut preview = match prefix.addr() {
+                    std::net::IpAddr::V4(_) => {
+                        self.v4.store.preview_upsert(
+                            PrefixId::<IPv4>::from(*prefix),
+                            record.multi_uniq_id,
+                        )
+                    }
+                    std::net::IpAddr::V6(_) => {
+                        self.v6.store.preview_upsert(
+                            PrefixId::<IPv6>::from(*prefix),
+                            record.multi_uniq_id,
+                        )
+                    }
+                };
+
+                if self.config.ignore_withdrawal_of_unknown
+                    && record.status == RouteStatus::Withdrawn
+                    && !preview.mui_exists
+                {
+                    preview.outcome = UpsertOutcome::NoOp;
+                }
+
+                Ok(preview)
+            }
+
+            /// Returns an unordered iterator over all prefixes, with any
+            /// status (including Withdrawn), for both IPv4 and IPv6,
+            /// currently in the store, including meta-data.
+            ///
+            /// Although the iterator is unordered within an address-family,
+            /// it first iterates over all IPv4 addresses and then over all
+            /// IPv6 addresses.
+            ///
+            /// The iterator can be reversed, e.g. to retrieve the last N
+            /// prefixes. Since the prefixes are not stored in any order, the
+            /// first step from the back collects references to all the
+            /// remaining prefixes of an address family in memory, going
+            /// forward does not. The records are only cloned for the
+            /// prefixes that are actually returned.
+            ///
+            /// The `guard` should be a `&epoch::pin()`. It allows the
+            /// iterator to create and return references to the meta-data
+            /// objects to the caller (instead of cloning them).
+            ///
+            /// # Example
+            /// ```
+            /// use std::net::Ipv4Addr;
+            ///
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let guard = epoch::pin();
+            ///
+            /// let pfx_addr = "185.49.140.0".parse::<Ipv4Addr>()
+            ///         .unwrap()
+            ///         .into();
+            /// let our_asn = Record::new(0, 0, RouteStatus::Active, PrefixAs(211321));
+            ///
+            /// store.insert(&Prefix::new(pfx_addr, 22).unwrap(), our_asn.clone(), None);
+            /// store.insert(&Prefix::new(pfx_addr, 23).unwrap(), our_asn.clone(), None);
+            /// store.insert(&Prefix::new(pfx_addr, 24).unwrap(), our_asn.clone(), None);
+            /// store.insert(&Prefix::new(pfx_addr, 25).unwrap(), our_asn, None);
+            ///
+            /// let mut iter = store.prefixes_iter();
+            ///
+            /// assert_eq!(iter.next().unwrap().prefix,
+            ///     Prefix::new(pfx_addr, 22).unwrap());
+            /// assert_eq!(iter.next().unwrap().prefix,
+            ///     Prefix::new(pfx_addr, 23).unwrap());
+            /// assert_eq!(iter.next().unwrap().prefix,
+            ///     Prefix::new(pfx_addr, 24).unwrap());
+            /// assert_eq!(iter.next().unwrap().prefix,
+            ///     Prefix::new(pfx_addr, 25).unwrap());
+            ///
+            /// let last_two = store.prefixes_iter()
+            ///     .rev()
+            ///     .take(2)
+            ///     .map(|p| p.prefix)
+            ///     .collect::<Vec<_>>();
+            ///
+            /// assert_eq!(last_two, vec![
+            ///     Prefix::new(pfx_addr, 25).unwrap(),
+            ///     Prefix::new(pfx_addr, 24).unwrap(),
+            /// ]);
+            /// ```
+            pub fn prefixes_iter(
+                &'a self,
+            ) -> impl DoubleEndedIterator<Item=PrefixRecord<M>> + 'a {
+                self.v4.store.prefixes_iter()
+                    .map(|p| PrefixRecord::from(p))
+                    .chain(
+                        self.v6.store.prefixes_iter()
+                        .map(|p| PrefixRecord::from(p))
+                    )
+            }
+
+            /// Returns an unordered iterator over a snapshot of all
+            /// prefixes, with any status (including Withdrawn), for both
+            /// IPv4 and IPv6, in the store, including meta-data.
+            ///
+            /// All the prefixes and their records are collected when this
+            /// method is called, so the iteration is a point-in-time view
+            /// of the store: prefixes and records that are inserted or
+            /// modified after this method returns are not observed, however
+            /// long the iteration takes. Changes that happen while the
+            /// snapshot is being collected may or may not be included,
+            /// there is no lock on the whole store. The price is that all
+            /// the records are cloned up front, so the snapshot takes as
+            /// much memory as the records in the store.
+            ///
+            /// Unlike `prefixes_iter`, this iterator takes the status of the
+            /// muis that are globally withdrawn into account: the records
+            /// for these muis are returned with a `Withdrawn` status. The
+            /// global withdrawn muis are read once, with the `guard`, for
+            /// each address family, at the same time as the records. A mui
+            /// that is withdrawn or re-activated after this method returns
+            /// doesn't change the statuses in the snapshot.
+            ///
+            /// Since the snapshot is collected up front, the `guard` doesn't
+            /// need to be held for the duration of the iteration. Keeping a
+            /// guard pinned for a long time delays the reclamation of all
+            /// the memory that is retired by the store (on all threads)
+            /// while it is pinned, e.g. the replaced withdrawn muis bitmaps.
+            ///
+            /// # Example
+            /// ```
+            /// use std::net::Ipv4Addr;
+            ///
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            ///
+            /// let pfx = Prefix::new(Ipv4Addr::new(185, 49, 140, 0).into(), 22)
+            ///     .unwrap();
+            /// store.insert(
+            ///     &pfx,
+            ///     Record::new(1, 0, RouteStatus::Active, PrefixAs(211321)),
+            ///     None
+            /// ).unwrap();
+            ///
+            /// let guard = &epoch::pin();
+            /// let iter = store.prefixes_iter_with_guard(guard);
+            ///
+            /// // The mui was withdrawn and a prefix was added after the
+            /// // iterator was created, the iterator doesn't see that.
+            /// store.mark_mui_as_withdrawn_v4(1).unwrap();
+            /// store.insert(
+            ///     &Prefix::new(Ipv4Addr::new(185, 49, 144, 0).into(), 22).unwrap(),
+            ///     Record::new(1, 0, RouteStatus::Active, PrefixAs(211321)),
+            ///     None
+            /// ).unwrap();
+            ///
+            /// let snapshot = iter.collect::<Vec<_>>();
+            /// assert_eq!(snapshot.len(), 1);
+            /// assert_eq!(snapshot[0].prefix, pfx);
+            /// assert_eq!(snapshot[0].meta[0].status, RouteStatus::Active);
+            ///
+            /// let p = store.prefixes_iter_with_guard(guard).next().unwrap();
+            /// assert_eq!(p.meta[0].status, RouteStatus::Withdrawn);
+            /// ```
+            pub fn prefixes_iter_with_guard(
+                &'a self,
+                guard: &'a Guard,
+            ) -> impl DoubleEndedIterator<Item=PrefixRecord<M>> + 'a {
+                self.v4.store.prefixes_iter_with_guard(guard)
+                    .map(|p| PrefixRecord::from(p))
+                    .chain(
+                        self.v6.store.prefixes_iter_with_guard(guard)
+                        .map(|p| PrefixRecord::from(p))
+                    )
+            }
+
+            /// Returns an unordered iterator over all IPv4 prefixes in the
+            /// currently in the store, with any status (including Withdrawn),
+            /// including meta-data.
+            ///
+            /// Like `prefixes_iter` this iterator can be reversed, at the
+            /// cost of collecting references to the remaining prefixes in
+            /// memory.
+            ///
+            /// The `guard` should be a `&epoch::pin()`. It allows the
+            /// iterator to create and return references to the meta-data
+            /// objects to the caller (instead of cloning them).
+            ///
+            /// # Example
+            /// ```
+            /// use std::net::Ipv4Addr;
+            ///
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let guard = epoch::pin();
+            ///
+            /// let pfx_addr = "185.49.140.0".parse::<Ipv4Addr>()
+            ///         .unwrap()
+            ///         .into();
+            /// let our_asn = Record::new(0, 0, RouteStatus::Active, PrefixAs(211321));
+            ///
+            /// store.insert(&Prefix::new(pfx_addr, 22).unwrap(), our_asn.clone(), None);
+            /// store.insert(&Prefix::new(pfx_addr, 23).unwrap(), our_asn.clone(), None);
+            /// store.insert(&Prefix::new(pfx_addr, 24).unwrap(), our_asn.clone(), None);
+            /// store.insert(&Prefix::new(pfx_addr, 25).unwrap(), our_asn, None);
+            ///
+            /// let mut iter = store.prefixes_iter();
+            ///
+            /// assert_eq!(iter.next().unwrap().prefix,
+            ///     Prefix::new(pfx_addr, 22).unwrap());
+            /// assert_eq!(iter.next().unwrap().prefix,
+            ///     Prefix::new(pfx_addr, 23).unwrap());
+            /// assert_eq!(iter.next().unwrap().prefix,
+            ///     Prefix::new(pfx_addr, 24).unwrap());
+            /// assert_eq!(iter.next().unwrap().prefix,
+            ///     Prefix::new(pfx_addr, 25).unwrap());
+            /// ```
+            pub fn prefixes_iter_v4(
+                &'a self,
+            ) -> impl DoubleEndedIterator<Item=PrefixRecord<M>> + 'a {
+                self.v4.store.prefixes_iter()
+                    .map(|p| PrefixRecord::from(p))
+            }
+
+            /// Returns an unordered iterator over all IPv6 prefixes in the
+            /// currently in the store, with any status (including Withdrawn),
+            /// including meta-data.
+            ///
+            /// Like `prefixes_iter` this iterator can be reversed, at the
+            /// cost of collecting references to the remaining prefixes in
+            /// memory.
+            ///
+            /// The `guard` should be a `&epoch::pin()`. It allows the
+            /// iterator to create and return references to the meta-data
+            /// objects to the caller (instead of cloning them).
+            ///
+            /// # Example
+            /// ```
+            /// use std::net::Ipv6Addr;
+            ///
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let guard = epoch::pin();
+            ///
+            /// let pfx_addr = "2a04:b900::".parse::<Ipv6Addr>()
+            ///         .unwrap()
+            ///         .into();
+            /// let our_asn = Record::new(0, 0, RouteStatus::Active, PrefixAs(211321));
+            ///
+            /// store.insert(&Prefix::new(pfx_addr, 29).unwrap(), our_asn.clone(), None);
+            /// store.insert(&Prefix::new(pfx_addr, 48).unwrap(), our_asn.clone(), None);
+            /// store.insert(&Prefix::new(pfx_addr, 56).unwrap(), our_asn.clone(), None);
+            /// store.insert(&Prefix::new(pfx_addr, 64).unwrap(), our_asn, None);
+            ///
+            /// let mut iter = store.prefixes_iter();
+            ///
+            /// assert_eq!(iter.next().unwrap().prefix,
+            ///     Prefix::new(pfx_addr, 29).unwrap());
+            /// assert_eq!(iter.next().unwrap().prefix,
+            ///     Prefix::new(pfx_addr, 48).unwrap());
+            /// assert_eq!(iter.next().unwrap().prefix,
+            ///     Prefix::new(pfx_addr, 56).unwrap());
+            /// assert_eq!(iter.next().unwrap().prefix,
+            ///     Prefix::new(pfx_addr, 64).unwrap());
+            /// ```
+            pub fn prefixes_iter_v6(
+                &'a self,
+            ) -> impl DoubleEndedIterator<Item=PrefixRecord<M>> + 'a {
+                self.v6.store.prefixes_iter()
+                    .map(|p| PrefixRecord::from(p))
+            }
+
+            /// Returns an unordered iterator over all prefixes, for both
+            /// IPv4 and IPv6, that have records with the specified `status`,
+            /// including only those records.
+            ///
+            /// If `apply_global_status` is false, only the local status of
+            /// the records is considered. If it is true, the records for the
+            /// `multi_uniq_id`s that are globally withdrawn are considered to
+            /// have a `Withdrawn` status, regardless of their local status.
+            /// Prefixes without any records with `status` are skipped.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let guard = &epoch::pin();
+            /// let pfx = Prefix::new("185.49.140.0".parse().unwrap(), 22).unwrap();
+            ///
+            /// for (mui, status) in [
+            ///     (1, RouteStatus::Active),
+            ///     (2, RouteStatus::Withdrawn),
+            ///     (3, RouteStatus::Active),
+            /// ] {
+            ///     let record = Record::new(mui, 0, status, PrefixAs(211321));
+            ///     store.insert(&pfx, record, None).unwrap();
+            /// }
+            /// store.mark_mui_as_withdrawn_v4(3).unwrap();
+            ///
+            /// let local = store
+            ///     .prefixes_iter_with_status(RouteStatus::Withdrawn, false, guard)
+            ///     .collect::<Vec<_>>();
+            /// assert_eq!(local.len(), 1);
+            /// assert_eq!(local[0].meta.len(), 1);
+            /// assert_eq!(local[0].meta[0].multi_uniq_id, 2);
+            ///
+            /// let global = store
+            ///     .prefixes_iter_with_status(RouteStatus::Withdrawn, true, guard)
+            ///     .collect::<Vec<_>>();
+            /// assert_eq!(global[0].meta.len(), 2);
+            /// ```
+            pub fn prefixes_iter_with_status(
+                &'a self,
+                status: RouteStatus,
+                apply_global_status: bool,
+                guard: &'a Guard,
+            ) -> impl Iterator<Item=PrefixRecord<M>> + 'a {
+                self.v4.store.prefixes_iter_with_status(
+                    status,
+                    apply_global_status,
+                    guard
+                )
+                .map(|p| PrefixRecord::from(p))
+                .chain(
+                    self.v6.store.prefixes_iter_with_status(
+                        status,
+                        apply_global_status,
+                        guard
+                    )
+                    .map(|p| PrefixRecord::from(p))
+                )
+            }
+
+            /// Returns an unordered iterator over all prefixes, for both
+            /// IPv4 and IPv6, that have records with an `ltime` greater
+            /// than `since_ltime`, with only those records.
+            ///
+            /// This is meant for incremental synchronization of a copy of
+            /// the store: remember the highest `ltime` seen, and ask for
+            /// the records changed since on the next round. Like
+            /// `prefixes_iter_with_guard`, the records for muis that are
+            /// globally withdrawn are returned with a `Withdrawn` status,
+            /// but note that withdrawing a mui globally doesn't change the
+            /// `ltime` of its records, so that by itself doesn't make them
+            /// show up here.
+            ///
+            /// The same goes for the status changes of the records of a
+            /// single prefix: `mark_mui_as_withdrawn_for_prefix`,
+            /// `mark_mui_as_withdrawn_for_prefixes`,
+            /// `mark_mui_as_active_for_prefix`,
+            /// `mark_mui_as_stale_for_prefix`, `mark_mui_as_stale` and
+            /// `sweep_stale` only change the status of a record, and keep
+            /// its `ltime`. Only an insert sets the `ltime` of a record, so
+            /// a caller that needs to synchronize status changes should
+            /// insert the record again with the new status and a new
+            /// `ltime` instead.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let guard = &epoch::pin();
+            /// let pfx = Prefix::new("185.49.140.0".parse().unwrap(), 22).unwrap();
+            ///
+            /// for (mui, ltime) in [(1, 10), (2, 20), (3, 30)] {
+            ///     let record = Record::new(mui, ltime, RouteStatus::Active, PrefixAs(211321));
+            ///     store.insert(&pfx, record, None).unwrap();
+            /// }
+            ///
+            /// let changed = store.changed_since(15, guard).collect::<Vec<_>>();
+            /// assert_eq!(changed.len(), 1);
+            /// let mut muis = changed[0].meta.iter()
+            ///     .map(|r| r.multi_uniq_id)
+            ///     .collect::<Vec<_>>();
+            /// muis.sort();
+            /// assert_eq!(muis, vec![2, 3]);
+            ///
+            /// assert_eq!(store.changed_since(30, guard).count(), 0);
+            /// ```
+            pub fn changed_since(
+                &'a self,
+                since_ltime: u64,
+                guard: &'a Guard,
+            ) -> impl Iterator<Item=PrefixRecord<M>> + 'a {
+                self.v4.store.changed_since(since_ltime, guard)
+                    .map(|p| PrefixRecord::from(p))
+                    .chain(
+                        self.v6.store.changed_since(since_ltime, guard)
+                        .map(|p| PrefixRecord::from(p))
+                    )
+            }
+
+            /// Returns an unordered iterator over all prefixes with length
+            /// `len`, for both IPv4 and IPv6, currently in the store, with
+            /// any status (including Withdrawn), including meta-data.
+            ///
+            /// Only the storage for the prefixes of length `len` is visited,
+            /// which is much cheaper than filtering the output of
+            /// `prefixes_iter` on the length. A length larger than 32 only
+            /// returns IPv6 prefixes, a length larger than 128 returns an
+            /// empty iterator.
+            ///
+            /// Like `prefixes_iter_with_guard`, the records for muis that
+            /// are globally withdrawn are returned with a `Withdrawn`
+            /// status.
+            ///
+            /// # Example
+            /// ```
+            /// use std::net::{Ipv4Addr, Ipv6Addr};
+            ///
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let guard = &epoch::pin();
+            ///
+            /// let v4_addr = Ipv4Addr::new(185, 49, 140, 0).into();
+            /// let v6_addr = "2a04:b900::".parse::<Ipv6Addr>().unwrap().into();
+            /// let our_asn = Record::new(0, 0, RouteStatus::Active, PrefixAs(211321));
+            ///
+            /// for len in [22, 23, 24] {
+            ///     store.insert(
+            ///         &Prefix::new(v4_addr, len).unwrap(), our_asn.clone(), None
+            ///     ).unwrap();
+            /// }
+            /// for len in [24, 29, 48] {
+            ///     store.insert(
+            ///         &Prefix::new(v6_addr, len).unwrap(), our_asn.clone(), None
+            ///     ).unwrap();
+            /// }
+            ///
+            /// let mut pfxs = store.prefixes_iter_for_len(24, guard)
+            ///     .map(|p| p.prefix)
+            ///     .collect::<Vec<_>>();
+            /// pfxs.sort();
+            /// assert_eq!(pfxs, vec![
+            ///     Prefix::new(v4_addr, 24).unwrap(),
+            ///     Prefix::new(v6_addr, 24).unwrap(),
+            /// ]);
+            ///
+            /// assert_eq!(store.prefixes_iter_for_len(48, guard).count(), 1);
+            /// assert_eq!(store.prefixes_iter_for_len(129, guard).count(), 0);
+            /// ```
+            pub fn prefixes_iter_for_len(
                 &'a self,
+                len: u8,
+                guard: &'a Guard,
             ) -> impl Iterator<Item=PrefixRecord<M>> + 'a {
-                self.v4.store.prefixes_iter()
+                self.v4.store.prefixes_iter_for_len(len, guard)
                     .map(|p| PrefixRecord::from(p))
                     .chain(
-                        self.v6.store.prefixes_iter()
+                        self.v6.store.prefixes_iter_for_len(len, guard)
                         .map(|p| PrefixRecord::from(p))
                     )
             }
 
-            /// Returns an unordered iterator over all IPv4 prefixes in the
-            /// currently in the store, with any status (including Withdrawn),
-            /// including meta-data.
+            /// Consumes the store and returns an iterator over all the
+            /// prefixes, for both IPv4 and IPv6, with all their records,
+            /// with any status (including Withdrawn).
             ///
-            /// The `guard` should be a `&epoch::pin()`. It allows the
-            /// iterator to create and return references to the meta-data
-            /// objects to the caller (instead of cloning them).
+            /// Unlike `prefixes_iter`, the records are moved out of the
+            /// store instead of being cloned, and the returned iterator
+            /// doesn't borrow the store. The local status of the records is
+            /// returned as is, the globally withdrawn muis are not taken
+            /// into account. Prefixes without any records are skipped.
+            ///
+            /// Note that all the prefixes are collected when this method is
+            /// called, the memory used by the tree itself is released when
+            /// the store is dropped at the end of this method.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let pfx = Prefix::new("185.49.140.0".parse().unwrap(), 22).unwrap();
+            ///
+            /// for mui in 1..=3 {
+            ///     let record = Record::new(mui, 0, RouteStatus::Active, PrefixAs(211321));
+            ///     store.insert(&pfx, record, None).unwrap();
+            /// }
+            ///
+            /// let records = store.into_prefix_records().collect::<Vec<_>>();
+            /// assert_eq!(records.len(), 1);
+            /// assert_eq!(records[0].prefix, pfx);
+            /// assert_eq!(records[0].meta.len(), 3);
+            /// ```
+            pub fn into_prefix_records(
+                self,
+            ) -> impl Iterator<Item=PrefixRecord<M>> {
+                let mut records = self.v4.store.take_prefix_records();
+                records.extend(self.v6.store.take_prefix_records());
+
+                records.into_iter()
+                    .filter(|(_, recs)| !recs.is_empty())
+                    .map(PrefixRecord::from)
+            }
+
+            /// Returns an unordered iterator over the ids of all IPv4
+            /// prefixes in the store.
+            ///
+            /// This iterator only walks the bitmaps of the nodes in the
+            /// tree, it only reads from the prefix store to look up the
+            /// default route, which doesn't live in any node. So it doesn't
+            /// return any records, and it doesn't look at the (withdrawn)
+            /// statuses of the prefixes. This makes it a lot cheaper than
+            /// `prefixes_iter_v4` if only the prefixes are needed.
             ///
             /// # Example
             /// ```
@@ -1203,139 +3317,556 @@ pub fn create_store(attr: TokenStream, item: TokenStream) -> TokenStream {
             /// use rotonda_store::meta_examples::PrefixAs;
             ///
             /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
-            /// let guard = epoch::pin();
             ///
             /// let pfx_addr = "185.49.140.0".parse::<Ipv4Addr>()
             ///         .unwrap()
             ///         .into();
             /// let our_asn = Record::new(0, 0, RouteStatus::Active, PrefixAs(211321));
             ///
-            /// store.insert(&Prefix::new(pfx_addr, 22).unwrap(), our_asn.clone(), None);
-            /// store.insert(&Prefix::new(pfx_addr, 23).unwrap(), our_asn.clone(), None);
-            /// store.insert(&Prefix::new(pfx_addr, 24).unwrap(), our_asn.clone(), None);
-            /// store.insert(&Prefix::new(pfx_addr, 25).unwrap(), our_asn, None);
+            /// for len in [8, 12, 22, 23, 24, 32] {
+            ///     store.insert(
+            ///         &Prefix::new_relaxed(pfx_addr, len).unwrap(),
+            ///         our_asn.clone(),
+            ///         None
+            ///     ).unwrap();
+            /// }
             ///
-            /// let mut iter = store.prefixes_iter();
+            /// let mut ids = store.prefix_ids_iter_v4()
+            ///     .map(|id| id.into_pub())
+            ///     .collect::<Vec<_>>();
+            /// let mut prefixes = store.prefixes_iter_v4()
+            ///     .map(|p| p.prefix)
+            ///     .collect::<Vec<_>>();
+            /// ids.sort();
+            /// prefixes.sort();
             ///
-            /// assert_eq!(iter.next().unwrap().prefix,
-            ///     Prefix::new(pfx_addr, 22).unwrap());
-            /// assert_eq!(iter.next().unwrap().prefix,
-            ///     Prefix::new(pfx_addr, 23).unwrap());
-            /// assert_eq!(iter.next().unwrap().prefix,
-            ///     Prefix::new(pfx_addr, 24).unwrap());
-            /// assert_eq!(iter.next().unwrap().prefix,
-            ///     Prefix::new(pfx_addr, 25).unwrap());
+            /// assert_eq!(ids.len(), 6);
+            /// assert_eq!(ids, prefixes);
             /// ```
-            pub fn prefixes_iter_v4(
+            pub fn prefix_ids_iter_v4(
                 &'a self,
-            ) -> impl Iterator<Item=PrefixRecord<M>> + 'a {
-                self.v4.store.prefixes_iter()
-                    .map(|p| PrefixRecord::from(p))
+            ) -> impl Iterator<Item=PrefixId<IPv4>> + 'a {
+                self.v4.store.prefix_ids_iter()
             }
 
-            /// Returns an unordered iterator over all IPv6 prefixes in the
-            /// currently in the store, with any status (including Withdrawn),
-            /// including meta-data.
+            /// Returns an unordered iterator over the ids of all IPv6
+            /// prefixes in the store.
             ///
-            /// The `guard` should be a `&epoch::pin()`. It allows the
-            /// iterator to create and return references to the meta-data
-            /// objects to the caller (instead of cloning them).
+            /// Like `prefix_ids_iter_v4` this iterator only walks the bitmaps
+            /// of the nodes in the tree, and only reads the default route
+            /// from the prefix store.
+            pub fn prefix_ids_iter_v6(
+                &'a self,
+            ) -> impl Iterator<Item=PrefixId<IPv6>> + 'a {
+                self.v6.store.prefix_ids_iter()
+            }
+
+            /// Change the local status of the record for the combination of
+            /// (prefix, multi_uniq_id) to Withdrawn. Note that by default the
+            /// global `Withdrawn` status for a mui overrides the local status
+            /// of a record.
+            pub fn mark_mui_as_withdrawn_for_prefix(
+                &self,
+                prefix: &Prefix,
+                mui: u32
+            ) -> Result<(), PrefixStoreError> {
+                self.check_af(prefix)?;
+                let guard = &epoch::pin();
+                let changed = match prefix.addr() {
+                    std::net::IpAddr::V4(addr) => {
+                        self.v4.store.mark_mui_as_withdrawn_for_prefix(
+                            PrefixId::<IPv4>::from(*prefix),
+                            mui,
+                            // &guard
+                        )
+                    }
+                    std::net::IpAddr::V6(addr) => {
+                        self.v6.store.mark_mui_as_withdrawn_for_prefix(
+                            PrefixId::<IPv6>::from(*prefix),
+                            mui,
+                            // &guard
+                        )
+                    }
+                }?;
+
+                if changed {
+                    self.emit(StoreEvent::Withdrawn { prefix: *prefix, mui });
+                }
+                Ok(())
+            }
+
+            /// Change the local status of the records for `mui` for all the
+            /// specified prefixes to Withdrawn, e.g. for a batch of
+            /// withdrawals from a route refresh.
+            ///
+            /// Prefixes that are not in the store, or that don't have a
+            /// record for `mui`, are skipped. Returns the number of records
+            /// whose status actually changed. If any of the prefixes is of
+            /// an address family that is disabled for this store, an
+            /// `AddressFamilyDisabled` error is returned and no records are
+            /// changed.
             ///
             /// # Example
             /// ```
-            /// use std::net::Ipv6Addr;
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let pfxs = ["185.49.140.0/22", "2a04:b900::/29", "192.0.2.0/24"]
+            ///     .map(|p| p.parse::<Prefix>().unwrap());
+            ///
+            /// // The last prefix only has a record for another mui.
+            /// for (pfx, mui) in pfxs.iter().zip([1, 1, 2]) {
+            ///     let record = Record::new(mui, 0, RouteStatus::Active, PrefixAs(211321));
+            ///     store.insert(pfx, record, None).unwrap();
+            /// }
+            ///
+            /// let unknown = "198.51.100.0/24".parse::<Prefix>().unwrap();
+            /// let batch = [pfxs[0], pfxs[1], pfxs[2], unknown];
+            /// assert_eq!(store.mark_mui_as_withdrawn_for_prefixes(&batch, 1).unwrap(), 2);
+            ///
+            /// // Already withdrawn.
+            /// assert_eq!(store.mark_mui_as_withdrawn_for_prefixes(&batch, 1).unwrap(), 0);
+            /// ```
+            pub fn mark_mui_as_withdrawn_for_prefixes(
+                &'a self,
+                prefixes: &[Prefix],
+                mui: u32
+            ) -> Result<usize, PrefixStoreError> {
+                for prefix in prefixes {
+                    self.check_af(prefix)?;
+                }
+
+                let withdrawn_v4 = self.v4.store.mark_mui_as_withdrawn_for_prefixes(
+                    prefixes
+                        .iter()
+                        .filter(|p| p.is_v4())
+                        .map(|p| PrefixId::<IPv4>::from(*p)),
+                    mui,
+                );
+                let withdrawn_v6 = self.v6.store.mark_mui_as_withdrawn_for_prefixes(
+                    prefixes
+                        .iter()
+                        .filter(|p| p.is_v6())
+                        .map(|p| PrefixId::<IPv6>::from(*p)),
+                    mui,
+                );
+
+                for prefix in withdrawn_v4.iter().map(|p| p.into_pub()) {
+                    self.emit(StoreEvent::Withdrawn { prefix, mui });
+                }
+                for prefix in withdrawn_v6.iter().map(|p| p.into_pub()) {
+                    self.emit(StoreEvent::Withdrawn { prefix, mui });
+                }
+
+                Ok(withdrawn_v4.len() + withdrawn_v6.len())
+            }
+
+            /// Change the local status of the record for the combination of
+            /// (prefix, multi_uniq_id) to Active. Note that by default the
+            /// global `Withdrawn` status for a mui overrides the local status
+            /// of a record.
+            pub fn mark_mui_as_active_for_prefix(
+                &self,
+                prefix: &Prefix,
+                mui: u32
+            ) -> Result<(), PrefixStoreError> {
+                self.check_af(prefix)?;
+                let guard = &epoch::pin();
+                let changed = match prefix.addr() {
+                    std::net::IpAddr::V4(addr) => {
+                        self.v4.store.mark_mui_as_active_for_prefix(
+                            PrefixId::<IPv4>::from(*prefix),
+                            mui,
+                            // &guard
+                        )
+                    }
+                    std::net::IpAddr::V6(addr) => {
+                        self.v6.store.mark_mui_as_active_for_prefix(
+                            PrefixId::<IPv6>::from(*prefix),
+                            mui,
+                            // &guard
+                        )
+                    }
+                }?;
+
+                if changed {
+                    self.emit(StoreEvent::Activated { prefix: *prefix, mui });
+                }
+                Ok(())
+            }
+
+            /// Change the local status of the record for the combination of
+            /// (prefix, multi_uniq_id) to Stale, if it is Active. Stale
+            /// records are still returned by queries, but carry the `Stale`
+            /// status, until they are refreshed by a new insert, or
+            /// withdrawn by `sweep_stale`. A Withdrawn or InActive record
+            /// is left alone.
+            pub fn mark_mui_as_stale_for_prefix(
+                &self,
+                prefix: &Prefix,
+                mui: u32
+            ) -> Result<(), PrefixStoreError> {
+                self.check_af(prefix)?;
+                let changed = match prefix.addr() {
+                    std::net::IpAddr::V4(addr) => {
+                        self.v4.store.mark_mui_as_stale_for_prefix(
+                            PrefixId::<IPv4>::from(*prefix),
+                            mui,
+                        )
+                    }
+                    std::net::IpAddr::V6(addr) => {
+                        self.v6.store.mark_mui_as_stale_for_prefix(
+                            PrefixId::<IPv6>::from(*prefix),
+                            mui,
+                        )
+                    }
+                }?;
+
+                if changed {
+                    self.emit(StoreEvent::Stale { prefix: *prefix, mui });
+                }
+                Ok(())
+            }
+
+            /// Change the local status of all the Active records for this
+            /// `multi_uniq_id` to Stale, e.g. when the BGP session for this
+            /// `multi_uniq_id` goes through a graceful restart. Records
+            /// that are Withdrawn or InActive are left alone. Returns the
+            /// number of records that were marked.
+            ///
+            /// Records that are not refreshed by a new insert in time can
+            /// be withdrawn with `sweep_stale`.
             ///
+            /// # Example
+            /// ```
             /// use rotonda_store::prelude::*;
             /// use rotonda_store::prelude::multi::*;
             /// use rotonda_store::meta_examples::PrefixAs;
             ///
             /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
-            /// let guard = epoch::pin();
+            /// let guard = &epoch::pin();
             ///
-            /// let pfx_addr = "2a04:b900::".parse::<Ipv6Addr>()
-            ///         .unwrap()
-            ///         .into();
-            /// let our_asn = Record::new(0, 0, RouteStatus::Active, PrefixAs(211321));
+            /// let pfx1 = Prefix::new("185.49.140.0".parse().unwrap(), 22).unwrap();
+            /// let pfx2 = Prefix::new("185.49.144.0".parse().unwrap(), 22).unwrap();
+            /// for pfx in [pfx1, pfx2] {
+            ///     let record = Record::new(1, 10, RouteStatus::Active, PrefixAs(211321));
+            ///     store.insert(&pfx, record, None).unwrap();
+            /// }
             ///
-            /// store.insert(&Prefix::new(pfx_addr, 29).unwrap(), our_asn.clone(), None);
-            /// store.insert(&Prefix::new(pfx_addr, 48).unwrap(), our_asn.clone(), None);
-            /// store.insert(&Prefix::new(pfx_addr, 56).unwrap(), our_asn.clone(), None);
-            /// store.insert(&Prefix::new(pfx_addr, 64).unwrap(), our_asn, None);
+            /// // The session for mui 1 goes down.
+            /// assert_eq!(store.mark_mui_as_stale(1), 2);
             ///
-            /// let mut iter = store.prefixes_iter();
+            /// // Stale records are still returned, but flagged as such.
+            /// let options = MatchOptions {
+            ///     match_type: MatchType::ExactMatch,
+            ///     include_withdrawn: false,
+            ///     include_less_specifics: false,
+            ///     include_more_specifics: false,
+            ///     mui: None,
+            ///     debug_path: false,
+            ///     stop_at_first_less_specific: false,
+            /// };
+            /// let res = store.match_prefix(&pfx2, &options, guard);
+            /// assert_eq!(res.prefix_meta[0].status, RouteStatus::Stale);
             ///
-            /// assert_eq!(iter.next().unwrap().prefix,
-            ///     Prefix::new(pfx_addr, 29).unwrap());
-            /// assert_eq!(iter.next().unwrap().prefix,
-            ///     Prefix::new(pfx_addr, 48).unwrap());
-            /// assert_eq!(iter.next().unwrap().prefix,
-            ///     Prefix::new(pfx_addr, 56).unwrap());
-            /// assert_eq!(iter.next().unwrap().prefix,
-            ///     Prefix::new(pfx_addr, 64).unwrap());
+            /// // It comes back up and re-announces one of the prefixes.
+            /// let record = Record::new(1, 20, RouteStatus::Active, PrefixAs(211321));
+            /// store.insert(&pfx1, record, None).unwrap();
+            ///
+            /// // The other one is withdrawn after the deadline.
+            /// assert_eq!(store.sweep_stale(20).unwrap(), 1);
+            ///
+            /// assert_eq!(store.match_prefix(&pfx1, &options, guard).prefix_meta.len(), 1);
+            /// assert!(store.match_prefix(&pfx2, &options, guard).prefix_meta.is_empty());
+            /// ```
+            pub fn mark_mui_as_stale(&self, mui: u32) -> usize {
+                self.mark_mui_as_stale_with_guard(mui, &epoch::pin())
+            }
+
+            /// Like `mark_mui_as_stale`, but uses the `guard` passed in by
+            /// the caller instead of pinning one for every call.
+            pub fn mark_mui_as_stale_with_guard(
+                &self,
+                mui: u32,
+                guard: &Guard,
+            ) -> usize {
+                let count = self.v4.store.mark_mui_as_stale(mui, guard)
+                    + self.v6.store.mark_mui_as_stale(mui, guard);
+
+                if count > 0 {
+                    self.emit(StoreEvent::MuiStale { mui });
+                }
+                count
+            }
+
+            /// Withdraw all the records that are still Stale, and have a
+            /// logical time before `older_than_ltime`. The best and backup
+            /// path for the prefixes involved are marked as outdated.
+            ///
+            /// Returns the number of records that were withdrawn.
+            pub fn sweep_stale(
+                &self,
+                older_than_ltime: u64
+            ) -> Result<usize, PrefixStoreError> {
+                let guard = &epoch::pin();
+
+                Ok(self.v4.store.sweep_stale(older_than_ltime, guard)?
+                    + self.v6.store.sweep_stale(older_than_ltime, guard)?)
+            }
+
+            /// Remove all the records, for all prefixes, for which `f`
+            /// returns false, e.g. to drop all the routes for an AS, or all
+            /// the routes older than a logical time. The best and backup
+            /// path for the prefixes involved are marked as outdated.
+            ///
+            /// The records of each prefix are locked while `f` runs on them,
+            /// so concurrent readers see the records of a prefix either
+            /// before or after they were filtered. The prefixes themselves
+            /// are never removed from the store, a prefix that lost all its
+            /// records stays in the store without records. No events are
+            /// sent to the subscribers for the removed records.
+            ///
+            /// Returns the number of records that were removed.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let pfx = Prefix::new("185.49.140.0".parse().unwrap(), 22).unwrap();
+            ///
+            /// for (mui, asn) in [(1, 65001), (2, 65002), (3, 65001)] {
+            ///     let record = Record::new(mui, 0, RouteStatus::Active, PrefixAs(asn));
+            ///     store.insert(&pfx, record, None).unwrap();
+            /// }
+            ///
+            /// // Drop everything from AS65001.
+            /// assert_eq!(store.retain(|_, rec| rec.meta.0 != 65001), 2);
+            /// assert_eq!(store.records_count(), 1);
+            /// ```
+            pub fn retain(
+                &self,
+                mut f: impl FnMut(&Prefix, &Record<M>) -> bool
+            ) -> usize {
+                let guard = &epoch::pin();
+
+                self.v4.store.retain_records(
+                    |id, rec| f(&id.into_pub(), rec),
+                    guard
+                ) + self.v6.store.retain_records(
+                    |id, rec| f(&id.into_pub(), rec),
+                    guard
+                )
+            }
+
+            /// Lock all the records for the specified prefix and run the
+            /// closure `f` on a [MultiMapView] on them. Concurrent inserts
+            /// for this prefix will wait until `f` returns, so `f` can read,
+            /// transform and write back the records without racing them.
+            ///
+            /// Only records for `multi_uniq_id`s that already exist for the
+            /// prefix can be modified. If `f` modifies any record, the
+            /// stored best and backup path for the prefix are marked as
+            /// outdated.
+            ///
+            /// Returns the result of `f`, or a
+            /// `PrefixStoreError::PrefixNotFound` error if the prefix does
+            /// not exist in the store.
+            pub fn with_prefix_records<R>(
+                &self,
+                prefix: &Prefix,
+                f: impl FnOnce(&mut MultiMapView<M>) -> R
+            ) -> Result<R, PrefixStoreError> {
+                self.check_af(prefix)?;
+                let guard = &epoch::pin();
+                match prefix.addr() {
+                    std::net::IpAddr::V4(addr) => {
+                        self.v4.store.with_prefix_records(
+                            PrefixId::<IPv4>::from(*prefix),
+                            f,
+                            guard
+                        )
+                    }
+                    std::net::IpAddr::V6(addr) => {
+                        self.v6.store.with_prefix_records(
+                            PrefixId::<IPv6>::from(*prefix),
+                            f,
+                            guard
+                        )
+                    }
+                }
+            }
+
+            /// Set an opaque attribute for `prefix`, replacing the attribute
+            /// that was set before, if any.
+            ///
+            /// The attribute belongs to the prefix itself, not to any of its
+            /// records, so data that is the same for all the records of a
+            /// prefix (e.g. its ROA validation state) doesn't have to be
+            /// duplicated into the meta-data of every record. The store
+            /// doesn't interpret the bytes in any way.
+            ///
+            /// The prefix has to be in the store, otherwise a
+            /// `PrefixStoreError::PrefixNotFound` error is returned.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let pfx = Prefix::new("185.49.140.0".parse().unwrap(), 22).unwrap();
+            ///
+            /// assert_eq!(
+            ///     store.set_prefix_attr(&pfx, vec![1]),
+            ///     Err(PrefixStoreError::PrefixNotFound)
+            /// );
+            ///
+            /// let record = Record::new(1, 0, RouteStatus::Active, PrefixAs(211321));
+            /// store.insert(&pfx, record, None).unwrap();
+            /// assert_eq!(store.get_prefix_attr(&pfx), None);
+            ///
+            /// store.set_prefix_attr(&pfx, vec![1, 2, 3]).unwrap();
+            /// assert_eq!(store.get_prefix_attr(&pfx), Some(vec![1, 2, 3]));
             /// ```
-            pub fn prefixes_iter_v6(
-                &'a self,
-            ) -> impl Iterator<Item=PrefixRecord<M>> + 'a {
-                self.v6.store.prefixes_iter()
-                    .map(|p| PrefixRecord::from(p))
-            }
-
-            /// Change the local status of the record for the combination of
-            /// (prefix, multi_uniq_id) to Withdrawn. Note that by default the
-            /// global `Withdrawn` status for a mui overrides the local status
-            /// of a record.
-            pub fn mark_mui_as_withdrawn_for_prefix(
+            pub fn set_prefix_attr(
                 &self,
                 prefix: &Prefix,
-                mui: u32
+                attr: Vec<u8>,
             ) -> Result<(), PrefixStoreError> {
                 let guard = &epoch::pin();
+
                 match prefix.addr() {
-                    std::net::IpAddr::V4(addr) => {
-                        self.v4.store.mark_mui_as_withdrawn_for_prefix(
+                    std::net::IpAddr::V4(_) => {
+                        self.v4.store.set_prefix_attr(
                             PrefixId::<IPv4>::from(*prefix),
-                            mui,
-                            // &guard
+                            attr,
+                            guard
                         )
                     }
-                    std::net::IpAddr::V6(addr) => {
-                        self.v6.store.mark_mui_as_withdrawn_for_prefix(
+                    std::net::IpAddr::V6(_) => {
+                        self.v6.store.set_prefix_attr(
                             PrefixId::<IPv6>::from(*prefix),
-                            mui,
-                            // &guard
+                            attr,
+                            guard
                         )
                     }
                 }
             }
 
-            /// Change the local status of the record for the combination of
-            /// (prefix, multi_uniq_id) to Active. Note that by default the
-            /// global `Withdrawn` status for a mui overrides the local status
-            /// of a record.
-            pub fn mark_mui_as_active_for_prefix(
-                &self,
-                prefix: &Prefix,
-                mui: u32
-            ) -> Result<(), PrefixStoreError> {
+            /// Returns the opaque attribute for `prefix` that was set with
+            /// `set_prefix_attr`, or None if the prefix is not in the store,
+            /// or no attribute was set for it.
+            pub fn get_prefix_attr(&self, prefix: &Prefix) -> Option<Vec<u8>> {
                 let guard = &epoch::pin();
+
                 match prefix.addr() {
-                    std::net::IpAddr::V4(addr) => {
-                        self.v4.store.mark_mui_as_active_for_prefix(
+                    std::net::IpAddr::V4(_) => {
+                        self.v4.store.get_prefix_attr(
                             PrefixId::<IPv4>::from(*prefix),
-                            mui,
-                            // &guard
+                            guard
                         )
                     }
-                    std::net::IpAddr::V6(addr) => {
-                        self.v6.store.mark_mui_as_active_for_prefix(
+                    std::net::IpAddr::V6(_) => {
+                        self.v6.store.get_prefix_attr(
                             PrefixId::<IPv6>::from(*prefix),
-                            mui,
-                            // &guard
+                            guard
+                        )
+                    }
+                }
+            }
+
+            /// Returns the highest `ltime` of all the records for `prefix`,
+            /// or None if the prefix is not in the store.
+            ///
+            /// All records are considered, regardless of their (local or
+            /// global) status. Note that the `ltime` is the logical time the
+            /// caller set on the records, changing the status of a record
+            /// with one of the `mark_mui_*` methods does not change it.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let pfx = Prefix::new("185.49.140.0".parse().unwrap(), 22).unwrap();
+            ///
+            /// assert_eq!(store.last_modified(&pfx), None);
+            ///
+            /// for (mui, ltime) in [(1, 10), (2, 30), (3, 20)] {
+            ///     let record = Record::new(mui, ltime, RouteStatus::Active, PrefixAs(211321));
+            ///     store.insert(&pfx, record, None).unwrap();
+            /// }
+            ///
+            /// assert_eq!(store.last_modified(&pfx), Some(30));
+            /// ```
+            pub fn last_modified(&self, prefix: &Prefix) -> Option<u64> {
+                match prefix.addr() {
+                    std::net::IpAddr::V4(_) => {
+                        self.v4.store.get_last_modified(
+                            PrefixId::<IPv4>::from(*prefix)
+                        )
+                    }
+                    std::net::IpAddr::V6(_) => {
+                        self.v6.store.get_last_modified(
+                            PrefixId::<IPv6>::from(*prefix)
                         )
                     }
                 }
             }
 
+            /// Returns the records of the IPv4 default route (0.0.0.0/0),
+            /// or None if it is not in the store.
+            ///
+            /// The records are read directly from where the default route
+            /// is stored, without going through `match_prefix`. All
+            /// records are returned, the status of the records for globally
+            /// withdrawn `multi_uniq_id`s is set to `Withdrawn`.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let guard = &epoch::pin();
+            ///
+            /// assert!(store.default_route_v4(guard).is_none());
+            ///
+            /// let record = Record::new(1, 0, RouteStatus::Active, PrefixAs(211321));
+            /// store.insert(&"0.0.0.0/0".parse().unwrap(), record, None).unwrap();
+            ///
+            /// let records = store.default_route_v4(guard).unwrap();
+            /// assert_eq!(records.len(), 1);
+            /// assert_eq!(records[0].meta, PrefixAs(211321));
+            /// assert!(store.default_route_v6(guard).is_none());
+            /// ```
+            pub fn default_route_v4(
+                &'a self,
+                guard: &Guard
+            ) -> Option<Vec<Record<M>>> {
+                self.v4.store.get_default_route(guard)
+            }
+
+            /// Returns the records of the IPv6 default route (::/0), or None
+            /// if it is not in the store.
+            ///
+            /// See `default_route_v4`.
+            pub fn default_route_v6(
+                &'a self,
+                guard: &Guard
+            ) -> Option<Vec<Record<M>>> {
+                self.v6.store.get_default_route(guard)
+            }
+
             /// Change the status of all records for IPv4 prefixes for this
             /// `multi_uniq_id` globally to Active.  Note that the global
             /// `Active` status will be overridden by the local status of the
@@ -1344,12 +3875,33 @@ pub fn create_store(attr: TokenStream, item: TokenStream) -> TokenStream {
                 &self,
                 mui: u32
             ) -> Result<(), PrefixStoreError> {
-                let guard = &epoch::pin();
+                self.mark_mui_as_active_v4_with_guard(mui, &epoch::pin())
+            }
+
+            /// Like `mark_mui_as_active_v4`, but uses the `guard` passed in
+            /// by the caller instead of pinning one for every call.
+            pub fn mark_mui_as_active_v4_with_guard(
+                &self,
+                mui: u32,
+                guard: &Guard,
+            ) -> Result<(), PrefixStoreError> {
+                if !self.config.address_families.has_v4() {
+                    return Err(PrefixStoreError::AddressFamilyDisabled);
+                }
 
-                self.v4.store.mark_mui_as_active(
+                let changed = self.v4.store.mark_mui_as_active(
                     mui,
-                    &guard
-                )
+                    guard
+                )?;
+
+                if changed {
+                    self.emit(StoreEvent::MuiActivated {
+                        mui,
+                        ipv4: true,
+                        ipv6: false,
+                    });
+                }
+                Ok(())
             }
 
             /// Change the status of all records for IPv4 prefixes for this
@@ -1362,12 +3914,52 @@ pub fn create_store(attr: TokenStream, item: TokenStream) -> TokenStream {
                 &self,
                 mui: u32
             ) -> Result<(), PrefixStoreError> {
-                let guard = &epoch::pin();
+                self.mark_mui_as_withdrawn_v4_with_guard(mui, &epoch::pin())
+            }
+
+            /// Like `mark_mui_as_withdrawn_v4`, but uses the `guard` passed in
+            /// by the caller instead of pinning one for every call. Useful
+            /// when marking many `multi_uniq_id`s in one go.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let guard = &epoch::pin();
+            ///
+            /// for mui in 1..=10 {
+            ///     store.mark_mui_as_withdrawn_v4_with_guard(mui, guard).unwrap();
+            /// }
+            /// assert!(store.mui_is_withdrawn_v4(7));
+            ///
+            /// store.mark_mui_as_active_v4_with_guard(7, guard).unwrap();
+            /// assert!(!store.mui_is_withdrawn_v4(7));
+            /// ```
+            pub fn mark_mui_as_withdrawn_v4_with_guard(
+                &self,
+                mui: u32,
+                guard: &Guard,
+            ) -> Result<(), PrefixStoreError> {
+                if !self.config.address_families.has_v4() {
+                    return Err(PrefixStoreError::AddressFamilyDisabled);
+                }
 
-                self.v4.store.mark_mui_as_withdrawn(
+                let changed = self.v4.store.mark_mui_as_withdrawn(
                     mui,
-                    &guard
-                )
+                    guard
+                )?;
+
+                if changed {
+                    self.emit(StoreEvent::MuiWithdrawn {
+                        mui,
+                        ipv4: true,
+                        ipv6: false,
+                    });
+                }
+                Ok(())
             }
 
             /// Change the status of all records for IPv6 prefixes for this
@@ -1378,12 +3970,33 @@ pub fn create_store(attr: TokenStream, item: TokenStream) -> TokenStream {
                 &self,
                 mui: u32
             ) -> Result<(), PrefixStoreError> {
-                let guard = &epoch::pin();
+                self.mark_mui_as_active_v6_with_guard(mui, &epoch::pin())
+            }
+
+            /// Like `mark_mui_as_active_v6`, but uses the `guard` passed in
+            /// by the caller instead of pinning one for every call.
+            pub fn mark_mui_as_active_v6_with_guard(
+                &self,
+                mui: u32,
+                guard: &Guard,
+            ) -> Result<(), PrefixStoreError> {
+                if !self.config.address_families.has_v6() {
+                    return Err(PrefixStoreError::AddressFamilyDisabled);
+                }
 
-                self.v6.store.mark_mui_as_active(
+                let changed = self.v6.store.mark_mui_as_active(
                     mui,
-                    &guard
-                )
+                    guard
+                )?;
+
+                if changed {
+                    self.emit(StoreEvent::MuiActivated {
+                        mui,
+                        ipv6: true,
+                        ipv4: false,
+                    });
+                }
+                Ok(())
             }
 
             /// Change the status of all records for IPv6 prefixes for this
@@ -1396,14 +4009,144 @@ pub fn create_store(attr: TokenStream, item: TokenStream) -> TokenStream {
                 &self,
                 mui: u32
             ) -> Result<(), PrefixStoreError> {
-                let guard = &epoch::pin();
+                self.mark_mui_as_withdrawn_v6_with_guard(mui, &epoch::pin())
+            }
+
+            /// Like `mark_mui_as_withdrawn_v6`, but uses the `guard` passed
+            /// in by the caller instead of pinning one for every call.
+            pub fn mark_mui_as_withdrawn_v6_with_guard(
+                &self,
+                mui: u32,
+                guard: &Guard,
+            ) -> Result<(), PrefixStoreError> {
+                if !self.config.address_families.has_v6() {
+                    return Err(PrefixStoreError::AddressFamilyDisabled);
+                }
+
+                let changed = self.v6.store.mark_mui_as_withdrawn(
+                    mui,
+                    guard
+                )?;
+
+                if changed {
+                    self.emit(StoreEvent::MuiWithdrawn {
+                        mui,
+                        ipv6: true,
+                        ipv4: false,
+                    });
+                }
+                Ok(())
+            }
+
+            /// Change the status of all records for IPv4 prefixes for this
+            /// `multi_uniq_id` globally to Withdrawn, like
+            /// `mark_mui_as_withdrawn_v4`, and remember `ltime` as the
+            /// logical time of the withdrawal.
+            ///
+            /// The time can be retrieved with `mui_withdrawn_at_v4`, until
+            /// the `multi_uniq_id` is marked as active again.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            ///
+            /// store.mark_mui_as_withdrawn_at_v4(1, 100).unwrap();
+            /// store.mark_mui_as_withdrawn_v4(2).unwrap();
+            ///
+            /// assert_eq!(store.mui_withdrawn_at_v4(1), Some(100));
+            /// assert_eq!(store.mui_withdrawn_at_v6(1), None);
+            /// // Withdrawn, but at an unknown time.
+            /// assert!(store.mui_is_withdrawn_v4(2));
+            /// assert_eq!(store.mui_withdrawn_at_v4(2), None);
+            ///
+            /// store.mark_mui_as_active_v4(1).unwrap();
+            /// assert_eq!(store.mui_withdrawn_at_v4(1), None);
+            /// ```
+            pub fn mark_mui_as_withdrawn_at_v4(
+                &self,
+                mui: u32,
+                ltime: u64,
+            ) -> Result<(), PrefixStoreError> {
+                self.mark_mui_as_withdrawn_at_v4_with_guard(
+                    mui,
+                    ltime,
+                    &epoch::pin()
+                )
+            }
+
+            /// Like `mark_mui_as_withdrawn_at_v4`, but uses the `guard`
+            /// passed in by the caller instead of pinning one for every call.
+            pub fn mark_mui_as_withdrawn_at_v4_with_guard(
+                &self,
+                mui: u32,
+                ltime: u64,
+                guard: &Guard,
+            ) -> Result<(), PrefixStoreError> {
+                if !self.config.address_families.has_v4() {
+                    return Err(PrefixStoreError::AddressFamilyDisabled);
+                }
+
+                let changed =
+                    self.v4.store.mark_mui_as_withdrawn_at(mui, ltime, guard)?;
+
+                if changed {
+                    self.emit(StoreEvent::MuiWithdrawn {
+                        mui,
+                        ipv4: true,
+                        ipv6: false,
+                    });
+                }
+                Ok(())
+            }
 
-                self.v6.store.mark_mui_as_withdrawn(
+            /// Change the status of all records for IPv6 prefixes for this
+            /// `multi_uniq_id` globally to Withdrawn, like
+            /// `mark_mui_as_withdrawn_v6`, and remember `ltime` as the
+            /// logical time of the withdrawal.
+            ///
+            /// The time can be retrieved with `mui_withdrawn_at_v6`, until
+            /// the `multi_uniq_id` is marked as active again.
+            pub fn mark_mui_as_withdrawn_at_v6(
+                &self,
+                mui: u32,
+                ltime: u64,
+            ) -> Result<(), PrefixStoreError> {
+                self.mark_mui_as_withdrawn_at_v6_with_guard(
                     mui,
-                    &guard
+                    ltime,
+                    &epoch::pin()
                 )
             }
 
+            /// Like `mark_mui_as_withdrawn_at_v6`, but uses the `guard`
+            /// passed in by the caller instead of pinning one for every call.
+            pub fn mark_mui_as_withdrawn_at_v6_with_guard(
+                &self,
+                mui: u32,
+                ltime: u64,
+                guard: &Guard,
+            ) -> Result<(), PrefixStoreError> {
+                if !self.config.address_families.has_v6() {
+                    return Err(PrefixStoreError::AddressFamilyDisabled);
+                }
+
+                let changed =
+                    self.v6.store.mark_mui_as_withdrawn_at(mui, ltime, guard)?;
+
+                if changed {
+                    self.emit(StoreEvent::MuiWithdrawn {
+                        mui,
+                        ipv6: true,
+                        ipv4: false,
+                    });
+                }
+                Ok(())
+            }
+
 
             /// Change the status of all records for this `multi_uniq_id` to
             /// Withdrawn.
@@ -1415,22 +4158,247 @@ pub fn create_store(attr: TokenStream, item: TokenStream) -> TokenStream {
             pub fn mark_mui_as_withdrawn(
                 &self,
                 mui: u32
+            ) -> Result<(), PrefixStoreError> {
+                self.mark_mui_as_withdrawn_with_guard(mui, &epoch::pin())
+            }
+
+            /// Like `mark_mui_as_withdrawn`, but uses the `guard` passed in
+            /// by the caller instead of pinning one for every call.
+            pub fn mark_mui_as_withdrawn_with_guard(
+                &self,
+                mui: u32,
+                guard: &Guard,
+            ) -> Result<(), PrefixStoreError> {
+                let res_v4 = self.v4.store.mark_mui_as_withdrawn(
+                    mui,
+                    guard
+                );
+                let res_v6 = self.v6.store.mark_mui_as_withdrawn(
+                    mui,
+                    guard
+                );
+
+                let ipv4 = matches!(res_v4, Ok(true));
+                let ipv6 = matches!(res_v6, Ok(true));
+                if ipv4 || ipv6 {
+                    self.emit(StoreEvent::MuiWithdrawn { mui, ipv4, ipv6 });
+                }
+
+                res_v4.and(res_v6).map(|_| ())
+            }
+
+
+
+            /// Returns a snapshot of the globally withdrawn `multi_uniq_id`s
+            /// for IPv4 and IPv6 respectively, serialized in the native
+            /// RoaringBitmap format.
+            ///
+            /// The snapshot can be restored with `import_withdrawn_muis`.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// store.mark_mui_as_withdrawn_v4(1).unwrap();
+            /// store.mark_mui_as_withdrawn(2).unwrap();
+            ///
+            /// let (v4, v6) = store.export_withdrawn_muis();
+            ///
+            /// let restored = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// restored.import_withdrawn_muis(&v4, &v6).unwrap();
+            ///
+            /// assert!(restored.mui_is_withdrawn_v4(1));
+            /// assert!(!restored.mui_is_withdrawn_v6(1));
+            /// assert!(restored.mui_is_withdrawn_v4(2));
+            /// assert!(restored.mui_is_withdrawn_v6(2));
+            ///
+            /// // Garbage is rejected, and leaves the store as it was.
+            /// assert!(restored.import_withdrawn_muis(&[1, 2, 3], &v6).is_err());
+            /// assert!(restored.mui_is_withdrawn_v4(1));
+            /// ```
+            pub fn export_withdrawn_muis(&self) -> (Vec<u8>, Vec<u8>) {
+                let guard = &epoch::pin();
+
+                (
+                    self.v4.store.withdrawn_muis_to_bytes(guard),
+                    self.v6.store.withdrawn_muis_to_bytes(guard),
+                )
+            }
+
+            /// Replace the globally withdrawn `multi_uniq_id`s for IPv4 and
+            /// IPv6 with the snapshots created by `export_withdrawn_muis`.
+            ///
+            /// Both snapshots are decoded before anything is changed, so if
+            /// either of them is invalid, an error is returned and the store
+            /// is left untouched.
+            pub fn import_withdrawn_muis(
+                &self,
+                v4: &[u8],
+                v6: &[u8],
             ) -> Result<(), PrefixStoreError> {
                 let guard = &epoch::pin();
 
-                let res_v4 = self.v4.store.mark_mui_as_withdrawn(
-                    mui,
-                    &guard
-                );
-                let res_v6 = self.v6.store.mark_mui_as_withdrawn(
-                    mui,
-                    &guard
-                );
+                let bmin_v4 = self.v4.store.withdrawn_muis_from_bytes(v4)?;
+                let bmin_v6 = self.v6.store.withdrawn_muis_from_bytes(v6)?;
+
+                self.v4.store.set_withdrawn_muis(bmin_v4, guard);
+                self.v6.store.set_withdrawn_muis(bmin_v6, guard);
+
+                Ok(())
+            }
+
+            /// Writes all the prefixes in the store with all their records,
+            /// and the globally withdrawn `multi_uniq_id`s for IPv4 and
+            /// IPv6, to `w` in a versioned binary format, that can be read
+            /// back with `load_binary`.
+            ///
+            /// The records are written with their own status, and the metas
+            /// as the bytes returned by their `AsRef<[u8]>` implementation.
+            /// The stored best and backup paths, the prefix attributes and
+            /// the times at which `multi_uniq_id`s were withdrawn are not
+            /// written. Prefixes without records are skipped.
+            ///
+            /// Note that this is *not* a point-in-time snapshot of the
+            /// store, records that are inserted or modified while the dump
+            /// is written may or may not be in it.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::NoMeta;
+            ///
+            /// let store = MultiThreadedStore::<NoMeta>::new().unwrap();
+            /// let record = Record::new(1, 10, RouteStatus::Active, NoMeta::Empty);
+            /// store.insert(&"185.49.140.0/22".parse().unwrap(), record.clone(), None).unwrap();
+            /// store.insert(&"2a04:b900::/29".parse().unwrap(), record, None).unwrap();
+            /// store.mark_mui_as_withdrawn_v6(1).unwrap();
+            ///
+            /// let mut dump = vec![];
+            /// store.dump_binary(&mut dump).unwrap();
+            ///
+            /// let restored = MultiThreadedStore::<NoMeta>::load_binary(
+            ///     StoreConfig::default(), &mut dump.as_slice()
+            /// ).unwrap();
+            ///
+            /// assert_eq!(restored.prefixes_count(), 2);
+            /// assert!(restored.mui_is_withdrawn_v6(1));
+            /// ```
+            pub fn dump_binary<W: std::io::Write>(
+                &'a self,
+                w: &mut W,
+            ) -> std::io::Result<()>
+            where
+                M: AsRef<[u8]>,
+            {
+                let (bmin_v4, bmin_v6) = self.export_withdrawn_muis();
+
+                w.write_all(Self::BINARY_DUMP_MAGIC)?;
+                w.write_all(&[Self::BINARY_DUMP_VERSION])?;
+                for bmin in [bmin_v4, bmin_v6] {
+                    w.write_all(&(bmin.len() as u32).to_be_bytes())?;
+                    w.write_all(&bmin)?;
+                }
+
+                self.v4.store.write_prefixes_binary(w)?;
+                self.v6.store.write_prefixes_binary(w)?;
 
-                res_v4.and(res_v6)
+                // The end of the prefix frames.
+                w.write_all(&[0])
             }
 
+            /// Creates a new store with the given [StoreConfig] from a dump
+            /// written by `dump_binary`.
+            ///
+            /// The metas of the records are created from their bytes with
+            /// their `From<Vec<u8>>` implementation. The records are
+            /// inserted without updating the path selections, and without
+            /// sending [StoreEvent]s. Withdrawn records are always
+            /// restored, regardless of `ignore_withdrawal_of_unknown`.
+            ///
+            /// Returns an error if the dump is truncated or invalid, or if
+            /// it contains prefixes that the config doesn't accept.
+            pub fn load_binary<R: std::io::Read>(
+                config: StoreConfig,
+                r: &mut R,
+            ) -> Result<Self, Box<dyn std::error::Error>>
+            where
+                M: From<Vec<u8>>,
+            {
+                use std::io::Read;
+
+                let store = Self::new_with_config(config)?;
+
+                let mut header = [0; 5];
+                r.read_exact(&mut header)?;
+                if &header[..4] != Self::BINARY_DUMP_MAGIC
+                    || header[4] != Self::BINARY_DUMP_VERSION
+                {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "not a binary store dump, or an unsupported version",
+                    ).into());
+                }
+
+                let mut bmins = vec![];
+                for _ in 0..2 {
+                    let mut len = [0; 4];
+                    r.read_exact(&mut len)?;
+                    let len = u32::from_be_bytes(len) as u64;
+                    let mut bmin = vec![];
+                    r.by_ref().take(len).read_to_end(&mut bmin)?;
+                    if bmin.len() as u64 != len {
+                        return Err(std::io::Error::from(
+                            std::io::ErrorKind::UnexpectedEof
+                        ).into());
+                    }
+                    bmins.push(bmin);
+                }
+                store.import_withdrawn_muis(&bmins[0], &bmins[1])?;
+
+                loop {
+                    let mut tag = [0; 1];
+                    r.read_exact(&mut tag)?;
+                    let (prefix, records) = match tag[0] {
+                        0 => break,
+                        32 => store.v4.store.read_prefix_binary(r)?,
+                        128 => store.v6.store.read_prefix_binary(r)?,
+                        _ => {
+                            return Err(std::io::Error::new(
+                                std::io::ErrorKind::InvalidData,
+                                "invalid prefix frame tag",
+                            ).into());
+                        }
+                    };
+
+                    store.check_af(&prefix)?;
+                    if !store.config.accepts_len(&prefix) {
+                        return Err(
+                            PrefixStoreError::PrefixLengthNotAccepted.into()
+                        );
+                    }
+
+                    for record in records {
+                        match prefix.addr() {
+                            std::net::IpAddr::V4(_) => store.v4.insert(
+                                PrefixId::<IPv4>::from(prefix),
+                                record,
+                                None,
+                            )?,
+                            std::net::IpAddr::V6(_) => store.v6.insert(
+                                PrefixId::<IPv6>::from(prefix),
+                                record,
+                                None,
+                            )?,
+                        };
+                    }
+                }
 
+                Ok(store)
+            }
 
             // Whether the global status for IPv4 prefixes and the specified
             // `multi_uniq_id` is set to `Withdrawn`.
@@ -1454,6 +4422,32 @@ pub fn create_store(attr: TokenStream, item: TokenStream) -> TokenStream {
                 self.v6.store.mui_is_withdrawn(mui, guard)
             }
 
+            /// Returns the logical time at which the specified
+            /// `multi_uniq_id` was globally withdrawn for IPv4 prefixes with
+            /// `mark_mui_as_withdrawn_at_v4`.
+            ///
+            /// Returns None if the `multi_uniq_id` isn't globally withdrawn,
+            /// or if it was withdrawn without a time, e.g. with
+            /// `mark_mui_as_withdrawn_v4`. The snapshots of
+            /// `export_withdrawn_muis` don't include the times.
+            pub fn mui_withdrawn_at_v4(&self, mui: u32) -> Option<u64> {
+                let guard = &epoch::pin();
+
+                self.v4.store.mui_withdrawn_at(mui, guard)
+            }
+
+            /// Returns the logical time at which the specified
+            /// `multi_uniq_id` was globally withdrawn for IPv6 prefixes with
+            /// `mark_mui_as_withdrawn_at_v6`.
+            ///
+            /// Returns None if the `multi_uniq_id` isn't globally withdrawn,
+            /// or if it was withdrawn without a time.
+            pub fn mui_withdrawn_at_v6(&self, mui: u32) -> Option<u64> {
+                let guard = &epoch::pin();
+
+                self.v6.store.mui_withdrawn_at(mui, guard)
+            }
+
             /// Returns the number of all prefixes in the store.
             ///
             /// Note that this method will actually traverse the complete
@@ -1463,6 +4457,226 @@ pub fn create_store(attr: TokenStream, item: TokenStream) -> TokenStream {
                 + self.v6.store.get_prefixes_count()
             }
 
+            /// Returns the number of all prefixes in the store, counted from
+            /// the tree itself instead of read from the counters.
+            ///
+            /// The counters that `prefixes_count` and the
+            /// `prefixes_*_count` methods read are updated after a prefix
+            /// is created, so they can lag behind under contention. This
+            /// method counts the prefixes in all the nodes of the tree, so
+            /// it is exact for all the prefixes that were inserted before it
+            /// was called. Prefixes that are inserted concurrently may or may
+            /// not be included. It is a lot slower than `prefixes_count`,
+            /// since it visits every node in the tree.
+            ///
+            /// This method doesn't take a `Guard`. It only reads the bitmaps
+            /// of the nodes, and nodes are never removed from the store, so
+            /// there is no memory that a pinned epoch would have to keep
+            /// alive. Pinning an epoch would not stop concurrent inserts
+            /// either, so it wouldn't make the count any more exact.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let record = Record::new(1, 0, RouteStatus::Active, PrefixAs(211321));
+            ///
+            /// for pfx in ["0.0.0.0/0", "185.49.140.0/22", "2a04:b900::/29"] {
+            ///     store.insert(&pfx.parse().unwrap(), record.clone(), None).unwrap();
+            /// }
+            ///
+            /// assert_eq!(store.exact_prefixes_count(), 3);
+            /// ```
+            pub fn exact_prefixes_count(&'a self) -> usize {
+                self.v4.store.get_exact_prefixes_count()
+                + self.v6.store.get_exact_prefixes_count()
+            }
+
+            /// Returns the number of all records in the store.
+            ///
+            /// Each prefix holds a record for every `multi_uniq_id` it was
+            /// inserted with, so in a store that is fed by many peers this
+            /// number may be many times larger than `prefixes_count`.
+            /// Records for withdrawn `multi_uniq_id`s, or with a local
+            /// withdrawn status are included in the count.
+            ///
+            /// Note that this method will actually traverse all the
+            /// prefixes in the store.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let pfx = Prefix::new("185.49.140.0".parse().unwrap(), 22).unwrap();
+            ///
+            /// for mui in 1..=3 {
+            ///     let record = Record::new(mui, 0, RouteStatus::Active, PrefixAs(211321));
+            ///     store.insert(&pfx, record, None).unwrap();
+            /// }
+            ///
+            /// assert_eq!(store.prefixes_count(), 1);
+            /// assert_eq!(store.records_count(), 3);
+            /// ```
+            pub fn records_count(&'a self) -> usize {
+                self.v4.store.get_records_count()
+                + self.v6.store.get_records_count()
+            }
+
+            /// Returns an unordered iterator over all the prefixes, for both
+            /// IPv4 and IPv6, that have more than `threshold` records,
+            /// together with their number of records.
+            ///
+            /// Records for withdrawn `multi_uniq_id`s, or with a local
+            /// withdrawn status are included in the count, like in
+            /// `records_count`. A prefix with many records is heavily
+            /// multi-homed, or is announced (and withdrawn) by many peers.
+            ///
+            /// Note that this method will actually traverse all the
+            /// prefixes in the store, and that the count for a prefix that
+            /// is updated concurrently may be outdated.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let pfx1 = Prefix::new("185.49.140.0".parse().unwrap(), 22).unwrap();
+            /// let pfx2 = Prefix::new("185.49.140.0".parse().unwrap(), 23).unwrap();
+            ///
+            /// for mui in 1..=3 {
+            ///     let record = Record::new(mui, 0, RouteStatus::Active, PrefixAs(211321));
+            ///     store.insert(&pfx1, record.clone(), None).unwrap();
+            ///     if mui == 1 {
+            ///         store.insert(&pfx2, record, None).unwrap();
+            ///     }
+            /// }
+            ///
+            /// let hot = store.prefixes_with_record_count_over(1)
+            ///     .collect::<Vec<_>>();
+            /// assert_eq!(hot, vec![(pfx1, 3)]);
+            /// ```
+            pub fn prefixes_with_record_count_over(
+                &'a self,
+                threshold: usize,
+            ) -> impl Iterator<Item=(Prefix, usize)> + 'a {
+                self.v4.store.prefixes_with_record_count_over(threshold)
+                    .chain(
+                        self.v6.store.prefixes_with_record_count_over(threshold)
+                    )
+            }
+
+            /// Returns the differences between the records in this store
+            /// and the records in `other`.
+            ///
+            /// The records are compared on their `multi_uniq_id`, `ltime`,
+            /// local status and meta-data. The global status of the
+            /// `multi_uniq_id`s is not compared, and prefixes without any
+            /// records are treated as absent. Use `is_empty` on the result
+            /// to check whether the stores hold the same records.
+            ///
+            /// Note that this method will actually traverse all the
+            /// prefixes in both stores, and holds all the records of
+            /// `other` in memory while comparing.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let other = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let pfx1 = Prefix::new("185.49.140.0".parse().unwrap(), 22).unwrap();
+            /// let pfx2 = Prefix::new("185.49.140.0".parse().unwrap(), 23).unwrap();
+            /// let record = Record::new(1, 0, RouteStatus::Active, PrefixAs(211321));
+            ///
+            /// for s in [&store, &other] {
+            ///     s.insert(&pfx1, record.clone(), None).unwrap();
+            /// }
+            /// assert!(store.diff(&other).is_empty());
+            ///
+            /// store.insert(&pfx2, record.clone(), None).unwrap();
+            /// other.insert(
+            ///     &pfx1,
+            ///     Record::new(1, 1, RouteStatus::Active, PrefixAs(211321)),
+            ///     None
+            /// ).unwrap();
+            ///
+            /// let diff = store.diff(&other);
+            /// assert_eq!(diff.only_in_self.len(), 1);
+            /// assert_eq!(diff.only_in_self[0].prefix, pfx2);
+            /// assert!(diff.only_in_other.is_empty());
+            /// assert_eq!(diff.differing.len(), 1);
+            /// assert_eq!(diff.differing[0].prefix, pfx1);
+            /// ```
+            pub fn diff(&'a self, other: &'a Self) -> StoreDiff<M>
+            where
+                M: PartialEq,
+            {
+                let mut other_pfxs = other.prefixes_iter()
+                    .filter(|p| !p.meta.is_empty())
+                    .map(|p| (p.prefix, p.meta))
+                    .collect::<std::collections::HashMap<_, _>>();
+
+                let mut diff = StoreDiff {
+                    only_in_self: vec![],
+                    only_in_other: vec![],
+                    differing: vec![],
+                };
+
+                for mut p in self.prefixes_iter() {
+                    if p.meta.is_empty() {
+                        continue;
+                    }
+                    p.meta.sort_by_key(|r| r.multi_uniq_id);
+
+                    match other_pfxs.remove(&p.prefix) {
+                        Some(mut other_recs) => {
+                            other_recs.sort_by_key(|r| r.multi_uniq_id);
+                            let equal = p.meta.len() == other_recs.len()
+                                && p.meta.iter().zip(other_recs.iter()).all(
+                                    |(a, b)| {
+                                        a.multi_uniq_id == b.multi_uniq_id
+                                            && a.ltime == b.ltime
+                                            && a.status == b.status
+                                            && a.meta == b.meta
+                                    }
+                                );
+                            if !equal {
+                                diff.differing.push(DifferingPrefix {
+                                    prefix: p.prefix,
+                                    in_self: p.meta,
+                                    in_other: other_recs,
+                                });
+                            }
+                        }
+                        None => diff.only_in_self.push(p),
+                    }
+                }
+
+                diff.only_in_other = other_pfxs
+                    .into_iter()
+                    .map(|(prefix, mut meta)| {
+                        meta.sort_by_key(|r| r.multi_uniq_id);
+                        PrefixRecord { prefix, meta }
+                    })
+                    .collect();
+
+                diff.only_in_self.sort_by_key(|p| p.prefix);
+                diff.only_in_other.sort_by_key(|p| p.prefix);
+                diff.differing.sort_by_key(|d| d.prefix);
+
+                diff
+            }
+
             /// Returns the number of all IPv4 prefixes in the store.
             ///
             /// Note that this counter may be lower than the actual
@@ -1482,6 +4696,33 @@ pub fn create_store(attr: TokenStream, item: TokenStream) -> TokenStream {
                 self.v4.store.get_prefixes_count_for_len(len)
             }
 
+            /// Returns the IPv4 prefix lengths that have at least one
+            /// prefix in the store, in ascending order.
+            ///
+            /// This is read from the counters, so it is cheap, but it may
+            /// miss a length that got its first prefix just now, due to
+            /// contention at the time of reading the counters. Prefixes are
+            /// never removed from the store, so a length never disappears.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// for pfx in ["0.0.0.0/0", "185.49.140.0/22", "185.49.140.0/24"] {
+            ///     let record = Record::new(1, 0, RouteStatus::Active, PrefixAs(211321));
+            ///     store.insert(&pfx.parse().unwrap(), record, None).unwrap();
+            /// }
+            ///
+            /// assert_eq!(store.populated_lengths_v4(), vec![0, 22, 24]);
+            /// assert!(store.populated_lengths_v6().is_empty());
+            /// ```
+            pub fn populated_lengths_v4(&self) -> Vec<u8> {
+                self.v4.store.get_populated_lengths()
+            }
+
             /// Returns the number of all IPv6 prefixes in the store.
             ///
             /// Note that this counter may be lower than the actual
@@ -1501,6 +4742,17 @@ pub fn create_store(attr: TokenStream, item: TokenStream) -> TokenStream {
                 self.v6.store.get_prefixes_count_for_len(len)
             }
 
+            /// Returns the IPv6 prefix lengths that have at least one
+            /// prefix in the store, in ascending order.
+            ///
+            /// This is read from the counters, so it is cheap, but it may
+            /// miss a length that got its first prefix just now, due to
+            /// contention at the time of reading the counters. Prefixes are
+            /// never removed from the store, so a length never disappears.
+            pub fn populated_lengths_v6(&self) -> Vec<u8> {
+                self.v6.store.get_populated_lengths()
+            }
+
             /// Returns the number of nodes in the store.
             ///
             /// Note that this counter may be lower than the actual
@@ -1529,7 +4781,51 @@ pub fn create_store(attr: TokenStream, item: TokenStream) -> TokenStream {
                 self.v6.store.get_nodes_count()
             }
 
-            /// Print the store statistics to the standard output.
+            /// Returns the number of IPv4 nodes in the store for every
+            /// length that nodes are stored for, i.e. the lengths at which
+            /// the strides start, in ascending order.
+            ///
+            /// Unlike `nodes_v4_count` this traverses all the nodes in the
+            /// store, so the numbers add up to the actual number of nodes,
+            /// apart from nodes that are created concurrently.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// for pfx in ["185.49.140.0/22", "185.49.140.0/24", "10.0.0.0/8"] {
+            ///     let record = Record::new(1, 0, RouteStatus::Active, PrefixAs(211321));
+            ///     store.insert(&pfx.parse().unwrap(), record, None).unwrap();
+            /// }
+            ///
+            /// let counts = store.nodes_v4_count_per_len();
+            /// assert_eq!(counts[0], (0, 1));
+            /// assert!(counts.windows(2).all(|w| w[0].0 < w[1].0));
+            /// assert_eq!(
+            ///     counts.iter().map(|(_, c)| c).sum::<usize>(),
+            ///     store.nodes_v4_count()
+            /// );
+            /// ```
+            pub fn nodes_v4_count_per_len(&self) -> Vec<(u8, usize)> {
+                self.v4.store.nodes_count_per_len()
+            }
+
+            /// Returns the number of IPv6 nodes in the store for every
+            /// length that nodes are stored for, i.e. the lengths at which
+            /// the strides start, in ascending order.
+            ///
+            /// Unlike `nodes_v6_count` this traverses all the nodes in the
+            /// store, so the numbers add up to the actual number of nodes,
+            /// apart from nodes that are created concurrently.
+            pub fn nodes_v6_count_per_len(&self) -> Vec<(u8, usize)> {
+                self.v6.store.nodes_count_per_len()
+            }
+
+            /// Print the store statistics to the standard output, as
+            /// reported by [stats_report](Self::stats_report).
             #[cfg(feature = "cli")]
             pub fn print_funky_stats(&self) {
                 println!("");
@@ -1544,8 +4840,270 @@ pub fn create_store(attr: TokenStream, item: TokenStream) -> TokenStream {
                 StoreStats {
                     v4: self.v4.store.counters.get_prefix_stats(),
                     v6: self.v6.store.counters.get_prefix_stats(),
+                    fatal_node_errors: self.v4.store.counters
+                        .get_fatal_node_errors_count()
+                        + self.v6.store.counters
+                        .get_fatal_node_errors_count(),
+                }
+            }
+
+            /// Returns the [InternerStats] of the interner of the
+            /// meta-data of the records, that is shared by both address
+            /// families. Returns None if the store was created without
+            /// `intern_meta` in its [StoreConfig].
+            pub fn interner_stats(&self) -> Option<InternerStats> {
+                self.v4
+                    .store
+                    .meta_interner
+                    .as_ref()
+                    .map(|interner| interner.stats())
+            }
+
+            /// Returns a [StatsReport] with the number of nodes and prefixes
+            /// in the store, in total and per length, the occupancy of each
+            /// prefix length, and an estimate of the memory they use, for
+            /// both address families.
+            ///
+            /// Unlike `print_funky_stats`, this doesn't need the `cli`
+            /// feature, and the report can be serialized with the `serde`
+            /// feature, e.g. to export it as metrics. The numbers are
+            /// counted by walking all the buckets and record maps in the
+            /// store, so they don't lag behind like the counters can.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// for pfx in ["185.49.140.0/24", "185.49.141.0/24", "2a04:b900::/29"] {
+            ///     let record = Record::new(1, 0, RouteStatus::Active, PrefixAs(211321));
+            ///     store.insert(&pfx.parse().unwrap(), record, None).unwrap();
+            /// }
+            ///
+            /// let report = store.stats_report();
+            /// assert_eq!(report.v4.prefixes, 2);
+            /// assert_eq!(report.v4.records, 2);
+            /// assert_eq!(report.v6.prefixes, 1);
+            ///
+            /// let len_24 = report.v4.lengths.iter().find(|l| l.len == 24).unwrap();
+            /// assert_eq!(len_24.prefixes, 2);
+            /// assert!(len_24.prefixes_occupied_pct > 0.0);
+            /// assert!(report.v4.memory_bytes > 0);
+            /// ```
+            pub fn stats_report(&'a self) -> StatsReport {
+                StatsReport {
+                    v4: self.v4.store.stats_report(),
+                    v6: self.v6.store.stats_report(),
+                }
+            }
+
+            /// Returns an iterator over the nodes of the tree bitmap, first
+            /// for IPv4, then for IPv6, that walks the tree structurally
+            /// instead of by prefix.
+            ///
+            /// The nodes of each tree are visited depth-first, starting at
+            /// the root node. A node is always returned before its children,
+            /// and its `depth` in the [NodeReport] tells how many nodes are
+            /// above it, so that the returned nodes can be used to render
+            /// the structure of the tree.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let guard = &epoch::pin();
+            ///
+            /// for pfx in ["185.34.0.0/16", "185.34.10.0/24"] {
+            ///     let pfx: Prefix = pfx.parse().unwrap();
+            ///     let record = Record::new(1, 0, RouteStatus::Active, PrefixAs(211321));
+            ///     store.insert(&pfx, record, None).unwrap();
+            /// }
+            ///
+            /// let nodes = store
+            ///     .node_iter(guard)
+            ///     .filter(|n| n.node_id.is_v4())
+            ///     .collect::<Vec<_>>();
+            ///
+            /// // With the default strides the path to the /24 goes through
+            /// // the nodes for /5, /10, /14, /17, /20 and /23.
+            /// let lens = nodes.iter().map(|n| n.len).collect::<Vec<_>>();
+            /// assert_eq!(lens, vec![0, 5, 10, 14, 17, 20, 23]);
+            /// assert!(nodes.iter().enumerate().all(|(i, n)| n.depth == i));
+            ///
+            /// // The /14 node hosts the /16 and has one child.
+            /// assert_eq!(nodes[3].node_id, "185.32.0.0/14".parse().unwrap());
+            /// assert_eq!(nodes[3].pfx_count, 1);
+            /// assert_eq!(nodes[3].child_count, 1);
+            /// assert_eq!(nodes[6].child_count, 0);
+            /// ```
+            pub fn node_iter(
+                &'a self,
+                guard: &'a Guard
+            ) -> impl Iterator<Item = NodeReport> + 'a {
+                self.v4.store.node_iter().chain(self.v6.store.node_iter())
+            }
+
+            /// Returns the occupancy of the buckets that the nodes in the
+            /// store live in, per length of the node ids and level in the
+            /// chains of buckets, for both address families.
+            ///
+            /// The buckets are sparse arrays that are allocated as a whole,
+            /// so a low fill factor means allocated, but unused, memory.
+            /// This may help to decide on the stride sizes and the number of
+            /// root bits of a store. This method walks all the node buckets
+            /// in the store.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// for pfx in ["185.49.140.0/24", "185.49.141.0/24", "10.0.0.0/24"] {
+            ///     let record = Record::new(1, 0, RouteStatus::Active, PrefixAs(211321));
+            ///     store.insert(&pfx.parse().unwrap(), record, None).unwrap();
+            /// }
+            ///
+            /// let fills = store.fill_factors();
+            ///
+            /// // The root node is the only node in the root array for its
+            /// // length.
+            /// let root = fills.v4.iter().find(|f| f.len == 0).unwrap();
+            /// assert_eq!((root.level, root.buckets, root.occupied), (0, 1, 1));
+            /// assert!(fills.v4.iter().all(|f| f.fill_factor() <= 1.0));
+            /// ```
+            pub fn fill_factors(&self) -> FillFactors {
+                FillFactors {
+                    v4: self.v4.store.fill_factors(),
+                    v6: self.v6.store.fill_factors(),
+                }
+            }
+
+            /// Returns the depths of the chains of buckets that the nodes
+            /// and the prefixes in the store live in, per length, for both
+            /// address families.
+            ///
+            /// Prefixes (and nodes) of the same length whose first bits
+            /// collide in the root array for that length are stored in
+            /// chained buckets. These statistics show how deep these chains
+            /// get in practice, e.g. to compare stride sizes. This method
+            /// walks all the buckets in the store.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// for pfx in ["185.49.140.0/24", "185.49.141.0/24", "10.0.0.0/24"] {
+            ///     let record = Record::new(1, 0, RouteStatus::Active, PrefixAs(211321));
+            ///     store.insert(&pfx.parse().unwrap(), record, None).unwrap();
+            /// }
+            ///
+            /// let stats = store.chain_stats();
+            /// let len_24 = stats.v4.prefixes.iter().find(|d| d.len == 24).unwrap();
+            ///
+            /// assert_eq!(len_24.count, 3);
+            /// // The two 185.49 prefixes share their first bits, so one of
+            /// // them lives in a chained bucket.
+            /// assert!(len_24.max_depth > 0);
+            /// assert!(stats.v6.prefixes.is_empty());
+            /// ```
+            pub fn chain_stats(&self) -> ChainStats {
+                ChainStats {
+                    v4: self.v4.store.chain_depths(),
+                    v6: self.v6.store.chain_depths(),
+                }
+            }
+
+            /// Return the internals of the node in the tree bitmap that
+            /// would host the specified prefix, or `None` if that node
+            /// doesn't exist.
+            ///
+            /// This is meant for debugging the store only, the contents of
+            /// [NodeDebug] are tied to the implementation and may change
+            /// without notice. Only available with the `debug-internals`
+            /// feature.
+            #[cfg(feature = "debug-internals")]
+            pub fn inspect_node(&self, prefix: &Prefix) -> Option<NodeDebug> {
+                match prefix.addr() {
+                    std::net::IpAddr::V4(addr) => {
+                        self.v4.store.inspect_node(
+                            PrefixId::<IPv4>::from(*prefix)
+                        )
+                    }
+                    std::net::IpAddr::V6(addr) => {
+                        self.v6.store.inspect_node(
+                            PrefixId::<IPv6>::from(*prefix)
+                        )
+                    }
+                }
+            }
+
+            /// Shrink the memory used for the records of all the prefixes
+            /// in the store, both IPv4 and IPv6, to fit the number of
+            /// records they currently hold, and return the memory that was
+            /// reclaimed.
+            ///
+            /// Nodes and prefixes themselves can't be removed from the
+            /// store, so only the storage for the records is compacted.
+            /// Each record map is locked while it is being shrunk, so
+            /// running this during low-traffic periods is advisable.
+            pub fn compact_memory(&self) -> MemoryStats {
+                self.v4.store.compact_memory() + self.v6.store.compact_memory()
+            }
+
+            /// Returns a [HealthReport] with the number of nodes whose mui
+            /// index is poisoned, for both address families.
+            ///
+            /// The mui index of a node is locked while it is updated on an
+            /// insert. If the inserting thread panics while it holds the
+            /// lock, the lock is poisoned, and every later query for a mui
+            /// that visits the node panics too. This method walks all the
+            /// node buckets in the store, so operators can detect this
+            /// before the queries do.
+            ///
+            /// # Example
+            /// ```
+            /// use rotonda_store::prelude::*;
+            /// use rotonda_store::prelude::multi::*;
+            /// use rotonda_store::meta_examples::PrefixAs;
+            ///
+            /// let store = MultiThreadedStore::<PrefixAs>::new().unwrap();
+            /// let record = Record::new(1, 0, RouteStatus::Active, PrefixAs(211321));
+            /// store.insert(&"185.49.140.0/24".parse().unwrap(), record, None).unwrap();
+            ///
+            /// assert!(store.health_check().is_healthy());
+            /// assert_eq!(store.clear_poison(), 0);
+            /// ```
+            pub fn health_check(&self) -> HealthReport {
+                HealthReport {
+                    v4_poisoned_nodes: self.v4.store
+                        .poisoned_rbm_indexes(false),
+                    v6_poisoned_nodes: self.v6.store
+                        .poisoned_rbm_indexes(false),
                 }
             }
+
+            /// Clears the poison of the mui indexes of all the nodes found
+            /// by [health_check](Self::health_check), and returns the
+            /// number of nodes it was cleared for.
+            ///
+            /// The index of such a node is used as the panicking thread
+            /// left it, so it may lack the mui of the insert that panicked.
+            /// Queries for that mui may then miss the records of that
+            /// insert.
+            pub fn clear_poison(&self) -> usize {
+                self.v4.store.poisoned_rbm_indexes(true)
+                    + self.v6.store.poisoned_rbm_indexes(true)
+            }
         }
     };
 
@@ -21,18 +21,24 @@
 // [12, 24, 0, 0, 0, 0, 0, 0, 0, 0].
 // This is an array for a prefix-length of 24 and stores all prefixes in two
 // levels maximum.
+//
+// The functions below don't use such arrays, but calculate the end bits:
+// the first level ends at `root_bits` (4 by default, overridable through
+// the `create_store` macro) and every next level adds 4 bits, until the
+// prefix-length is reached.
 
 use quote::quote;
 
-pub fn node_buckets_map_v4() -> quote::__private::TokenStream {
+pub fn node_buckets_map_v4(root_bits: u8) -> quote::__private::TokenStream {
     quote! {
 
     fn len_to_store_bits(len: u8, lvl: u8) -> u8 {
-        let res = 4 * (lvl + 1);
+        let res = #root_bits + 4 * lvl;
+        let step = if lvl == 0 { #root_bits } else { 4 };
         if res < len {
             res
         } else {
-           if res >= len + 4 {
+           if res >= len + step {
                 0
             } else {
                 len
@@ -110,15 +116,16 @@ pub fn node_buckets_map_v4() -> quote::__private::TokenStream {
     }
 }
 
-pub fn prefix_buckets_map_v4() -> quote::__private::TokenStream {
+pub fn prefix_buckets_map_v4(root_bits: u8) -> quote::__private::TokenStream {
     quote! {
 
         fn get_bits_for_len(len: u8, lvl: u8) -> u8 {
-            let res = 4 * (lvl + 1);
+            let res = #root_bits + 4 * lvl;
+            let step = if lvl == 0 { #root_bits } else { 4 };
             if res < len {
                 res
             } else {
-                if res >= len + 4 {
+                if res >= len + step {
                     0
                 } else {
                     len
@@ -196,15 +203,16 @@ pub fn prefix_buckets_map_v4() -> quote::__private::TokenStream {
     }
 }
 
-pub fn node_buckets_map_v6() -> quote::__private::TokenStream {
+pub fn node_buckets_map_v6(root_bits: u8) -> quote::__private::TokenStream {
     quote! {
 
         fn len_to_store_bits(len: u8, lvl: u8) -> u8 {
-            let res = 4 * (lvl + 1);
+            let res = #root_bits + 4 * lvl;
+            let step = if lvl == 0 { #root_bits } else { 4 };
             if res < len {
                 res
             } else {
-                if res >= len + 4 { 0 } else { len }
+                if res >= len + step { 0 } else { len }
             }
 
             // match len {
@@ -371,15 +379,16 @@ pub fn node_buckets_map_v6() -> quote::__private::TokenStream {
     }
 }
 
-pub fn prefix_buckets_map_v6() -> quote::__private::TokenStream {
+pub fn prefix_buckets_map_v6(root_bits: u8) -> quote::__private::TokenStream {
     quote! {
 
         fn get_bits_for_len(len: u8, lvl: u8) -> u8 {
-            let res = 4 * (lvl + 1);
+            let res = #root_bits + 4 * lvl;
+            let step = if lvl == 0 { #root_bits } else { 4 };
             if res <= len {
                 res
             } else {
-                if res >= len + 4 { 0 } else { len }
+                if res >= len + step { 0 } else { len }
             }
 
             // match len {
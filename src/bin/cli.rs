@@ -223,6 +223,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                                         include_less_specifics: true,
                                         include_more_specifics: true,
                                         mui: None,
+                                        debug_path: false,
+                                        stop_at_first_less_specific: false,
                                     },
                                     guard,
                                 );
@@ -285,7 +287,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                                             include_withdrawn: true,
                                             include_less_specifics: true,
                                             include_more_specifics: true,
-                                            mui: None
+                                            mui: None,
+                                            debug_path: false,
+                                            stop_at_first_less_specific: false,
                                         },
                                         guard
                                     )
@@ -13,11 +13,13 @@ macro_rules! insert_match {
         $pfx: ident; // the whole search prefix
         $record: ident; // the record holding the metadata
         $update_path_selections: ident; // boolean indicate whether to update the path selections for this route
+        $mode: ident; // what to do with an existing record for the mui
         $truncate_len: ident; // the start of the length of this stride
         $stride_len: ident; // the length of this stride
         $cur_i: expr; // the id of the current node in this stride
         $level: expr;
         $acc_retry_count: expr;
+        $acc_backoff_count: expr;
         // $enum: ident;
         // The strides to generate match arms for,
         // $variant is the name of the enum varian (Stride[3..8]) and
@@ -40,7 +42,11 @@ macro_rules! insert_match {
 
         // This macro counts the number of retries and adds that to the
         // $acc_retry_count variable, to be used by the incorporating
-        // function.
+        // function. The number of times the thread yielded because of
+        // contention is returned the same way, but only for this stride,
+        // the incorporating function accumulates it in $acc_backoff_count.
+        // If the thread has to retry too often, the incorporating function
+        // returns a NodeCreationMaxRetryError.
         {
             // this counts the number of retry_count for this loop only,
             // but ultimately we will return the accumulated count of all
@@ -64,8 +70,9 @@ macro_rules! insert_match {
                                 // the length of the next stride
                                 $self.store.get_stride_sizes().get(($level + 1) as usize),
                                 $is_last_stride,
-                            ) {
-                                (NewNodeOrIndex::NewNode(n), retry_count) => {
+                                $self.store.max_retries_before_yield,
+                            )? {
+                                (NewNodeOrIndex::NewNode(n), retry_count, backoff_count) => {
                                     // Stride3 logs to stats[0], Stride4 logs to stats[1], etc.
                                     // $self.stats[$stats_level].inc($level);
 
@@ -78,14 +85,14 @@ macro_rules! insert_match {
                                     // success.
                                     match $self.store.store_node(new_id, $record.multi_uniq_id, n)  {
                                         Ok((node_id, s_retry_count)) => {
-                                            Ok((node_id, $acc_retry_count + s_retry_count + retry_count))
+                                            Ok((node_id, $acc_retry_count + s_retry_count + retry_count, backoff_count))
                                         },
                                         Err(err) => {
                                             Err(err)
                                         }
                                     }
                                 }
-                                (NewNodeOrIndex::ExistingNode(node_id), retry_count) => {
+                                (NewNodeOrIndex::ExistingNode(node_id), retry_count, backoff_count) => {
                                     // $self.store.update_node($cur_i,SizedStrideRefMut::$variant(current_node));
                                     if log_enabled!(log::Level::Trace) {
                                         if local_retry_count > 0 {
@@ -94,21 +101,23 @@ macro_rules! insert_match {
                                             )
                                         }
                                     }
-                                    Ok((node_id, $acc_retry_count + local_retry_count + retry_count))
+                                    Ok((node_id, $acc_retry_count + local_retry_count + retry_count, backoff_count))
                                 },
-                                (NewNodeOrIndex::NewPrefix, retry_count) => {
-                                    return $self.store.upsert_prefix($pfx, $record, $update_path_selections, $guard)
+                                (NewNodeOrIndex::NewPrefix, retry_count, backoff_count) => {
+                                    return $self.store.upsert_prefix($pfx, $record, $update_path_selections, $mode, $guard)
                                         .and_then(|mut r| {
                                             r.cas_count += $acc_retry_count as usize + local_retry_count as usize + retry_count as usize;
+                                            r.backoff_count += $acc_backoff_count as usize + backoff_count as usize;
                                             Ok(r)
                                         })
                                     // Log
                                     // $self.stats[$stats_level].inc_prefix_count($level);
                                 }
-                                (NewNodeOrIndex::ExistingPrefix, retry_count) => {
-                                    return $self.store.upsert_prefix($pfx, $record, $update_path_selections, $guard)
+                                (NewNodeOrIndex::ExistingPrefix, retry_count, backoff_count) => {
+                                    return $self.store.upsert_prefix($pfx, $record, $update_path_selections, $mode, $guard)
                                         .and_then(|mut r| {
                                             r.cas_count += $acc_retry_count as usize + local_retry_count as usize + retry_count as usize;
+                                            r.backoff_count += $acc_backoff_count as usize + backoff_count as usize;
                                             Ok(r)
                                         })
                                 }
@@ -117,7 +126,10 @@ macro_rules! insert_match {
                     )*,
                 }
             } else {
-                Err(PrefixStoreError::NodeCreationMaxRetryError)
+                Err(PrefixStoreError::StoreOperationFailed {
+                    operation: "retrieve_node_mut",
+                    prefix: Some($pfx.into_pub()),
+                })
             }
         }
     }
@@ -15,6 +15,11 @@ use crate::local_array::store::iterators::SizedPrefixIter;
 pub use crate::local_array::tree::*;
 use crate::af::Zero;
 use crate::af::AddressFamily;
+use crate::local_array::store::errors::PrefixStoreError;
+
+// The number of failed compare-and-swaps on a bitmap of a node after which
+// eval_node_or_prefix_at gives up with a NodeCreationMaxRetryError.
+const MAX_CAS_RETRIES: u32 = 1 << 20;
 
 //------------ TreeBitMap Node ----------------------------------------------
 
@@ -155,8 +160,12 @@ where
     // - The index of the existing child node in the global `nodes` vec
     // - A newly created Prefix
     // - The index of the existing prefix in the global `prefixes` vec
-    // and the second element is the number of accumulated retries for the
-    // compare_exchange of both ptrbitarr and pfxbitarr.
+    // The second element is the number of accumulated retries for the
+    // compare_exchange of both ptrbitarr and pfxbitarr, and the third
+    // element is the number of times the thread yielded, because there
+    // were more than `max_retries_before_yield` retries. After
+    // MAX_CAS_RETRIES retries a NodeCreationMaxRetryError is returned.
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn eval_node_or_prefix_at(
         &self,
         nibble: u32,
@@ -167,7 +176,8 @@ where
         stride_len: u8,
         next_stride: Option<&u8>,
         is_last_stride: bool,
-    ) -> (NewNodeOrIndex<AF>, u32) {
+        max_retries_before_yield: u32,
+    ) -> Result<(NewNodeOrIndex<AF>, u32, u32), PrefixStoreError> {
 
         // THE CRITICAL SECTION
         //
@@ -181,6 +191,7 @@ where
         // using an old ptrbitarr and overwrite bits set in the
         // meantime elsewhere in the bitarray.
         let mut retry_count = 0;
+        let mut yield_count = 0;
         let ptrbitarr = self.ptrbitarr.load();
         let pfxbitarr = self.pfxbitarr.load();
         let bit_pos = S::get_bit_pos(nibble, nibble_len);
@@ -252,18 +263,28 @@ where
                             // Someone beat us to it, so we need to use the
                             // newer array.
                             retry_count += 1;
+                            if retry_count >= MAX_CAS_RETRIES {
+                                return Err(
+                                    PrefixStoreError::NodeCreationMaxRetryError
+                                );
+                            }
                             a_ptrbitarr = self.ptrbitarr.compare_exchange(newer_array,
                                 S::into_ptrbitarr_size(
                                 bit_pos | S::into_stride_size(newer_array),
                             ));
                         }
                     };
-                    spinwait.spin_no_yield();
+                    if retry_count > max_retries_before_yield {
+                        std::thread::yield_now();
+                        yield_count += 1;
+                    } else {
+                        spinwait.spin_no_yield();
+                    }
                 }
 
-                return (NewNodeOrIndex::NewNode(
+                return Ok((NewNodeOrIndex::NewNode(
                     new_node
-                ), retry_count);
+                ), retry_count, yield_count));
             }
         } else {
             // only at the last stride do we create the bit in the prefix
@@ -293,25 +314,35 @@ where
                             // Someone beat us to it, so we need to use the
                             // newer array.
                             retry_count += 1;
+                            if retry_count >= MAX_CAS_RETRIES {
+                                return Err(
+                                    PrefixStoreError::NodeCreationMaxRetryError
+                                );
+                            }
                             a_pfxbitarr = self.pfxbitarr.compare_exchange(
                                 newer_array, bit_pos | newer_array
                             );
                         }
                     };
-                    spinwait.spin_no_yield();
+                    if retry_count > max_retries_before_yield {
+                        std::thread::yield_now();
+                        yield_count += 1;
+                    } else {
+                        spinwait.spin_no_yield();
+                    }
                 }
 
-                return (NewNodeOrIndex::NewPrefix, retry_count);
+                return Ok((NewNodeOrIndex::NewPrefix, retry_count, yield_count));
             }
-            return (NewNodeOrIndex::ExistingPrefix, retry_count);
+            return Ok((NewNodeOrIndex::ExistingPrefix, retry_count, yield_count));
         }
 
         // Nodes always live at the last length of a stride (i.e. the last 
         // nibble), so we add the stride length to the length of the
         // base_prefix (which is always the start length of the stride).
-        (NewNodeOrIndex::ExistingNode(
+        Ok((NewNodeOrIndex::ExistingNode(
             base_prefix.add_to_len(stride_len).truncate_to_len()
-        ), retry_count)
+        ), retry_count, yield_count))
     }
 
     //-------- Search nibble functions --------------------------------------
@@ -764,10 +795,18 @@ impl<AF: AddressFamily, S: Stride> std::iter::Iterator for
             return None;
         }
 
-        // Previous iteration incremented the cursor beyond the stride size.
+        // The child nodes for the more-specifics of the start_bit_span are
+        // the ones whose nibble starts with the bits of the bit_span, e.g.
+        // for a stride of 5 and a bit_span of {bits: 2, len: 4} these are
+        // 0010 0 up to and including 0010 1.
+        let shift = S::STRIDE_LEN - self.start_bit_span.len;
+        let stop = ((self.start_bit_span.bits + 1) << shift) - 1;
+
+        // Previous iteration incremented the cursor beyond the last nibble
+        // for this bit_span.
         if let Some(cursor) = self.cursor { 
-            if cursor >= (1 << (S::STRIDE_LEN - self.start_bit_span.len)) {
-                trace!("cursor.bits >= (1 << (S::STRIDE_LEN - self.start_bit_span.len))");
+            if cursor > stop {
+                trace!("cursor > stop");
                 trace!("cursor: {}", cursor);
                 trace!("start_bit_span: {} {}", self.start_bit_span.bits, self.start_bit_span.len);
                 return None;
@@ -783,11 +822,11 @@ impl<AF: AddressFamily, S: Stride> std::iter::Iterator for
         trace!("          x1  4   8  12  16  20  24  28  32");
         trace!("ptrbitarr {:032b}", self.ptrbitarr);
 
-        let start = if let Some(bits) = self.cursor { bits } else { self.start_bit_span.bits };
-        // We either stop if we have reached the maximum number of bits that
-        // we should check for this bit_span or we stop at the end of the
-        // stride (in case of a start_bit_span.bits == 0).
-        let stop = <u32>::min((1 << (S::STRIDE_LEN - self.start_bit_span.len)) + start, (1 << S::STRIDE_LEN) - 1); 
+        let start = if let Some(bits) = self.cursor {
+            bits
+        } else {
+            self.start_bit_span.bits << shift
+        };
 
         trace!("start {:?} stop {}", start, stop);
         for cursor in start..=stop {
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::sync::atomic::Ordering;
 
 use crossbeam_epoch::{self as epoch};
@@ -5,26 +6,28 @@ use epoch::Guard;
 
 use crate::af::AddressFamily;
 use crate::local_array::store::atomic_types::{NodeBuckets, PrefixBuckets};
-use crate::prefix_record::{Meta, PublicRecord};
+use crate::local_array::store::custom_alloc::NodeHasher;
+use crate::prefix_record::{Meta, PublicRecord, RecordSet};
 use inetnum::addr::Prefix;
 
 use crate::QueryResult;
 
 use crate::local_array::node::TreeBitMapNode;
 use crate::local_array::tree::TreeBitMap;
-use crate::{MatchOptions, MatchType};
+use crate::{MatchOptions, MatchPathNode, MatchType};
 
 use super::node::{PrefixId, SizedStrideRef, StrideNodeId};
 use super::store::atomic_types::{RouteStatus, StoredPrefix};
 
 //------------ Prefix Matching ----------------------------------------------
 
-impl<'a, AF, M, NB, PB> TreeBitMap<AF, M, NB, PB>
+impl<'a, AF, M, NB, PB, H> TreeBitMap<AF, M, NB, PB, H>
 where
     AF: AddressFamily,
     M: Meta,
     NB: NodeBuckets<AF>,
     PB: PrefixBuckets<AF, M>,
+    H: NodeHasher,
 {
     pub fn more_specifics_from(
         &'a self,
@@ -57,7 +60,9 @@ where
                 .unwrap_or_default(),
             match_type: MatchType::EmptyMatch,
             less_specifics: None,
+            covering: None,
             more_specifics: Some(more_specifics_vec.collect()),
+            debug_path: None,
         }
     }
 
@@ -71,16 +76,20 @@ where
         let result = self.store.non_recursive_retrieve_prefix(prefix_id);
 
         let prefix = result.0;
-        let less_specifics_vec = result.1.map(
+        let less_specifics = result.1.map(
             |(prefix_id, _level, _cur_set, _parents, _index)| {
-                self.store.less_specific_prefix_iter(
-                    prefix_id,
-                    mui,
-                    include_withdrawn,
-                    guard,
-                )
+                self.store
+                    .less_specific_prefix_iter(
+                        prefix_id,
+                        mui,
+                        include_withdrawn,
+                        guard,
+                    )
+                    .collect::<RecordSet<M>>()
             },
         );
+        let covering =
+            less_specifics.as_ref().and_then(|ls| ls.longest_prefix());
 
         QueryResult {
             prefix: if let Some(pfx) = prefix {
@@ -96,8 +105,10 @@ where
                 .map(|r| self.get_filtered_records(r, mui, guard))
                 .unwrap_or_default(),
             match_type: MatchType::EmptyMatch,
-            less_specifics: less_specifics_vec.map(|iter| iter.collect()),
+            less_specifics,
+            covering,
             more_specifics: None,
+            debug_path: None,
         }
     }
 
@@ -126,6 +137,16 @@ where
         mui: Option<u32>,
         guard: &'a Guard,
     ) -> QueryResult<M> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::span!(
+            tracing::Level::TRACE,
+            "match_prefix",
+            af = if AF::BITS == 32 { "ipv4" } else { "ipv6" },
+            len = search_pfx.get_len(),
+            match_type = %options.match_type,
+        )
+        .entered();
+
         // `non_recursive_retrieve_prefix` returns an exact match
         // only, so no longest matching prefix!
         let mut stored_prefix =
@@ -191,30 +212,41 @@ where
             (MatchType::ExactMatch, _) => MatchType::EmptyMatch,
         };
 
+        let less_specifics: Option<RecordSet<M>> =
+            if options.include_less_specifics {
+                let less_specifics_iter = self.store.less_specific_prefix_iter(
+                    if let Some(ref pfx) = stored_prefix {
+                        pfx.0
+                    } else {
+                        search_pfx
+                    },
+                    mui,
+                    options.include_withdrawn,
+                    guard,
+                );
+                // The iterator returns the less-specifics from the longest
+                // to the shortest, so the first one is the nearest cover.
+                Some(if options.stop_at_first_less_specific {
+                    less_specifics_iter.take(1).collect()
+                } else {
+                    less_specifics_iter.collect()
+                })
+            } else {
+                None
+            };
+        // The less-specific with the greatest length is the nearest cover,
+        // also if there's no exact or longest match.
+        let covering =
+            less_specifics.as_ref().and_then(|ls| ls.longest_prefix());
+
         QueryResult {
             prefix: stored_prefix.as_ref().map(|p| p.0.into_pub()),
             prefix_meta: stored_prefix
                 .as_ref()
                 .map(|pfx| pfx.1.clone())
                 .unwrap_or_default(),
-            less_specifics: if options.include_less_specifics {
-                Some(
-                    self.store
-                        .less_specific_prefix_iter(
-                            if let Some(ref pfx) = stored_prefix {
-                                pfx.0
-                            } else {
-                                search_pfx
-                            },
-                            mui,
-                            options.include_withdrawn,
-                            guard,
-                        )
-                        .collect(),
-                )
-            } else {
-                None
-            },
+            less_specifics,
+            covering,
             more_specifics: if options.include_more_specifics {
                 Some(
                     self.store
@@ -237,9 +269,58 @@ where
                 None
             },
             match_type,
+            debug_path: if options.debug_path {
+                Some(self.match_path(search_pfx))
+            } else {
+                None
+            },
         }
     }
 
+    // The nodes on the path from the root node to the node that hosts
+    // `search_pfx`, together with the bits of `search_pfx` that fall within
+    // each node. The path ends early at the first node that doesn't exist.
+    pub(crate) fn match_path(
+        &'a self,
+        search_pfx: PrefixId<AF>,
+    ) -> Vec<MatchPathNode> {
+        let mut path = vec![];
+        let mut stride_end = 0;
+
+        for stride in self.store.get_stride_sizes() {
+            let node_len = stride_end;
+            stride_end += stride;
+
+            let node_id = StrideNodeId::new_with_cleaned_id(
+                search_pfx.get_net(),
+                node_len,
+            );
+            if self.store.retrieve_node(node_id).is_none() {
+                break;
+            }
+
+            let len = u8::min(search_pfx.get_len(), stride_end) - node_len;
+            let (net, id_len) = node_id.get_id();
+            path.push(MatchPathNode {
+                node_id: Prefix::new(net.into_ipaddr(), id_len)
+                    .unwrap_or_else(|p| {
+                        panic!("can't convert node id {:?} into prefix.", p)
+                    }),
+                // Checked shift, a span of zero bits shifts by AF::BITS.
+                bits: (search_pfx.get_net() << node_len)
+                    .checked_shr_or_zero((AF::BITS - len).into())
+                    .dangerously_truncate_to_u32(),
+                len,
+            });
+
+            if search_pfx.get_len() <= stride_end {
+                break;
+            }
+        }
+
+        path
+    }
+
     // In a LMP search we have to go over all the nibble lengths in the
     // stride up until the value of the actual nibble length were looking for
     // (until we reach stride length for all strides that aren't the last)
@@ -283,7 +364,13 @@ where
                         prefix_meta: vec![],
                         match_type: MatchType::EmptyMatch,
                         less_specifics: None,
+                        covering: None,
                         more_specifics: None,
+                        debug_path: if options.debug_path {
+                            Some(self.match_path(search_pfx))
+                        } else {
+                            None
+                        },
                     };
                 }
 
@@ -302,7 +389,13 @@ where
                         prefix_meta,
                         match_type: MatchType::ExactMatch,
                         less_specifics: None,
+                        covering: None,
                         more_specifics: None,
+                        debug_path: if options.debug_path {
+                            Some(self.match_path(search_pfx))
+                        } else {
+                            None
+                        },
                     };
                 }
             }
@@ -751,18 +844,19 @@ where
             };
         };
 
-        QueryResult {
-            prefix: prefix.map(|pfx: (&StoredPrefix<AF, M>, usize)| {
-                pfx.0.prefix.into_pub()
-            }),
-            prefix_meta: prefix
-                .map(|pfx| pfx.0.record_map.as_records())
-                .unwrap_or_default(),
-            match_type,
-            less_specifics: if options.include_less_specifics {
+        let less_specifics: Option<RecordSet<M>> =
+            if options.include_less_specifics {
+                let less_specifics_vec = less_specifics_vec.unwrap();
+                // The less-specifics were collected while descending the
+                // tree, so the last one is the longest.
+                let skip = if options.stop_at_first_less_specific {
+                    less_specifics_vec.len().saturating_sub(1)
+                } else {
+                    0
+                };
                 less_specifics_vec
-                    .unwrap()
                     .iter()
+                    .skip(skip)
                     .filter_map(move |p| {
                         self.store.retrieve_prefix(*p).map(|p| {
                             Some((p.0.prefix, p.0.record_map.as_records()))
@@ -771,7 +865,20 @@ where
                     .collect()
             } else {
                 None
-            },
+            };
+        let covering =
+            less_specifics.as_ref().and_then(|ls| ls.longest_prefix());
+
+        QueryResult {
+            prefix: prefix.map(|pfx: (&StoredPrefix<AF, M>, usize)| {
+                pfx.0.prefix.into_pub()
+            }),
+            prefix_meta: prefix
+                .map(|pfx| pfx.0.record_map.as_records())
+                .unwrap_or_default(),
+            match_type,
+            less_specifics,
+            covering,
             more_specifics: if options.include_more_specifics {
                 more_specifics_vec.map(|vec| {
                     vec.into_iter()
@@ -792,6 +899,11 @@ where
             } else {
                 None
             },
+            debug_path: if options.debug_path {
+                Some(self.match_path(search_pfx))
+            } else {
+                None
+            },
         }
     }
 
@@ -814,3 +926,131 @@ where
         pfx.record_map.get_filtered_records(mui, bmin)
     }
 }
+
+//------------ Aggregation --------------------------------------------------
+
+impl<'a, AF, M, NB, PB, H> TreeBitMap<AF, M, NB, PB, H>
+where
+    AF: AddressFamily,
+    M: Meta,
+    NB: NodeBuckets<AF>,
+    PB: PrefixBuckets<AF, M>,
+    H: NodeHasher,
+{
+    // Returns the minimal set of prefixes that covers exactly the same
+    // address space as `root_id` and all its more-specifics in the store.
+    // Prefixes without active records are ignored.
+    //
+    // A prefix absorbs the more-specifics it covers, and two sibling
+    // prefixes are merged into their parent, but only if all the metas of
+    // the records of the prefixes involved are mergeable according to the
+    // `mergeable` callback. A more-specific is only absorbed by its nearest
+    // less-specific in the set: if that one isn't mergeable, it stays, since
+    // it is different from the prefix covering it.
+    pub fn aggregate(
+        &'a self,
+        root_id: PrefixId<AF>,
+        mergeable: impl Fn(&M, &M) -> bool,
+        guard: &'a Guard,
+    ) -> Vec<PrefixId<AF>> {
+        let mut aggs: HashMap<PrefixId<AF>, Vec<M>> = self
+            .store
+            .more_specific_prefix_iter_from(root_id, None, false, guard)
+            .map(|(pfx, recs)| {
+                (pfx, recs.into_iter().map(|r| r.meta).collect())
+            })
+            .collect();
+        if let Some(root) =
+            self.store.non_recursive_retrieve_prefix(root_id).0
+        {
+            aggs.insert(
+                root_id,
+                self.get_filtered_records(root, None, guard)
+                    .into_iter()
+                    .map(|r| r.meta)
+                    .collect(),
+            );
+        }
+        aggs.retain(|_, metas| !metas.is_empty());
+
+        let all_mergeable = |a: &[M], b: &[M]| {
+            a.iter().all(|ma| b.iter().all(|mb| mergeable(ma, mb)))
+        };
+
+        loop {
+            let mut changed = false;
+
+            // Let the nearest less-specific absorb each prefix, shortest
+            // prefixes first.
+            let mut pfxs = aggs.keys().copied().collect::<Vec<_>>();
+            pfxs.sort_by_key(|p| p.get_len());
+            for pfx in &pfxs {
+                let cover_metas = (root_id.get_len()..pfx.get_len())
+                    .rev()
+                    .find_map(|len| {
+                        let cover = PrefixId::new(
+                            pfx.get_net().truncate_to_len(len),
+                            len,
+                        );
+                        aggs.get(&cover)
+                    });
+                if let Some(cover_metas) = cover_metas {
+                    if all_mergeable(cover_metas, &aggs[pfx]) {
+                        aggs.remove(pfx);
+                        changed = true;
+                    }
+                }
+            }
+
+            // Merge siblings into their parent, longest prefixes first.
+            let mut pfxs = aggs.keys().copied().collect::<Vec<_>>();
+            pfxs.sort_by_key(|p| std::cmp::Reverse(p.get_len()));
+            for pfx in &pfxs {
+                if pfx.get_len() <= root_id.get_len() {
+                    continue;
+                }
+                let len = pfx.get_len() - 1;
+                let parent_net = pfx.get_net().truncate_to_len(len);
+                let parent = PrefixId::new(parent_net, len);
+                let sibling = if pfx.get_net() == parent_net {
+                    PrefixId::new(
+                        parent_net
+                            | (AF::from(1_u32) << (AF::BITS - len - 1)),
+                        pfx.get_len(),
+                    )
+                } else {
+                    PrefixId::new(parent_net, pfx.get_len())
+                };
+
+                let can_merge = match (aggs.get(pfx), aggs.get(&sibling)) {
+                    (Some(metas), Some(sibling_metas)) => {
+                        all_mergeable(metas, sibling_metas)
+                            && aggs.get(&parent).map_or(
+                                true,
+                                |parent_metas| {
+                                    all_mergeable(parent_metas, metas)
+                                        && all_mergeable(
+                                            parent_metas,
+                                            sibling_metas,
+                                        )
+                                },
+                            )
+                    }
+                    _ => false,
+                };
+                if can_merge {
+                    let mut metas = aggs.remove(pfx).unwrap_or_default();
+                    metas.extend(aggs.remove(&sibling).unwrap_or_default());
+                    aggs.entry(parent).or_default().extend(metas);
+                    changed = true;
+                }
+            }
+
+            if !changed {
+                break;
+            }
+        }
+
+        aggs.into_keys().collect()
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{hash_map::Entry, HashMap};
 use std::sync::{Arc, Mutex, MutexGuard, RwLock};
 use std::{
     fmt::{Debug, Display},
@@ -14,7 +14,7 @@ use epoch::{Guard, Owned};
 use roaring::RoaringBitmap;
 
 use crate::local_array::tree::*;
-use crate::prefix_record::PublicRecord;
+use crate::prefix_record::{BestPathPolicy, PolicyOrdered, PublicRecord};
 use crate::prelude::Meta;
 use crate::AddressFamily;
 
@@ -90,6 +90,28 @@ impl<AF: AddressFamily, S: Stride> NodeSet<AF, S> {
 
         Ok(try_count)
     }
+
+    // The number of nodes in this set, and in the sets below it, whose
+    // bitmap index is poisoned, i.e. a thread panicked while it was updating
+    // the index. If `clear` is set, the poison is cleared, and the index is
+    // used as the panicking thread left it from then on.
+    pub(crate) fn poisoned_rbm_indexes(&self, clear: bool) -> usize {
+        if self.0.is_null() {
+            return 0;
+        }
+        (0..self.0.size())
+            .filter_map(|index| self.0.get(index))
+            .map(|stored_node| {
+                let rbm = &stored_node.node_set.1;
+                let poisoned = rbm.is_poisoned();
+                if poisoned && clear {
+                    rbm.clear_poison();
+                }
+                poisoned as usize
+                    + stored_node.node_set.poisoned_rbm_indexes(clear)
+            })
+            .sum()
+    }
 }
 
 // ----------- Prefix related structs ---------------------------------------
@@ -125,6 +147,12 @@ pub struct StoredPrefix<AF: AddressFamily, M: crate::prefix_record::Meta> {
     pub record_map: MultiMap<M>,
     // (mui of best path entry, mui of backup path entry) from the record_map
     path_selections: Atomic<PathSelections>,
+    // The muis of all the records that were tied for the best path the
+    // last time they were calculated. Null if they were never calculated.
+    ecmp_muis: Atomic<Vec<u32>>,
+    // An opaque value that belongs to the prefix itself, instead of to any
+    // of its records. Null if it was never set.
+    attr: Atomic<Vec<u8>>,
     // the reference to the next set of records for this prefix, if any.
     pub next_bucket: PrefixSet<AF, M>,
 }
@@ -155,7 +183,7 @@ impl<AF: AddressFamily, M: crate::prefix_record::Meta> StoredPrefix<AF, M> {
                 std::thread::current().name().unwrap_or("unnamed-thread"),
                 pfx_id.get_len()
             );
-            PrefixSet::init(next_level - this_level)
+            PrefixSet::init(0)
         };
         // End of calculation
 
@@ -168,6 +196,8 @@ impl<AF: AddressFamily, M: crate::prefix_record::Meta> StoredPrefix<AF, M> {
                 path_selection_muis: (None, None),
             }),
             record_map: MultiMap::new(rec_map),
+            ecmp_muis: Atomic::null(),
+            attr: Atomic::null(),
             next_bucket,
         }
     }
@@ -195,7 +225,11 @@ impl<AF: AddressFamily, M: crate::prefix_record::Meta> StoredPrefix<AF, M> {
     ) -> Result<(), PrefixStoreError> {
         let current = self.path_selections.load(Ordering::SeqCst, guard);
 
-        if unsafe { current.as_ref() } == Some(&path_selections) {
+        // Only skip the update if the path selections are unchanged *and*
+        // not marked as outdated, otherwise the outdated tag would stick.
+        if current.tag() == 0
+            && unsafe { current.as_ref() } == Some(&path_selections)
+        {
             debug!("unchanged path_selections");
             return Ok(());
         }
@@ -213,6 +247,17 @@ impl<AF: AddressFamily, M: crate::prefix_record::Meta> StoredPrefix<AF, M> {
         Ok(())
     }
 
+    pub fn get_attr(&self, guard: &Guard) -> Option<Vec<u8>> {
+        unsafe { self.attr.load(Ordering::Acquire, guard).as_ref() }.cloned()
+    }
+
+    pub(crate) fn set_attr(&self, attr: Vec<u8>, guard: &Guard) {
+        let old = self.attr.swap(Owned::new(attr), Ordering::AcqRel, guard);
+        if !old.is_null() {
+            unsafe { guard.defer_destroy(old) };
+        }
+    }
+
     pub fn set_ps_outdated(
         &self,
         guard: &Guard,
@@ -247,6 +292,51 @@ impl<AF: AddressFamily, M: crate::prefix_record::Meta> StoredPrefix<AF, M> {
         Ok(path_selection_muis)
     }
 
+    // Calculates the muis of all the records that are tied for the best
+    // path, and stores them, replacing the ones stored before. This leaves
+    // the path selections alone.
+    pub fn calculate_and_store_ecmp(
+        &self,
+        tbi: &M::TBI,
+        guard: &Guard,
+    ) -> Vec<u32> {
+        let muis = self.record_map.ecmp(*tbi);
+        let old = self.ecmp_muis.swap(
+            Owned::new(muis.clone()),
+            Ordering::AcqRel,
+            guard,
+        );
+        if !old.is_null() {
+            unsafe { guard.defer_destroy(old) };
+        }
+        muis
+    }
+
+    pub fn get_ecmp_muis(&self, guard: &Guard) -> Option<Vec<u32>> {
+        unsafe { self.ecmp_muis.load(Ordering::Acquire, guard).as_ref() }
+            .cloned()
+    }
+
+    // Like `calculate_and_store_best_backup`, but selects the paths with
+    // `policy`.
+    pub fn calculate_and_store_best_backup_with<'a>(
+        &'a self,
+        policy: &dyn BestPathPolicy<M>,
+        guard: &'a Guard,
+    ) -> Result<(Option<u32>, Option<u32>), super::errors::PrefixStoreError>
+    {
+        let path_selection_muis = self.record_map.best_backup_with(policy);
+
+        self.set_path_selections(
+            PathSelections {
+                path_selection_muis,
+            },
+            guard,
+        )?;
+
+        Ok(path_selection_muis)
+    }
+
     pub(crate) fn get_next_bucket(&self) -> Option<&PrefixSet<AF, M>> {
         if self.next_bucket.is_empty() {
             None
@@ -256,11 +346,58 @@ impl<AF: AddressFamily, M: crate::prefix_record::Meta> StoredPrefix<AF, M> {
     }
 }
 
+impl<AF: AddressFamily, M: crate::prefix_record::Meta> Drop
+    for StoredPrefix<AF, M>
+{
+    fn drop(&mut self) {
+        // Nobody can hold a reference to the attribute, or the ecmp muis
+        // anymore, but either may never have been set.
+        let attr = std::mem::replace(&mut self.attr, Atomic::null());
+        drop(unsafe { attr.try_into_owned() });
+        let ecmp_muis =
+            std::mem::replace(&mut self.ecmp_muis, Atomic::null());
+        drop(unsafe { ecmp_muis.try_into_owned() });
+    }
+}
+
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
 pub enum RouteStatus {
     Active,
     InActive,
     Withdrawn,
+    // The route is still usable, but it is waiting to be refreshed, e.g.
+    // because the BGP session it was learned over went down, and is going
+    // through a graceful restart. Stale records are returned by queries
+    // like Active records, but they retain their Stale status.
+    Stale,
+}
+
+impl RouteStatus {
+    // Whether a record with this status should be returned by default, i.e.
+    // it is either Active or Stale.
+    pub(crate) fn is_usable(&self) -> bool {
+        matches!(self, RouteStatus::Active | RouteStatus::Stale)
+    }
+
+    // The byte for this status in the format of `dump_binary`.
+    pub(crate) fn to_u8(self) -> u8 {
+        match self {
+            RouteStatus::Active => 1,
+            RouteStatus::InActive => 2,
+            RouteStatus::Withdrawn => 3,
+            RouteStatus::Stale => 4,
+        }
+    }
+
+    pub(crate) fn from_u8(byte: u8) -> Option<Self> {
+        match byte {
+            1 => Some(RouteStatus::Active),
+            2 => Some(RouteStatus::InActive),
+            3 => Some(RouteStatus::Withdrawn),
+            4 => Some(RouteStatus::Stale),
+            _ => None,
+        }
+    }
 }
 
 impl std::fmt::Display for RouteStatus {
@@ -269,13 +406,43 @@ impl std::fmt::Display for RouteStatus {
             RouteStatus::Active => write!(f, "active"),
             RouteStatus::InActive => write!(f, "inactive"),
             RouteStatus::Withdrawn => write!(f, "withdrawn"),
+            RouteStatus::Stale => write!(f, "stale"),
+        }
+    }
+}
+
+// The meta-data of a record in a MultiMap. It is either owned by the
+// record, or, if the store interns meta-data, shared with all the other
+// records with the same meta-data.
+#[derive(Clone, Debug)]
+pub(crate) enum StoredMeta<M> {
+    Owned(M),
+    Interned(Arc<M>),
+}
+
+impl<M: Clone> StoredMeta<M> {
+    pub(crate) fn into_inner(self) -> M {
+        match self {
+            StoredMeta::Owned(meta) => meta,
+            StoredMeta::Interned(meta) => Arc::unwrap_or_clone(meta),
+        }
+    }
+}
+
+impl<M> std::ops::Deref for StoredMeta<M> {
+    type Target = M;
+
+    fn deref(&self) -> &M {
+        match self {
+            StoredMeta::Owned(meta) => meta,
+            StoredMeta::Interned(meta) => meta,
         }
     }
 }
 
 #[derive(Clone, Debug)]
 pub(crate) struct MultiMapValue<M> {
-    pub meta: M,
+    pub meta: StoredMeta<M>,
     pub ltime: u64,
     pub status: RouteStatus,
 }
@@ -283,7 +450,7 @@ pub(crate) struct MultiMapValue<M> {
 impl<M: Clone> MultiMapValue<M> {
     pub(crate) fn _new(meta: M, ltime: u64, status: RouteStatus) -> Self {
         Self {
-            meta,
+            meta: StoredMeta::Owned(meta),
             ltime,
             status,
         }
@@ -292,14 +459,14 @@ impl<M: Clone> MultiMapValue<M> {
 
 impl<M: crate::prefix_record::Meta> std::fmt::Display for MultiMapValue<M> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} {} {}", self.meta, self.ltime, self.status)
+        write!(f, "{} {} {}", *self.meta, self.ltime, self.status)
     }
 }
 
 impl<M: Meta> From<PublicRecord<M>> for MultiMapValue<M> {
     fn from(value: PublicRecord<M>) -> Self {
         Self {
-            meta: value.meta,
+            meta: StoredMeta::Owned(value.meta),
             ltime: value.ltime,
             status: value.status,
         }
@@ -342,6 +509,22 @@ impl<M: Send + Sync + Debug + Display + Meta> MultiMap<M> {
         record_map.len()
     }
 
+    // The highest ltime of all the records in this map, regardless of their
+    // status. None if the map is empty.
+    pub fn max_ltime(&self) -> Option<u64> {
+        let c_map = Arc::clone(&self.0);
+        let record_map = c_map.lock().unwrap();
+        record_map.values().map(|r| r.ltime).max()
+    }
+
+    // Whether there is a record for this mui in the map, regardless of its
+    // status.
+    pub fn contains_mui(&self, mui: u32) -> bool {
+        let c_map = Arc::clone(&self.0);
+        let record_map = c_map.lock().unwrap();
+        record_map.contains_key(&mui)
+    }
+
     pub fn get_record_for_active_mui(
         &self,
         mui: u32,
@@ -350,7 +533,7 @@ impl<M: Send + Sync + Debug + Display + Meta> MultiMap<M> {
         let record_map = c_map.lock().unwrap();
 
         record_map.get(&mui).and_then(|r| {
-            if r.status == RouteStatus::Active {
+            if r.status.is_usable() {
                 Some(PublicRecord::from((mui, r.clone())))
             } else {
                 None
@@ -368,6 +551,45 @@ impl<M: Send + Sync + Debug + Display + Meta> MultiMap<M> {
         (best.map(|b| *b.1), bckup.map(|b| *b.1))
     }
 
+    // The muis of all the records that are tied for the best path, i.e.
+    // whose orderable is equal to the one of the best path, in ascending
+    // order. Of these `best_backup` picks the lowest mui as the best path.
+    pub fn ecmp(&self, tbi: M::TBI) -> Vec<u32> {
+        let c_map = Arc::clone(&self.0);
+        let record_map = c_map.lock().unwrap();
+        let Some(best) =
+            record_map.values().map(|r| r.meta.as_orderable(tbi)).min()
+        else {
+            return vec![];
+        };
+        let mut muis = record_map
+            .iter()
+            .filter(|r| r.1.meta.as_orderable(tbi) == best)
+            .map(|r| *r.0)
+            .collect::<Vec<_>>();
+        muis.sort_unstable();
+        muis
+    }
+
+    // Like `best_backup`, but orders the records with `policy`, instead of
+    // the ordering that comes with the Meta of the records.
+    pub fn best_backup_with(
+        &self,
+        policy: &dyn BestPathPolicy<M>,
+    ) -> (Option<u32>, Option<u32>) {
+        let c_map = Arc::clone(&self.0);
+        let record_map = c_map.lock().unwrap();
+        let ord_routes = record_map.iter().map(|r| {
+            PolicyOrdered::new(PublicRecord::from((*r.0, r.1.clone())), policy)
+        });
+        let (best, bckup) =
+            routecore::bgp::path_selection::best_backup_generic(ord_routes);
+        (
+            best.map(|b| b.record.multi_uniq_id),
+            bckup.map(|b| b.record.multi_uniq_id),
+        )
+    }
+
     pub(crate) fn get_record_for_mui_with_rewritten_status(
         &self,
         mui: u32,
@@ -378,17 +600,18 @@ impl<M: Send + Sync + Debug + Display + Meta> MultiMap<M> {
         let record_map = c_map.lock().unwrap();
         record_map.get(&mui).map(|r| {
             // We'll return a cloned record: the record in the store remains
-            // untouched.
-            let mut r = r.clone();
+            // untouched. Its local status is kept in `local_status`.
+            let mut r = PublicRecord::from((mui, r.clone()));
             if bmin.contains(mui) {
                 r.status = rewrite_status;
             }
-            PublicRecord::from((mui, r))
+            r
         })
     }
 
     // Helper to filter out records that are not-active (Inactive or
-    // Withdrawn), or whose mui appears in the global withdrawn index.
+    // Withdrawn), or whose mui appears in the global withdrawn index. Stale
+    // records are kept.
     pub fn get_filtered_records(
         &self,
         mui: Option<u32>,
@@ -415,11 +638,11 @@ impl<M: Send + Sync + Debug + Display + Meta> MultiMap<M> {
         record_map
             .iter()
             .map(move |r| {
-                let mut rec = r.1.clone();
+                let mut rec = PublicRecord::from((*r.0, r.1.clone()));
                 if bmin.contains(*r.0) {
                     rec.status = rewrite_status;
                 }
-                PublicRecord::from((*r.0, rec))
+                rec
             })
             .collect::<Vec<_>>()
     }
@@ -434,8 +657,8 @@ impl<M: Send + Sync + Debug + Display + Meta> MultiMap<M> {
     }
 
     // Returns a vec of records whose keys are not in the supplied bitmap
-    // index, and whose local Status is set to Active. Used to filter out
-    // withdrawn routes.
+    // index, and whose local Status is set to Active or Stale. Used to
+    // filter out withdrawn routes.
     pub fn as_active_records_not_in_bmin(
         &self,
         bmin: &RoaringBitmap,
@@ -445,7 +668,7 @@ impl<M: Send + Sync + Debug + Display + Meta> MultiMap<M> {
         record_map
             .iter()
             .filter_map(|r| {
-                if r.1.status == RouteStatus::Active && !bmin.contains(*r.0) {
+                if r.1.status.is_usable() && !bmin.contains(*r.0) {
                     Some(PublicRecord::from((*r.0, r.1.clone())))
                 } else {
                     None
@@ -455,45 +678,174 @@ impl<M: Send + Sync + Debug + Display + Meta> MultiMap<M> {
     }
 
     // Change the local status of the record for this mui to Withdrawn.
-    pub fn mark_as_withdrawn_for_mui(&self, mui: u32) {
+    // Returns whether the status changed, i.e. whether there was a record
+    // for this mui that wasn't Withdrawn already.
+    pub fn mark_as_withdrawn_for_mui(&self, mui: u32) -> bool {
         let c_map = Arc::clone(&self.0);
         let mut record_map = c_map.lock().unwrap();
         if let Some(rec) = record_map.get_mut(&mui) {
+            let changed = rec.status != RouteStatus::Withdrawn;
             rec.status = RouteStatus::Withdrawn;
             // record_map.insert(mui, rec);
+            changed
+        } else {
+            false
+        }
+    }
+
+    // Change the local status of the record for this mui to Stale, if it is
+    // Active. A record that is Withdrawn or InActive is left alone, since a
+    // Stale record is usable, and would come back in query results. Returns
+    // whether the record was marked.
+    pub fn mark_as_stale_for_mui(&self, mui: u32) -> bool {
+        let c_map = Arc::clone(&self.0);
+        let mut record_map = c_map.lock().unwrap();
+        match record_map.get_mut(&mui) {
+            Some(rec) if rec.status == RouteStatus::Active => {
+                rec.status = RouteStatus::Stale;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Change the local status of all the Stale records with a logical time
+    // before `older_than_ltime` to Withdrawn. Returns the number of records
+    // that were withdrawn.
+    pub fn withdraw_stale_records(&self, older_than_ltime: u64) -> usize {
+        let c_map = Arc::clone(&self.0);
+        let mut record_map = c_map.lock().unwrap();
+        let mut count = 0;
+        for rec in record_map.values_mut() {
+            if rec.status == RouteStatus::Stale && rec.ltime < older_than_ltime
+            {
+                rec.status = RouteStatus::Withdrawn;
+                count += 1;
+            }
         }
+        count
+    }
+
+    // Remove all the records for which `f` returns false. Returns the
+    // number of records that were removed.
+    pub fn retain_records(
+        &self,
+        mut f: impl FnMut(&PublicRecord<M>) -> bool,
+    ) -> usize {
+        let c_map = Arc::clone(&self.0);
+        let mut record_map = c_map.lock().unwrap();
+        let len = record_map.len();
+        record_map
+            .retain(|mui, rec| f(&PublicRecord::from((*mui, rec.clone()))));
+        len - record_map.len()
     }
 
     // Change the local status of the record for this mui to Active.
-    pub fn mark_as_active_for_mui(&self, mui: u32) {
+    // Returns whether the status changed, i.e. whether there was a record
+    // for this mui that wasn't Active already.
+    pub fn mark_as_active_for_mui(&self, mui: u32) -> bool {
         let record_map = Arc::clone(&self.0);
         let mut r_map = record_map.lock().unwrap();
         if let Some(rec) = r_map.get_mut(&mui) {
+            let changed = rec.status != RouteStatus::Active;
             rec.status = RouteStatus::Active;
             // r_map.insert(mui, rec);
+            changed
+        } else {
+            false
         }
     }
 
-    // Insert or replace the PublicRecord in the HashMap for the key of
-    // record.multi_uniq_id. Returns the number of entries in the HashMap
-    // after updating it, if it's more than 1. Returns None if this is the
-    // first entry.
-    pub fn upsert_record(
+    // Move all the records out of the HashMap, leaving it empty. The
+    // records are not cloned.
+    pub(crate) fn take_records(&self) -> Vec<PublicRecord<M>> {
+        let c_map = Arc::clone(&self.0);
+        let mut record_map = c_map.lock().unwrap();
+        std::mem::take(&mut *record_map)
+            .into_iter()
+            .map(PublicRecord::from)
+            .collect::<Vec<_>>()
+    }
+
+    // Shrink the capacity of the HashMap to fit its current entries. Returns
+    // an estimate of the number of bytes released.
+    pub(crate) fn shrink_to_fit(&self) -> usize {
+        let c_map = Arc::clone(&self.0);
+        let mut record_map = c_map.lock().unwrap();
+        let capacity = record_map.capacity();
+        record_map.shrink_to_fit();
+
+        (capacity - record_map.capacity())
+            * std::mem::size_of::<(u32, MultiMapValue<M>)>()
+    }
+
+    // Insert or replace the record in the HashMap for the key of `mui`.
+    // Returns the number of entries in the HashMap after updating it, if
+    // it's more than 1. Returns None if this is the first entry.
+    pub(crate) fn upsert_record(
         &self,
-        record: PublicRecord<M>,
+        mui: u32,
+        record: MultiMapValue<M>,
     ) -> (Option<usize>, usize) {
         let c_map = self.clone();
         let (mut record_map, retry_count) = c_map.guard_with_retry(0);
 
-        if record_map
-            .insert(record.multi_uniq_id, MultiMapValue::from(record))
-            .is_some()
+        if record_map.insert(mui, record).is_some()
         {
             (Some(record_map.len()), retry_count)
         } else {
             (None, retry_count)
         }
     }
+
+    // Insert or replace the record in the HashMap like `upsert_record`,
+    // but refuse to create an entry for a Withdrawn record: a withdrawal
+    // for a mui that has no record yet leaves the HashMap untouched, and
+    // returns None. Since the check and the insert happen under the same
+    // lock, a concurrent upsert for the mui can't slip in between them.
+    pub(crate) fn upsert_record_skip_unknown_withdrawal(
+        &self,
+        mui: u32,
+        record: MultiMapValue<M>,
+    ) -> Option<(Option<usize>, usize)> {
+        let c_map = self.clone();
+        let (mut record_map, retry_count) = c_map.guard_with_retry(0);
+
+        match record_map.entry(mui) {
+            Entry::Occupied(mut entry) => {
+                entry.insert(record);
+                Some((Some(record_map.len()), retry_count))
+            }
+            Entry::Vacant(_) if record.status == RouteStatus::Withdrawn => {
+                None
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(record);
+                Some((None, retry_count))
+            }
+        }
+    }
+
+    // Insert the record in the HashMap for the key of `mui`, if there's no
+    // entry for that key yet. Returns the same as `upsert_record` if the
+    // record was inserted, and RecordAlreadyExists, leaving the HashMap
+    // untouched, otherwise.
+    pub(crate) fn insert_record(
+        &self,
+        mui: u32,
+        record: MultiMapValue<M>,
+    ) -> Result<(Option<usize>, usize), PrefixStoreError> {
+        let c_map = self.clone();
+        let (mut record_map, retry_count) = c_map.guard_with_retry(0);
+
+        match record_map.entry(mui) {
+            Entry::Occupied(_) => Err(PrefixStoreError::RecordAlreadyExists),
+            Entry::Vacant(entry) => {
+                entry.insert(record);
+                Ok((None, retry_count))
+            }
+        }
+    }
 }
 
 impl<M: Meta> Clone for MultiMap<M> {
@@ -502,6 +854,100 @@ impl<M: Meta> Clone for MultiMap<M> {
     }
 }
 
+impl<M: Meta> MultiMap<M> {
+    // Lock the HashMap for the duration of `f`, and hand `f` a view on it
+    // that can read and modify the records. Returns the result of `f` and
+    // whether any records were modified.
+    pub(crate) fn with_view<R>(
+        &self,
+        f: impl FnOnce(&mut MultiMapView<M>) -> R,
+    ) -> (R, bool) {
+        let c_map = Arc::clone(&self.0);
+        let mut record_map = c_map.lock().unwrap();
+        let mut view = MultiMapView {
+            record_map: &mut record_map,
+            modified: false,
+        };
+        let res = f(&mut view);
+        let modified = view.modified;
+
+        (res, modified)
+    }
+}
+
+// ----------- MultiMapView --------------------------------------------------
+// A view on the locked HashMap of a MultiMap, that is handed out to the
+// closure of a read-modify-write operation on the records of a prefix.
+
+/// A locked view on all the records for a prefix.
+///
+/// The records can be read and modified for muis that already have a
+/// record for the prefix. New muis can only be added through `insert` on
+/// the store, so that the mui indexes in the tree stay up to date. Any
+/// modification marks the stored best and backup path of the prefix as
+/// outdated.
+#[derive(Debug)]
+pub struct MultiMapView<'a, M: Meta> {
+    record_map: &'a mut HashMap<u32, MultiMapValue<M>>,
+    modified: bool,
+}
+
+impl<'a, M: Meta> MultiMapView<'a, M> {
+    /// Returns the number of records for the prefix.
+    pub fn len(&self) -> usize {
+        self.record_map.len()
+    }
+
+    /// Whether there are no records for the prefix.
+    pub fn is_empty(&self) -> bool {
+        self.record_map.is_empty()
+    }
+
+    /// Returns the record for the `mui`, regardless of its status.
+    pub fn get(&self, mui: u32) -> Option<PublicRecord<M>> {
+        self.record_map
+            .get(&mui)
+            .map(|r| PublicRecord::from((mui, r.clone())))
+    }
+
+    /// Returns all the records for the prefix, regardless of their status.
+    pub fn records(&self) -> Vec<PublicRecord<M>> {
+        self.record_map
+            .iter()
+            .map(|r| PublicRecord::from((*r.0, r.1.clone())))
+            .collect::<Vec<_>>()
+    }
+
+    /// Replace the record for the mui of `record` and return the previous
+    /// one. If there is no record for this mui, nothing is stored and
+    /// `None` is returned.
+    pub fn replace(
+        &mut self,
+        record: PublicRecord<M>,
+    ) -> Option<PublicRecord<M>> {
+        let mui = record.multi_uniq_id;
+        let rec = self.record_map.get_mut(&mui)?;
+        self.modified = true;
+
+        Some(PublicRecord::from((
+            mui,
+            std::mem::replace(rec, MultiMapValue::from(record)),
+        )))
+    }
+
+    /// Change the local status of the record for the `mui`. Returns
+    /// whether a record for the mui exists.
+    pub fn set_status(&mut self, mui: u32, status: RouteStatus) -> bool {
+        if let Some(rec) = self.record_map.get_mut(&mui) {
+            rec.status = status;
+            self.modified = true;
+            true
+        } else {
+            false
+        }
+    }
+}
+
 // ----------- AtomicStoredPrefix -------------------------------------------
 // Unlike StoredNode, we don't need an Empty variant, since we're using
 // serial == 0 as the empty value. We're not using an Option here, to
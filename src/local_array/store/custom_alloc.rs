@@ -184,8 +184,12 @@
 // produce a fix for it).
 
 use std::{
+    collections::HashMap,
     fmt::Debug,
+    io::{self, Read, Write},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
     sync::atomic::{AtomicUsize, Ordering},
+    sync::{Arc, Mutex},
 };
 
 use log::{debug, info, log_enabled, trace};
@@ -197,7 +201,10 @@ use roaring::RoaringBitmap;
 
 use std::marker::PhantomData;
 
-use crate::{local_array::tree::*, stats::CreatedNodes};
+use crate::{
+    local_array::tree::*,
+    stats::{ChainDepth, CreatedNodes},
+};
 use crate::{
     local_array::{bit_span::BitSpan, store::errors::PrefixStoreError},
     prefix_record::PublicRecord,
@@ -209,7 +216,14 @@ use crate::{
 };
 
 use super::atomic_types::*;
+use super::interner::MetaInterner;
+use crate::rotonda_store::{
+    MatchOptions, QueryCostEstimate, DEFAULT_MAX_RETRIES_BEFORE_YIELD,
+};
 use crate::AddressFamily;
+use inetnum::addr::Prefix;
+
+use crate::local_array::atomic_stride::AtomicBitmap;
 
 //------------ Counters -----------------------------------------------------
 
@@ -217,6 +231,9 @@ use crate::AddressFamily;
 pub struct Counters {
     nodes: AtomicUsize,
     prefixes: [AtomicUsize; 129],
+    // The number of times a node could not be stored at all, e.g. because
+    // the store ran out of levels. This is an internal logic error.
+    fatal_node_errors: AtomicUsize,
 }
 
 impl Counters {
@@ -239,6 +256,14 @@ impl Counters {
         self.prefixes[len as usize].fetch_add(1, Ordering::Relaxed);
     }
 
+    pub fn get_fatal_node_errors_count(&self) -> usize {
+        self.fatal_node_errors.load(Ordering::Relaxed)
+    }
+
+    pub fn inc_fatal_node_errors_count(&self) {
+        self.fatal_node_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
     pub fn get_prefix_stats(&self) -> Vec<CreatedNodes> {
         self.prefixes
             .iter()
@@ -268,6 +293,7 @@ impl Default for Counters {
         Self {
             nodes: AtomicUsize::new(0),
             prefixes: prefixes.try_into().unwrap(),
+            fatal_node_errors: AtomicUsize::new(0),
         }
     }
 }
@@ -278,6 +304,193 @@ impl Default for Counters {
 pub struct StoreStats {
     pub v4: Vec<CreatedNodes>,
     pub v6: Vec<CreatedNodes>,
+    // The number of nodes that could not be created, for both address
+    // families. Anything other than zero indicates an internal error in the
+    // store.
+    pub fatal_node_errors: usize,
+}
+
+//------------ StatsReport ---------------------------------------------------
+
+/// A machine-readable report of the nodes and prefixes in the store, for
+/// both address families. Unlike the output of `print_funky_stats`, it
+/// doesn't depend on the `cli` feature, and it can be serialized with the
+/// `serde` feature.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct StatsReport {
+    pub v4: AfStatsReport,
+    pub v6: AfStatsReport,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AfStatsReport {
+    pub nodes: usize,
+    pub prefixes: usize,
+    pub records: usize,
+    /// The nodes and prefixes per length, only for the lengths that have
+    /// either of them.
+    pub lengths: Vec<LenStatsReport>,
+    /// An estimate of the memory used by the nodes, the prefixes and their
+    /// records, in bytes. It does not account for the arrays of the buckets
+    /// and the overhead of the record maps.
+    pub memory_bytes: usize,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LenStatsReport {
+    pub len: u8,
+    pub nodes: usize,
+    pub prefixes: usize,
+    /// The percentage of all the possible prefixes of this length that are
+    /// in the store.
+    pub prefixes_occupied_pct: f64,
+}
+
+//------------ HealthReport --------------------------------------------------
+
+/// The nodes in the store whose mui index is poisoned, for both address
+/// families. A poisoned index was left behind by a thread that panicked
+/// while it was updating it, and queries for a mui that hit it will panic.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct HealthReport {
+    /// The number of IPv4 nodes with a poisoned mui index.
+    pub v4_poisoned_nodes: usize,
+    /// The number of IPv6 nodes with a poisoned mui index.
+    pub v6_poisoned_nodes: usize,
+}
+
+impl HealthReport {
+    /// Whether no poisoned nodes were found.
+    pub fn is_healthy(&self) -> bool {
+        self.v4_poisoned_nodes == 0 && self.v6_poisoned_nodes == 0
+    }
+}
+
+//------------ ChainStats ----------------------------------------------------
+
+/// The depths of the bucket chains, per length, that the nodes and the
+/// prefixes live in, for both address families. Only lengths that have
+/// stored elements are included.
+#[derive(Debug)]
+pub struct ChainStats {
+    pub v4: ChainDepths,
+    pub v6: ChainDepths,
+}
+
+#[derive(Debug, Default)]
+pub struct ChainDepths {
+    pub nodes: Vec<ChainDepth>,
+    pub prefixes: Vec<ChainDepth>,
+}
+
+//------------ FillFactors ---------------------------------------------------
+
+/// The occupancy of the buckets that the nodes live in, per length and level,
+/// for both address families.
+#[derive(Debug)]
+pub struct FillFactors {
+    pub v4: Vec<LevelFill>,
+    pub v6: Vec<LevelFill>,
+}
+
+/// The occupancy of the allocated buckets for the nodes of one length at one
+/// level of the chains of buckets.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LevelFill {
+    /// The length of the node ids.
+    pub len: u8,
+    /// The level in the chains of buckets, 0 for the root array.
+    pub level: u8,
+    /// The number of allocated buckets at this level.
+    pub buckets: usize,
+    /// The number of slots in these buckets.
+    pub slots: usize,
+    /// The number of slots that hold a node.
+    pub occupied: usize,
+}
+
+impl LevelFill {
+    /// The fraction of the slots that hold a node.
+    pub fn fill_factor(&self) -> f64 {
+        if self.slots == 0 {
+            return 0.0;
+        }
+        self.occupied as f64 / self.slots as f64
+    }
+}
+
+//------------ MemoryStats ---------------------------------------------------
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// The number of prefixes whose record maps were visited.
+    pub prefixes: usize,
+    /// The number of record maps that had spare capacity and were shrunk.
+    pub shrunk_maps: usize,
+    /// An estimate of the number of bytes returned to the allocator, based on
+    /// the size of the entries in the record maps. It does not account for
+    /// the overhead of the HashMaps themselves.
+    pub reclaimed_bytes: usize,
+}
+
+impl std::ops::Add for MemoryStats {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self {
+            prefixes: self.prefixes + rhs.prefixes,
+            shrunk_maps: self.shrunk_maps + rhs.shrunk_maps,
+            reclaimed_bytes: self.reclaimed_bytes + rhs.reclaimed_bytes,
+        }
+    }
+}
+
+//------------ NodeDebug -----------------------------------------------------
+
+// A snapshot of the internals of a node in the tree bitmap, for debugging
+// purposes only.
+#[cfg(feature = "debug-internals")]
+#[derive(Clone, Debug)]
+pub struct NodeDebug {
+    // The node id, expressed as the prefix formed by the bits of the node id
+    // and its length.
+    pub node_id: Prefix,
+    // The stride size of the node.
+    pub stride: u8,
+    // The bitmap of the prefixes hosted by this node.
+    pub pfxbitarr: u64,
+    // The bitmap of the child nodes of this node.
+    pub ptrbitarr: u64,
+    // The index of the muis that appear in the sub-tree of this node.
+    pub muis: RoaringBitmap,
+}
+
+//------------ NodeReport ----------------------------------------------------
+
+/// The structure of a node in the tree bitmap, as reported by `node_iter`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NodeReport {
+    /// The node id, expressed as the prefix formed by the bits of the node id
+    /// and its length.
+    pub node_id: Prefix,
+    /// The length of the node id.
+    pub len: u8,
+    /// The number of nodes between the root node and this node, zero for the
+    /// root node itself.
+    pub depth: usize,
+    /// The stride size of the node.
+    pub stride: u8,
+    /// The number of bits set in the pfxbitarr, i.e. the number of prefixes
+    /// hosted by this node.
+    pub pfx_count: u32,
+    /// The number of bits set in the ptrbitarr.
+    pub ptr_count: u32,
+    /// The number of child nodes that could be retrieved from the store. This
+    /// may be lower than `ptr_count` while a child node is being created.
+    pub child_count: usize,
 }
 
 //------------ UpsertReport --------------------------------------------------
@@ -296,6 +509,122 @@ pub struct UpsertReport {
     pub mui_new: bool,
     // The number of mui records for this prefix after the upsert operation.
     pub mui_count: usize,
+    // The number of times the inserting thread yielded, because it had to
+    // retry the update of the bitmaps of a node more than the
+    // `max_retries_before_yield` of the StoreConfig.
+    pub backoff_count: usize,
+    // Whether this upsert created the (prefix, mui) record, or replaced an
+    // existing one.
+    pub outcome: UpsertOutcome,
+}
+
+//------------ UpsertOutcome -------------------------------------------------
+
+// The effect an upsert had on the record for the (prefix, mui) combination
+// it was called with.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum UpsertOutcome {
+    // There was no record for this mui for the prefix, a new one was
+    // inserted.
+    Inserted,
+    // There already was a record for this mui for the prefix, it was
+    // replaced by the new record.
+    Replaced,
+    // Nothing was changed, the record was a withdrawal for a (prefix, mui)
+    // combination that wasn't in the store, and the store is configured to
+    // ignore those.
+    NoOp,
+}
+
+impl std::fmt::Display for UpsertOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpsertOutcome::Inserted => write!(f, "inserted"),
+            UpsertOutcome::Replaced => write!(f, "replaced"),
+            UpsertOutcome::NoOp => write!(f, "no-op"),
+        }
+    }
+}
+
+//------------ UpsertMode ----------------------------------------------------
+
+// What an upsert does with the record for the (prefix, mui) combination it
+// was called with.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum UpsertMode {
+    // Insert the record, or replace the existing record for the mui.
+    Replace,
+    // Insert the record, but leave an existing record for the mui alone,
+    // and return RecordAlreadyExists.
+    InsertOnly,
+    // Like Replace, but don't insert a Withdrawn record for a mui that has
+    // no record for the prefix yet. The upsert is a NoOp instead.
+    SkipUnknownWithdrawal,
+}
+
+//------------ InsertPreview ------------------------------------------------
+
+/// The effect an upsert for a (prefix, mui) combination would have, if it
+/// were executed at the time the preview was created.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct InsertPreview {
+    /// Whether the prefix is already in the store.
+    pub prefix_exists: bool,
+    /// Whether there already is a record for the mui for this prefix, with
+    /// any status.
+    pub mui_exists: bool,
+    /// The number of records for the prefix, with any status.
+    pub mui_count: usize,
+    /// The outcome the upsert would have.
+    pub outcome: UpsertOutcome,
+}
+
+//------------ NodeHasher ----------------------------------------------------
+
+// The hashing function that turns a node id into the index of its slot in
+// the bucket at a level, see the comments on `hash_node_id` in
+// CustomAllocStorage for the details.
+//
+// A hasher receives the id together with the bit positions where the level
+// above (`last_level`) and this level (`this_level`) end. It should return
+// an index smaller than `1 << (this_level - last_level)`. Node ids that
+// collide at a level end up in the same bucket at the next level, and there
+// is no level after the last one for a length. So, for node ids that share
+// the bits up to `last_level`, the index MUST be unique for each value of
+// the bits from `last_level` up to `this_level`, or some nodes could never
+// be stored.
+//
+// Because of that, a valid hasher can only permute the slots within a
+// bucket. All the node ids in a bucket below the root level share the bits
+// above this level, so the number of occupied slots in every bucket, and
+// thus the chain depths, are the same for every valid hasher. A hasher that
+// mixes in the bits above this level (e.g. with Fibonacci hashing[^3]) was
+// tried, and it didn't change the chain depths, nor the insert and lookup
+// times, so only the DefaultNodeHasher is provided.
+pub trait NodeHasher {
+    fn hash_node_id<AF: AddressFamily>(
+        id: StrideNodeId<AF>,
+        last_level: u8,
+        this_level: u8,
+    ) -> usize;
+}
+
+// The hasher the store uses by default: the index is the slice of the bits
+// of the node id for this level.
+#[derive(Debug)]
+pub struct DefaultNodeHasher;
+
+impl NodeHasher for DefaultNodeHasher {
+    fn hash_node_id<AF: AddressFamily>(
+        id: StrideNodeId<AF>,
+        last_level: u8,
+        this_level: u8,
+    ) -> usize {
+        // HASHING FUNCTION
+        ((id.get_id().0 << last_level)
+            >> ((<AF>::BITS - (this_level - last_level)) % <AF>::BITS))
+            .dangerously_truncate_to_u32() as usize
+    }
 }
 
 // ----------- CustomAllocStorage -------------------------------------------
@@ -308,15 +637,30 @@ pub struct CustomAllocStorage<
     M: crate::prefix_record::Meta,
     NB: NodeBuckets<AF>,
     PB: PrefixBuckets<AF, M>,
+    H: NodeHasher = DefaultNodeHasher,
 > {
     pub(crate) buckets: NB,
     pub prefixes: PB,
     pub default_route_prefix_serial: AtomicUsize,
     // Global Roaring Bitmap INdex that stores MUIs.
     pub withdrawn_muis_bmin: Atomic<RoaringBitmap>,
+    // The ltime at which muis in the withdrawn_muis_bmin were withdrawn, if
+    // the caller supplied one. Writers of the bitmap hold this lock while
+    // they swap it, so the two stay in step. The bitmap stays the authority
+    // on whether a mui is withdrawn.
+    withdrawn_muis_ltime: Mutex<HashMap<u32, u64>>,
     pub counters: Counters,
+    // The number of retries of a compare-and-swap on the bitmaps of a node
+    // after which an inserting thread yields instead of spinning. Set from
+    // the StoreConfig.
+    pub max_retries_before_yield: u32,
+    // The interner of the meta-data of the records, shared by the stores
+    // of both address families. Only set if the StoreConfig asks for
+    // interning.
+    pub meta_interner: Option<Arc<MetaInterner<M>>>,
     _m: PhantomData<M>,
     _af: PhantomData<AF>,
+    _h: PhantomData<H>,
 }
 
 impl<
@@ -325,7 +669,8 @@ impl<
         M: crate::prefix_record::Meta,
         NB: NodeBuckets<AF>,
         PB: PrefixBuckets<AF, M>,
-    > CustomAllocStorage<AF, M, NB, PB>
+        H: NodeHasher,
+    > CustomAllocStorage<AF, M, NB, PB, H>
 {
     pub(crate) fn init(
         root_node: SizedStrideNode<AF>,
@@ -345,9 +690,13 @@ impl<
             prefixes: PrefixBuckets::<AF, M>::init(),
             default_route_prefix_serial: AtomicUsize::new(0),
             withdrawn_muis_bmin: RoaringBitmap::new().into(),
+            withdrawn_muis_ltime: Mutex::new(HashMap::new()),
             counters: Counters::default(),
+            max_retries_before_yield: DEFAULT_MAX_RETRIES_BEFORE_YIELD,
+            meta_interner: None,
             _af: PhantomData,
             _m: PhantomData,
+            _h: PhantomData,
         };
 
         let _retry_count = store.store_node(
@@ -634,17 +983,23 @@ impl<
     //    If Step 4 succeeded we're done!
     // 6. FAILURE - REPEAT
     //    If Step 4 failed we're going to do the whole thing again.
+    //
+    // The `mode` decides what happens with an existing record for the mui
+    // of `record`, see UpsertMode.
 
     pub(crate) fn upsert_prefix(
         &self,
         prefix: PrefixId<AF>,
         record: PublicRecord<M>,
         update_path_selections: Option<M::TBI>,
+        mode: UpsertMode,
         guard: &Guard,
     ) -> Result<UpsertReport, PrefixStoreError> {
         let mut prefix_new = true;
+        let mui = record.multi_uniq_id;
+        let record = self.multi_map_value(record);
 
-        let (mui_new, insert_retry_count) =
+        let (upserted, insert_retry_count, record_map) =
             match self.non_recursive_retrieve_prefix_mut(prefix) {
                 // There's no StoredPrefix at this location yet. Create a new
                 // PrefixRecord and try to store it in the empty slot.
@@ -658,7 +1013,7 @@ impl<
 
                     // We're creating a StoredPrefix without our record first,
                     // to avoid having to clone it on retry.
-                    let res = locked_prefix
+                    let record_map = &locked_prefix
                         // .get_or_init(|| {
                         //     StoredPrefix::new::<PB>(
                         //         PrefixId::new(prefix.get_net(), prefix.get_len()),
@@ -666,11 +1021,23 @@ impl<
                         //     )
                         // })
                         // .0
-                        .record_map
-                        .upsert_record(record);
+                        .record_map;
+                    let res = match mode {
+                        UpsertMode::Replace => {
+                            Ok(Some(record_map.upsert_record(mui, record)))
+                        }
+                        UpsertMode::InsertOnly => {
+                            record_map.insert_record(mui, record).map(Some)
+                        }
+                        UpsertMode::SkipUnknownWithdrawal => Ok(record_map
+                            .upsert_record_skip_unknown_withdrawal(
+                                mui, record,
+                            )),
+                    };
 
                     self.counters.inc_prefixes_count(prefix.get_len());
-                    res
+                    let res = res?;
+                    (res.map(|r| r.0), res.map_or(0, |r| r.1), record_map)
                 }
                 // There already is a StoredPrefix with a record at this
                 // location.
@@ -687,33 +1054,129 @@ impl<
 
                     // Update the already existing record_map with our caller's
                     // record.
-                    stored_prefix.set_ps_outdated(guard)?;
-                    let res = stored_prefix.record_map.upsert_record(record);
+                    let res = match mode {
+                        UpsertMode::Replace => {
+                            stored_prefix.set_ps_outdated(guard)?;
+                            Some(
+                                stored_prefix
+                                    .record_map
+                                    .upsert_record(mui, record),
+                            )
+                        }
+                        UpsertMode::InsertOnly => {
+                            let res = stored_prefix
+                                .record_map
+                                .insert_record(mui, record)?;
+                            stored_prefix.set_ps_outdated(guard)?;
+                            Some(res)
+                        }
+                        UpsertMode::SkipUnknownWithdrawal => {
+                            let res = stored_prefix
+                                .record_map
+                                .upsert_record_skip_unknown_withdrawal(
+                                    mui, record,
+                                );
+                            if res.is_some() {
+                                stored_prefix.set_ps_outdated(guard)?;
+                            }
+                            res
+                        }
+                    };
 
-                    if let Some(tbi) = update_path_selections {
+                    if let (Some(tbi), Some(_)) = (update_path_selections, res)
+                    {
                         stored_prefix
                             .calculate_and_store_best_backup(&tbi, guard)?;
                     }
 
-                    res
+                    (
+                        res.map(|r| r.0),
+                        res.map_or(0, |r| r.1),
+                        &stored_prefix.record_map,
+                    )
                 }
             };
 
+        let Some(mui_new) = upserted else {
+            return Ok(UpsertReport {
+                prefix_new,
+                cas_count: insert_retry_count,
+                backoff_count: 0,
+                mui_new: false,
+                mui_count: record_map.len(),
+                outcome: UpsertOutcome::NoOp,
+            });
+        };
+
         Ok(UpsertReport {
             prefix_new,
             cas_count: insert_retry_count,
+            backoff_count: 0,
             mui_new: mui_new.is_none(),
             mui_count: mui_new.unwrap_or(1),
+            outcome: if mui_new.is_none() {
+                UpsertOutcome::Inserted
+            } else {
+                UpsertOutcome::Replaced
+            },
         })
     }
 
+    // Convert `record` to the value that is stored for it in the record map
+    // of its prefix, interning its meta-data if the store does that.
+    fn multi_map_value(&self, record: PublicRecord<M>) -> MultiMapValue<M> {
+        let meta = match &self.meta_interner {
+            Some(interner) => match interner.intern(record.meta) {
+                Ok(meta) => StoredMeta::Interned(meta),
+                Err(meta) => StoredMeta::Owned(meta),
+            },
+            None => StoredMeta::Owned(record.meta),
+        };
+
+        MultiMapValue {
+            meta,
+            ltime: record.ltime,
+            status: record.status,
+        }
+    }
+
+    // Report what an upsert of a record for `mui` for `prefix` would do,
+    // without changing anything in the store. Concurrent upserts may make
+    // the preview outdated as soon as it is returned.
+    pub fn preview_upsert(
+        &'a self,
+        prefix: PrefixId<AF>,
+        mui: u32,
+    ) -> InsertPreview {
+        let (prefix_exists, mui_exists, mui_count) =
+            match self.non_recursive_retrieve_prefix(prefix).0 {
+                Some(s_pfx) => (
+                    true,
+                    s_pfx.record_map.contains_mui(mui),
+                    s_pfx.record_map.len(),
+                ),
+                None => (false, false, 0),
+            };
+
+        InsertPreview {
+            prefix_exists,
+            mui_exists,
+            mui_count,
+            outcome: if mui_exists {
+                UpsertOutcome::Replaced
+            } else {
+                UpsertOutcome::Inserted
+            },
+        }
+    }
+
     // Change the status of the record for the specified (prefix, mui)
-    // combination  to Withdrawn.
+    // combination  to Withdrawn. Returns whether the status changed.
     pub fn mark_mui_as_withdrawn_for_prefix(
         &self,
         prefix: PrefixId<AF>,
         mui: u32,
-    ) -> Result<(), PrefixStoreError> {
+    ) -> Result<bool, PrefixStoreError> {
         let (stored_prefix, exists) =
             self.non_recursive_retrieve_prefix_mut(prefix);
 
@@ -721,18 +1184,34 @@ impl<
             return Err(PrefixStoreError::StoreNotReadyError);
         }
 
-        stored_prefix.record_map.mark_as_withdrawn_for_mui(mui);
+        Ok(stored_prefix.record_map.mark_as_withdrawn_for_mui(mui))
+    }
 
-        Ok(())
+    // Change the status of the records for the mui for all the specified
+    // prefixes to Withdrawn. Prefixes that are not in the store, or don't
+    // have a record for the mui, are skipped. Returns the prefixes for which
+    // the status of the record changed.
+    pub fn mark_mui_as_withdrawn_for_prefixes(
+        &'a self,
+        prefixes: impl Iterator<Item = PrefixId<AF>>,
+        mui: u32,
+    ) -> Vec<PrefixId<AF>> {
+        prefixes
+            .filter(|prefix| {
+                self.non_recursive_retrieve_prefix(*prefix).0.is_some_and(
+                    |s_pfx| s_pfx.record_map.mark_as_withdrawn_for_mui(mui),
+                )
+            })
+            .collect()
     }
 
     // Change the status of the record for the specified (prefix, mui)
-    // combination  to Active.
+    // combination  to Active. Returns whether the status changed.
     pub fn mark_mui_as_active_for_prefix(
         &self,
         prefix: PrefixId<AF>,
         mui: u32,
-    ) -> Result<(), PrefixStoreError> {
+    ) -> Result<bool, PrefixStoreError> {
         let (stored_prefix, exists) =
             self.non_recursive_retrieve_prefix_mut(prefix);
 
@@ -740,55 +1219,282 @@ impl<
             return Err(PrefixStoreError::StoreNotReadyError);
         }
 
-        stored_prefix.record_map.mark_as_active_for_mui(mui);
+        Ok(stored_prefix.record_map.mark_as_active_for_mui(mui))
+    }
+
+    // Change the status of the record for the specified (prefix, mui)
+    // combination to Stale, if it is Active. Returns whether the status
+    // changed.
+    pub fn mark_mui_as_stale_for_prefix(
+        &self,
+        prefix: PrefixId<AF>,
+        mui: u32,
+    ) -> Result<bool, PrefixStoreError> {
+        let (stored_prefix, exists) =
+            self.non_recursive_retrieve_prefix_mut(prefix);
+
+        if !exists {
+            return Err(PrefixStoreError::StoreNotReadyError);
+        }
+
+        Ok(stored_prefix.record_map.mark_as_stale_for_mui(mui))
+    }
+
+    // Change the status of the Active records for this mui to Stale. The
+    // prefixes are found through the mui bitmap indexes on the nodes, and
+    // only the prefixes with a usable record for the mui are visited.
+    // Returns the number of records that were marked.
+    pub fn mark_mui_as_stale(&'a self, mui: u32, guard: &'a Guard) -> usize {
+        let mut count = 0;
+
+        for (pfx_id, _) in self.prefixes_iter_for_mui(mui, false, guard) {
+            if let (Some(stored_prefix), _) =
+                self.non_recursive_retrieve_prefix(pfx_id)
+            {
+                if stored_prefix.record_map.mark_as_stale_for_mui(mui) {
+                    count += 1;
+                }
+            }
+        }
+
+        count
+    }
+
+    // Withdraw all the records that are still Stale and have a logical time
+    // before `older_than_ltime`. The best and backup path for the prefixes
+    // that had records withdrawn are marked as outdated. Returns the number
+    // of records that were withdrawn.
+    pub fn sweep_stale(
+        &'a self,
+        older_than_ltime: u64,
+        guard: &Guard,
+    ) -> Result<usize, PrefixStoreError> {
+        let mut count = 0;
+
+        for stored_prefix in self.stored_prefixes_iter() {
+            let withdrawn =
+                stored_prefix.record_map.withdraw_stale_records(older_than_ltime);
+            if withdrawn > 0 {
+                stored_prefix.set_ps_outdated(guard)?;
+                count += withdrawn;
+            }
+        }
+
+        Ok(count)
+    }
+
+    // Remove all the records for which `f` returns false from all the
+    // prefixes in the store. The best and backup path for the prefixes that
+    // had records removed are marked as outdated. The prefixes themselves
+    // stay in the store, also if they lost all their records. Returns the
+    // number of records that were removed.
+    pub fn retain_records(
+        &'a self,
+        mut f: impl FnMut(PrefixId<AF>, &PublicRecord<M>) -> bool,
+        guard: &Guard,
+    ) -> usize {
+        let mut count = 0;
+
+        for stored_prefix in self.stored_prefixes_iter() {
+            let prefix_id = stored_prefix.get_prefix_id();
+            let removed = stored_prefix
+                .record_map
+                .retain_records(|rec| f(prefix_id, rec));
+            if removed > 0 {
+                // The update closure of `set_ps_outdated` always returns
+                // Some, so this can't fail.
+                let _ = stored_prefix.set_ps_outdated(guard);
+                count += removed;
+            }
+        }
+
+        count
+    }
+
+    // Shrink the record maps of all the prefixes in this store to fit their
+    // current number of records. The nodes and the prefix buckets are not
+    // touched, they can't be removed from the store.
+    pub fn compact_memory(&'a self) -> MemoryStats {
+        let mut stats = MemoryStats::default();
+
+        for stored_prefix in self.stored_prefixes_iter() {
+            stats.prefixes += 1;
+            let reclaimed = stored_prefix.record_map.shrink_to_fit();
+            if reclaimed > 0 {
+                stats.shrunk_maps += 1;
+                stats.reclaimed_bytes += reclaimed;
+            }
+        }
+
+        stats
+    }
+
+    // Lock the records for the specified prefix, and run `f` on a view on
+    // them. If `f` modified the records, the path selections for the
+    // prefix are marked as outdated.
+    pub fn with_prefix_records<R>(
+        &self,
+        prefix: PrefixId<AF>,
+        f: impl FnOnce(&mut MultiMapView<M>) -> R,
+        guard: &Guard,
+    ) -> Result<R, PrefixStoreError> {
+        let stored_prefix = self
+            .non_recursive_retrieve_prefix(prefix)
+            .0
+            .ok_or(PrefixStoreError::PrefixNotFound)?;
+
+        let (res, modified) = stored_prefix.record_map.with_view(f);
+
+        if modified {
+            stored_prefix.set_ps_outdated(guard)?;
+        }
+
+        Ok(res)
+    }
+
+    // Set the opaque per-prefix attribute of the specified prefix, replacing
+    // the one that was set before, if any. The prefix has to exist.
+    pub fn set_prefix_attr(
+        &self,
+        prefix: PrefixId<AF>,
+        attr: Vec<u8>,
+        guard: &Guard,
+    ) -> Result<(), PrefixStoreError> {
+        self.non_recursive_retrieve_prefix(prefix)
+            .0
+            .ok_or(PrefixStoreError::PrefixNotFound)?
+            .set_attr(attr, guard);
 
         Ok(())
     }
 
+    // The opaque per-prefix attribute of the specified prefix. None if the
+    // prefix doesn't exist, or its attribute was never set.
+    pub fn get_prefix_attr(
+        &self,
+        prefix: PrefixId<AF>,
+        guard: &Guard,
+    ) -> Option<Vec<u8>> {
+        self.non_recursive_retrieve_prefix(prefix)
+            .0
+            .and_then(|p| p.get_attr(guard))
+    }
+
+    // The StoredPrefix of the default route, if it is in the store. The
+    // default route has no place in the prefix buckets that the prefix
+    // iterators walk, nor in the pfxbitarr of any node, so every walk over
+    // all the prefixes in the store has to add it by looking it up here.
+    pub(crate) fn default_route_stored_prefix(
+        &'a self,
+    ) -> Option<&'a StoredPrefix<AF, M>> {
+        self.non_recursive_retrieve_prefix(PrefixId::new(AF::zero(), 0))
+            .0
+    }
+
+    // The records of the default route, read directly from the prefix store,
+    // since the default route doesn't live in the pfxbitarr of any node. The
+    // status of the records for globally withdrawn muis is rewritten to
+    // Withdrawn, like the iterators do. None if there's no default route.
+    pub fn get_default_route(
+        &'a self,
+        guard: &Guard,
+    ) -> Option<Vec<PublicRecord<M>>> {
+        self.non_recursive_retrieve_prefix(PrefixId::new(AF::zero(), 0))
+            .0
+            .map(|p| {
+                let bmin = unsafe {
+                    self.withdrawn_muis_bmin
+                        .load(Ordering::Acquire, guard)
+                        .as_ref()
+                }
+                .unwrap();
+                p.record_map
+                    .as_records_with_rewritten_status(
+                        bmin,
+                        RouteStatus::Withdrawn,
+                    )
+            })
+    }
+
+    // The highest ltime of the records for the specified prefix, regardless
+    // of their status. None if the prefix doesn't exist, or has no records.
+    pub fn get_last_modified(&self, prefix: PrefixId<AF>) -> Option<u64> {
+        self.non_recursive_retrieve_prefix(prefix)
+            .0
+            .and_then(|p| p.record_map.max_ltime())
+    }
+
     // Change the status of the mui globally to Withdrawn. Iterators and match
     // functions will by default not return any records for this mui.
+    // Returns whether the global status changed, i.e. whether the mui
+    // wasn't globally withdrawn already.
     pub fn mark_mui_as_withdrawn(
         &self,
         mui: u32,
         guard: &Guard,
-    ) -> Result<(), PrefixStoreError> {
-        let current = self.withdrawn_muis_bmin.load(Ordering::Acquire, guard);
+    ) -> Result<bool, PrefixStoreError> {
+        // The time of this withdrawal is unknown, don't keep the time of an
+        // earlier one around.
+        Ok(self.update_withdrawn_muis(mui, true, None, guard))
+    }
 
-        let mut new = unsafe { current.as_ref() }.unwrap().clone();
-        new.insert(mui);
+    // Change the status of the mui globally to Withdrawn, like
+    // `mark_mui_as_withdrawn`, and remember `ltime` as the time of the
+    // withdrawal.
+    pub fn mark_mui_as_withdrawn_at(
+        &self,
+        mui: u32,
+        ltime: u64,
+        guard: &Guard,
+    ) -> Result<bool, PrefixStoreError> {
+        Ok(self.update_withdrawn_muis(mui, true, Some(ltime), guard))
+    }
 
-        #[allow(clippy::assigning_clones)]
-        loop {
-            match self.withdrawn_muis_bmin.compare_exchange(
-                current,
-                Owned::new(new),
-                Ordering::AcqRel,
-                Ordering::Acquire,
-                guard,
-            ) {
-                Ok(_) => return Ok(()),
-                Err(updated) => {
-                    new =
-                        unsafe { updated.current.as_ref() }.unwrap().clone();
-                }
-            }
+    // The ltime at which this mui was globally withdrawn. None if the mui
+    // isn't globally withdrawn, or if it was withdrawn without an ltime.
+    pub fn mui_withdrawn_at(&self, mui: u32, guard: &Guard) -> Option<u64> {
+        if !self.mui_is_withdrawn(mui, guard) {
+            return None;
         }
+        self.withdrawn_muis_ltime.lock().unwrap().get(&mui).copied()
     }
 
     // Change the status of the mui globally to Active. Iterators and match
-    // functions will default to the status on the record itself.
+    // functions will default to the status on the record itself. Returns
+    // whether the global status changed, i.e. whether the mui was globally
+    // withdrawn.
     pub fn mark_mui_as_active(
         &self,
         mui: u32,
         guard: &Guard,
-    ) -> Result<(), PrefixStoreError> {
-        let current = self.withdrawn_muis_bmin.load(Ordering::Acquire, guard);
+    ) -> Result<bool, PrefixStoreError> {
+        Ok(self.update_withdrawn_muis(mui, false, None, guard))
+    }
 
-        let mut new = unsafe { current.as_ref() }.unwrap().clone();
-        new.remove(mui);
+    // Add the mui to, or remove it from, the global withdrawn muis index,
+    // and store the ltime of a withdrawal, or forget it. Changes to the
+    // global status of a mui are serialized by the lock on the ltimes, so
+    // that the ltimes always match the bitmap. Readers of the bitmap don't
+    // take the lock. Returns whether the global status of the mui changed.
+    fn update_withdrawn_muis(
+        &self,
+        mui: u32,
+        withdrawn: bool,
+        ltime: Option<u64>,
+        guard: &Guard,
+    ) -> bool {
+        let mut ltimes = self.withdrawn_muis_ltime.lock().unwrap();
+        let mut current =
+            self.withdrawn_muis_bmin.load(Ordering::Acquire, guard);
 
-        #[allow(clippy::assigning_clones)]
         loop {
+            let mut new = unsafe { current.as_ref() }.unwrap().clone();
+            let changed = if withdrawn {
+                new.insert(mui)
+            } else {
+                new.remove(mui)
+            };
+
             match self.withdrawn_muis_bmin.compare_exchange(
                 current,
                 Owned::new(new),
@@ -796,15 +1502,35 @@ impl<
                 Ordering::Acquire,
                 guard,
             ) {
-                Ok(_) => return Ok(()),
+                Ok(_) => {
+                    match ltime {
+                        Some(ltime) => ltimes.insert(mui, ltime),
+                        None => ltimes.remove(&mui),
+                    };
+                    return changed;
+                }
                 Err(updated) => {
-                    new =
-                        unsafe { updated.current.as_ref() }.unwrap().clone();
+                    current = updated.current;
                 }
             }
         }
     }
 
+    // The current global withdrawn muis index. The bitmap is never null: it
+    // is created with the store, and only ever replaced by a new one. The
+    // replaced bitmaps are destroyed when no guard is pinned anymore, so
+    // the returned reference stays valid for as long as `guard` lives.
+    pub fn withdrawn_muis_snapshot<'g>(
+        &self,
+        guard: &'g Guard,
+    ) -> &'g RoaringBitmap {
+        unsafe {
+            self.withdrawn_muis_bmin
+                .load(Ordering::Acquire, guard)
+                .deref()
+        }
+    }
+
     // Whether this mui is globally withdrawn. Note that this overrules (by
     // default) any (prefix, mui) combination in iterators and match functions.
     pub fn mui_is_withdrawn(&self, mui: u32, guard: &Guard) -> bool {
@@ -830,6 +1556,212 @@ impl<
         .contains(mui)
     }
 
+    // Write a frame for every prefix of this address family that has
+    // records to `w`, in the format of `dump_binary`. All the records are
+    // written with their local status, the global status of the muis is
+    // not applied. A frame consists of:
+    //
+    // - a tag with the number of bits of the address family (32 or 128),
+    // - the prefix length (u8) and the address (4 or 16 bytes),
+    // - the number of records (u32), followed by, for every record, its
+    //   mui (u32), ltime (u64), status (u8) and the length (u32) and bytes
+    //   of its meta.
+    //
+    // All integers are big-endian.
+    pub fn write_prefixes_binary<W: Write>(
+        &'a self,
+        w: &mut W,
+    ) -> io::Result<()>
+    where
+        M: AsRef<[u8]>,
+    {
+        for (prefix, records) in self.prefixes_iter() {
+            if records.is_empty() {
+                continue;
+            }
+            w.write_all(&[AF::BITS, prefix.len()])?;
+            match prefix.addr() {
+                IpAddr::V4(addr) => w.write_all(&addr.octets())?,
+                IpAddr::V6(addr) => w.write_all(&addr.octets())?,
+            }
+            w.write_all(&(records.len() as u32).to_be_bytes())?;
+            for rec in records {
+                let meta = rec.meta.as_ref();
+                w.write_all(&rec.multi_uniq_id.to_be_bytes())?;
+                w.write_all(&rec.ltime.to_be_bytes())?;
+                w.write_all(&[rec.status.to_u8()])?;
+                w.write_all(&(meta.len() as u32).to_be_bytes())?;
+                w.write_all(meta)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Read a prefix frame written by `write_prefixes_binary` from `r`, with
+    // the tag already read.
+    pub fn read_prefix_binary<R: Read>(
+        &self,
+        r: &mut R,
+    ) -> io::Result<(Prefix, Vec<PublicRecord<M>>)>
+    where
+        M: From<Vec<u8>>,
+    {
+        fn invalid(msg: &str) -> io::Error {
+            io::Error::new(io::ErrorKind::InvalidData, msg)
+        }
+
+        let mut len = [0; 1];
+        r.read_exact(&mut len)?;
+        let addr = if AF::BITS == 32 {
+            let mut octets = [0; 4];
+            r.read_exact(&mut octets)?;
+            IpAddr::V4(Ipv4Addr::from(octets))
+        } else {
+            let mut octets = [0; 16];
+            r.read_exact(&mut octets)?;
+            IpAddr::V6(Ipv6Addr::from(octets))
+        };
+        let prefix =
+            Prefix::new(addr, len[0]).map_err(|_| invalid("invalid prefix"))?;
+
+        let mut buf = [0; 8];
+        r.read_exact(&mut buf[..4])?;
+        let count = u32::from_be_bytes(buf[..4].try_into().unwrap());
+
+        let mut records = vec![];
+        for _ in 0..count {
+            r.read_exact(&mut buf[..4])?;
+            let mui = u32::from_be_bytes(buf[..4].try_into().unwrap());
+            r.read_exact(&mut buf)?;
+            let ltime = u64::from_be_bytes(buf);
+            r.read_exact(&mut buf[..1])?;
+            let status = RouteStatus::from_u8(buf[0])
+                .ok_or_else(|| invalid("invalid route status"))?;
+            r.read_exact(&mut buf[..4])?;
+            let meta_len = u32::from_be_bytes(buf[..4].try_into().unwrap());
+
+            // Don't trust the length for the allocation, a truncated or
+            // corrupt dump would make us allocate up to 4GiB.
+            let mut meta = vec![];
+            r.by_ref().take(meta_len as u64).read_to_end(&mut meta)?;
+            if meta.len() != meta_len as usize {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+
+            records.push(PublicRecord::new(
+                mui,
+                ltime,
+                status,
+                M::from(meta),
+            ));
+        }
+
+        Ok((prefix, records))
+    }
+
+    // Serialize the bitmap of globally withdrawn muis into the native
+    // RoaringBitmap format.
+    pub fn withdrawn_muis_to_bytes(&self, guard: &Guard) -> Vec<u8> {
+        let bmin = unsafe {
+            self.withdrawn_muis_bmin
+                .load(Ordering::Acquire, guard)
+                .as_ref()
+        }
+        .unwrap();
+
+        let mut bytes = Vec::with_capacity(bmin.serialized_size());
+        // Writing into a Vec can't fail.
+        bmin.serialize_into(&mut bytes).unwrap();
+        bytes
+    }
+
+    // Deserialize a bitmap of globally withdrawn muis as created by
+    // `withdrawn_muis_to_bytes`. This does not modify the store, use
+    // `set_withdrawn_muis` to install the result.
+    pub fn withdrawn_muis_from_bytes(
+        &self,
+        bytes: &[u8],
+    ) -> Result<RoaringBitmap, PrefixStoreError> {
+        RoaringBitmap::deserialize_from(bytes)
+            .map_err(|_| PrefixStoreError::InvalidWithdrawnMuis)
+    }
+
+    // Replace the bitmap of globally withdrawn muis with `bmin`, e.g. to
+    // restore it from a snapshot taken with `withdrawn_muis_to_bytes`.
+    // The snapshot doesn't carry the ltimes of the withdrawals, only the
+    // ltimes of muis that stay withdrawn are kept.
+    pub fn set_withdrawn_muis(&self, bmin: RoaringBitmap, guard: &Guard) {
+        let mut ltimes = self.withdrawn_muis_ltime.lock().unwrap();
+        ltimes.retain(|mui, _| bmin.contains(*mui));
+        let old = self.withdrawn_muis_bmin.swap(
+            Owned::new(bmin),
+            Ordering::AcqRel,
+            guard,
+        );
+        unsafe { guard.defer_destroy(old) };
+    }
+
+    // Calculate and store the best and backup path for all the prefixes
+    // that have a record for this mui. The prefixes are found through the
+    // mui bitmap indexes on the nodes, so only the parts of the tree that
+    // contain this mui are visited. Returns the number of prefixes that
+    // were updated.
+    pub fn recalculate_best_paths_for_mui(
+        &'a self,
+        mui: u32,
+        tbi: &M::TBI,
+        guard: &'a Guard,
+    ) -> Result<usize, PrefixStoreError> {
+        let mut count = 0;
+
+        for (pfx_id, _) in self.prefixes_iter_for_mui(mui, true, guard) {
+            if let Some(stored_prefix) =
+                self.non_recursive_retrieve_prefix(pfx_id).0
+            {
+                stored_prefix.calculate_and_store_best_backup(tbi, guard)?;
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
+    // Calculate and store the best and backup path for all the prefixes
+    // whose path selections are outdated. This visits all the prefixes in
+    // the store. Returns the number of prefixes that were updated.
+    pub fn recalculate_outdated_best_paths(
+        &'a self,
+        tbi: &M::TBI,
+        guard: &'a Guard,
+    ) -> Result<usize, PrefixStoreError> {
+        let mut count = 0;
+
+        for stored_prefix in self
+            .stored_prefixes_iter()
+            .filter(|s_pfx| s_pfx.is_ps_outdated(guard))
+        {
+            stored_prefix.calculate_and_store_best_backup(tbi, guard)?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    // Mark the path selections for `prefix_id` as outdated, if the prefix
+    // is in the store.
+    pub fn set_ps_outdated_for_prefix(
+        &'a self,
+        prefix_id: PrefixId<AF>,
+        guard: &'a Guard,
+    ) -> Result<(), PrefixStoreError> {
+        if let Some(stored_prefix) =
+            self.non_recursive_retrieve_prefix(prefix_id).0
+        {
+            stored_prefix.set_ps_outdated(guard)?;
+        }
+        Ok(())
+    }
+
     // This function is used by the upsert_prefix function above.
     //
     // We're using a Chained Hash Table and this function returns one of:
@@ -1017,12 +1949,505 @@ impl<
         self.counters.get_prefixes_count().iter().sum()
     }
 
+    // The number of prefixes in the tree, counted from the pfxbitarrs of all
+    // the nodes, plus the default route, that only lives in the prefix
+    // store. Unlike the counters this doesn't depend on increments that may
+    // not have happened yet, but it visits every node in the tree.
+    pub fn get_exact_prefixes_count(&'a self) -> usize {
+        self.prefix_ids_iter().count()
+    }
+
     pub fn get_prefixes_count_for_len(&self, len: u8) -> usize {
         self.counters.get_prefixes_count()[len as usize]
     }
 
+    // The prefix lengths that have at least one prefix in the store, in
+    // ascending order, read from the counters.
+    pub fn get_populated_lengths(&self) -> Vec<u8> {
+        self.counters.get_prefixes_count()[..=AF::BITS as usize]
+            .iter()
+            .enumerate()
+            .filter_map(|(len, count)| (*count > 0).then_some(len as u8))
+            .collect()
+    }
+
+    // Estimates the size of the result of a query for `search_pfx` with
+    // `options` from the counters, without traversing the tree. Every
+    // populated length shorter than the search prefix holds at most one
+    // less-specific. For the more-specifics the prefixes of every longer
+    // length are assumed to be spread evenly over the address space, so
+    // the search prefix gets its share of them, rounded up, but never more
+    // than the number of prefixes of that length that fit into it.
+    pub fn estimate_query_cost(
+        &self,
+        search_pfx: PrefixId<AF>,
+        options: &MatchOptions,
+    ) -> QueryCostEstimate {
+        let len = search_pfx.get_len() as usize;
+        let counts = self.counters.get_prefixes_count();
+
+        let less_specifics = if options.include_less_specifics {
+            let populated =
+                counts[..len].iter().filter(|count| **count > 0).count();
+            if options.stop_at_first_less_specific {
+                populated.min(1)
+            } else {
+                populated
+            }
+        } else {
+            0
+        };
+
+        let more_specifics = if options.include_more_specifics {
+            counts[len + 1..=AF::BITS as usize]
+                .iter()
+                .zip(1_u32..)
+                .map(|(count, extra_bits)| {
+                    let share =
+                        (*count as f64 / 2_f64.powi(len as i32)).ceil();
+                    let fit =
+                        1_usize.checked_shl(extra_bits).unwrap_or(usize::MAX);
+                    (share as usize).min(fit)
+                })
+                .sum()
+        } else {
+            0
+        };
+
+        QueryCostEstimate {
+            less_specifics,
+            more_specifics,
+        }
+    }
+
+    // The number of records for all prefixes, i.e. the sum of the number of
+    // muis for each prefix. This traverses all the stored prefixes.
+    pub fn get_records_count(&'a self) -> usize {
+        self.stored_prefixes_iter()
+            .map(|p| p.record_map.len())
+            .sum()
+    }
+
+    // All the prefixes with more than `threshold` records, regardless of
+    // their status, together with their number of records. This traverses
+    // all the stored prefixes, a record map that is updated concurrently
+    // may be reported with a slightly outdated count.
+    pub fn prefixes_with_record_count_over(
+        &'a self,
+        threshold: usize,
+    ) -> impl Iterator<Item = (Prefix, usize)> + 'a {
+        self.stored_prefixes_iter().filter_map(move |p| {
+            let count = p.record_map.len();
+            if count > threshold {
+                Some((p.get_prefix_id().into_pub(), count))
+            } else {
+                None
+            }
+        })
+    }
+
+    // Walk all the node and prefix buckets and collect the depths of the
+    // chains of buckets the stored elements live in, per length.
+    pub fn chain_depths(&self) -> ChainDepths {
+        let mut depths = ChainDepths::default();
+
+        let mut len = 0;
+        for stride in self.get_stride_sizes() {
+            let id = StrideNodeId::new_with_cleaned_id(AF::zero(), len);
+            let mut chain_depth = ChainDepth::new(len);
+            match self.get_stride_for_id(id) {
+                3 => Self::node_chain_depth(
+                    len,
+                    self.buckets.get_store3(id),
+                    0,
+                    &mut chain_depth,
+                ),
+                4 => Self::node_chain_depth(
+                    len,
+                    self.buckets.get_store4(id),
+                    0,
+                    &mut chain_depth,
+                ),
+                _ => Self::node_chain_depth(
+                    len,
+                    self.buckets.get_store5(id),
+                    0,
+                    &mut chain_depth,
+                ),
+            }
+            if chain_depth.count > 0 {
+                depths.nodes.push(chain_depth);
+            }
+            len += stride;
+            if len >= AF::BITS {
+                break;
+            }
+        }
+
+        for len in 0..=AF::BITS {
+            let mut chain_depth = ChainDepth::new(len);
+            Self::prefix_chain_depth(
+                len,
+                self.prefixes.get_root_prefix_set(len),
+                0,
+                &mut chain_depth,
+            );
+            if chain_depth.count > 0 {
+                depths.prefixes.push(chain_depth);
+            }
+        }
+
+        depths
+    }
+
+    // The statistics of the nodes and prefixes for this address family,
+    // counted by walking all the buckets in the store, like `chain_depths`,
+    // and all the record maps.
+    pub fn stats_report(&'a self) -> AfStatsReport {
+        let depths = self.chain_depths();
+        let mut report = AfStatsReport {
+            records: self.get_records_count(),
+            ..Default::default()
+        };
+
+        for len in 0..=AF::BITS {
+            let nodes = depths
+                .nodes
+                .iter()
+                .find(|d| d.len == len)
+                .map_or(0, |d| d.count);
+            let prefixes = depths
+                .prefixes
+                .iter()
+                .find(|d| d.len == len)
+                .map_or(0, |d| d.count);
+            if nodes == 0 && prefixes == 0 {
+                continue;
+            }
+
+            if nodes > 0 {
+                let id = StrideNodeId::new_with_cleaned_id(AF::zero(), len);
+                report.memory_bytes += nodes
+                    * match self.get_stride_for_id(id) {
+                        3 => std::mem::size_of::<StoredNode<AF, Stride3>>(),
+                        4 => std::mem::size_of::<StoredNode<AF, Stride4>>(),
+                        _ => std::mem::size_of::<StoredNode<AF, Stride5>>(),
+                    };
+            }
+
+            report.nodes += nodes;
+            report.prefixes += prefixes;
+            report.lengths.push(LenStatsReport {
+                len,
+                nodes,
+                prefixes,
+                prefixes_occupied_pct: prefixes as f64
+                    / 2_f64.powi(len as i32)
+                    * 100.0,
+            });
+        }
+
+        report.memory_bytes += report.prefixes
+            * std::mem::size_of::<StoredPrefix<AF, M>>()
+            + report.records
+                * std::mem::size_of::<(u32, MultiMapValue<M>)>();
+
+        report
+    }
+
+    // Walk the nodes of the tree bitmap depth-first, starting at the root
+    // node, and report the structure of each node. Parents are reported
+    // before their children, and the children of a node in the order of
+    // their ids.
+    pub fn node_iter(&'a self) -> impl Iterator<Item = NodeReport> + 'a {
+        let mut stack = vec![(self.get_root_node_id(), 0)];
+
+        std::iter::from_fn(move || {
+            let (id, depth) = stack.pop()?;
+            let (stride, pfx_count, ptr_count, children) =
+                match self.retrieve_node(id)? {
+                    SizedStrideRef::Stride3(n) => (
+                        3,
+                        n.pfxbitarr.to_u64().count_ones(),
+                        n.ptrbitarr.to_u64().count_ones(),
+                        n.ptr_iter(id).collect::<Vec<_>>(),
+                    ),
+                    SizedStrideRef::Stride4(n) => (
+                        4,
+                        n.pfxbitarr.to_u64().count_ones(),
+                        n.ptrbitarr.to_u64().count_ones(),
+                        n.ptr_iter(id).collect::<Vec<_>>(),
+                    ),
+                    SizedStrideRef::Stride5(n) => (
+                        5,
+                        n.pfxbitarr.to_u64().count_ones(),
+                        n.ptrbitarr.to_u64().count_ones(),
+                        n.ptr_iter(id).collect::<Vec<_>>(),
+                    ),
+                };
+
+            // Nodes are never removed, so the children that can be
+            // retrieved now will still be there when they're popped.
+            let children = children
+                .into_iter()
+                .filter(|c| self.retrieve_node(*c).is_some())
+                .collect::<Vec<_>>();
+            let child_count = children.len();
+            stack.extend(children.into_iter().rev().map(|c| (c, depth + 1)));
+
+            let (net, len) = id.get_id();
+            Some(NodeReport {
+                node_id: Prefix::new(net.into_ipaddr(), len).unwrap_or_else(
+                    |p| panic!("can't convert node id {:?} into prefix.", p),
+                ),
+                len,
+                depth,
+                stride,
+                pfx_count,
+                ptr_count,
+                child_count,
+            })
+        })
+    }
+
+    // The number of nodes whose mui index is poisoned, clearing the poison
+    // if `clear` is set. This walks all the node buckets.
+    pub fn poisoned_rbm_indexes(&self, clear: bool) -> usize {
+        let mut count = 0;
+
+        let mut len = 0;
+        for stride in self.get_stride_sizes() {
+            let id = StrideNodeId::new_with_cleaned_id(AF::zero(), len);
+            count += match self.get_stride_for_id(id) {
+                3 => self.buckets.get_store3(id).poisoned_rbm_indexes(clear),
+                4 => self.buckets.get_store4(id).poisoned_rbm_indexes(clear),
+                _ => self.buckets.get_store5(id).poisoned_rbm_indexes(clear),
+            };
+            len += stride;
+            if len >= AF::BITS {
+                break;
+            }
+        }
+
+        count
+    }
+
+    // Walk all the node buckets, like `chain_depths`, and count the slots
+    // and the occupied slots of the allocated buckets, per length and level.
+    pub fn fill_factors(&self) -> Vec<LevelFill> {
+        let mut fills = vec![];
+
+        let mut len = 0;
+        for stride in self.get_stride_sizes() {
+            let id = StrideNodeId::new_with_cleaned_id(AF::zero(), len);
+            let mut len_fills = vec![];
+            match self.get_stride_for_id(id) {
+                3 => Self::node_fill(
+                    len,
+                    self.buckets.get_store3(id),
+                    0,
+                    &mut len_fills,
+                ),
+                4 => Self::node_fill(
+                    len,
+                    self.buckets.get_store4(id),
+                    0,
+                    &mut len_fills,
+                ),
+                _ => Self::node_fill(
+                    len,
+                    self.buckets.get_store5(id),
+                    0,
+                    &mut len_fills,
+                ),
+            }
+            fills.extend(len_fills);
+            len += stride;
+            if len >= AF::BITS {
+                break;
+            }
+        }
+
+        fills
+    }
+
+    fn node_fill<S: Stride>(
+        len: u8,
+        nodes: &NodeSet<AF, S>,
+        level: u8,
+        fills: &mut Vec<LevelFill>,
+    ) {
+        if nodes.0.is_null() {
+            return;
+        }
+        // The parent level is always in `fills` already.
+        if fills.len() == level as usize {
+            fills.push(LevelFill {
+                len,
+                level,
+                ..Default::default()
+            });
+        }
+        fills[level as usize].buckets += 1;
+        fills[level as usize].slots += nodes.0.size();
+
+        for index in 0..nodes.0.size() {
+            if let Some(stored_node) = nodes.0.get(index) {
+                fills[level as usize].occupied += 1;
+                Self::node_fill(len, &stored_node.node_set, level + 1, fills);
+            }
+        }
+    }
+
+    // The number of nodes for every length that nodes are stored for, i.e.
+    // the start lengths of the strides, in ascending order. This is read
+    // from the `chain_depths`, that walk all the buckets of nodes, so unlike
+    // `get_nodes_count` it is exact, at the price of a traversal.
+    pub fn nodes_count_per_len(&self) -> Vec<(u8, usize)> {
+        let depths = self.chain_depths().nodes;
+
+        let mut counts = vec![];
+        let mut len = 0;
+        for stride in self.get_stride_sizes() {
+            let count =
+                depths.iter().find(|d| d.len == len).map_or(0, |d| d.count);
+            counts.push((len, count));
+            len += stride;
+            if len >= AF::BITS {
+                break;
+            }
+        }
+
+        counts
+    }
+
+    // The number of slots in a bucket, given the end bits of its level and
+    // of the level above it.
+    fn bucket_size(this_level: u8, last_level: u8) -> usize {
+        1 << this_level.saturating_sub(last_level)
+    }
+
+    fn node_chain_depth<S: Stride>(
+        len: u8,
+        nodes: &NodeSet<AF, S>,
+        level: u8,
+        chain_depth: &mut ChainDepth,
+    ) {
+        if nodes.0.is_null() {
+            return;
+        }
+        let last_level = if level > 0 {
+            <NB as NodeBuckets<AF>>::len_to_store_bits(len, level - 1)
+        } else {
+            0
+        };
+        let this_level = <NB as NodeBuckets<AF>>::len_to_store_bits(len, level);
+
+        for index in 0..Self::bucket_size(this_level, last_level) {
+            if let Some(stored_node) = nodes.0.get(index) {
+                chain_depth.inc(level);
+                Self::node_chain_depth(
+                    len,
+                    &stored_node.node_set,
+                    level + 1,
+                    chain_depth,
+                );
+            }
+        }
+    }
+
+    fn prefix_chain_depth(
+        len: u8,
+        prefixes: &PrefixSet<AF, M>,
+        level: u8,
+        chain_depth: &mut ChainDepth,
+    ) {
+        if prefixes.is_empty() {
+            return;
+        }
+        let last_level = if level > 0 {
+            <PB as PrefixBuckets<AF, M>>::get_bits_for_len(len, level - 1)
+        } else {
+            0
+        };
+        let this_level = <PB as PrefixBuckets<AF, M>>::get_bits_for_len(len, level);
+
+        for index in 0..Self::bucket_size(this_level, last_level) {
+            if let Some(stored_prefix) = prefixes.get_by_index(index) {
+                chain_depth.inc(level);
+                if let Some(next_bucket) = stored_prefix.get_next_bucket() {
+                    Self::prefix_chain_depth(
+                        len,
+                        next_bucket,
+                        level + 1,
+                        chain_depth,
+                    );
+                }
+            }
+        }
+    }
+
     // Stride related methods
 
+    // Return the internals of the node that hosts the specified prefix, if
+    // that node exists.
+    #[cfg(feature = "debug-internals")]
+    pub fn inspect_node(&'a self, prefix: PrefixId<AF>) -> Option<NodeDebug> {
+        let (id, _) = self.get_node_id_for_prefix(&prefix);
+
+        match self.get_stride_for_id(id) {
+            3 => self
+                .find_stored_node(id, self.buckets.get_store3(id))
+                .map(|n| Self::node_debug(n, 3)),
+            4 => self
+                .find_stored_node(id, self.buckets.get_store4(id))
+                .map(|n| Self::node_debug(n, 4)),
+            _ => self
+                .find_stored_node(id, self.buckets.get_store5(id))
+                .map(|n| Self::node_debug(n, 5)),
+        }
+    }
+
+    // Follow the chain of NodeSets until the StoredNode for `id` is found.
+    #[cfg(feature = "debug-internals")]
+    fn find_stored_node<S: Stride>(
+        &self,
+        id: StrideNodeId<AF>,
+        mut nodes: &'a NodeSet<AF, S>,
+    ) -> Option<&'a StoredNode<AF, S>> {
+        let mut level = 0;
+        loop {
+            let stored_node = nodes.0.get(Self::hash_node_id(id, level))?;
+            if stored_node.node_id == id {
+                return Some(stored_node);
+            }
+            level += 1;
+            if <NB as NodeBuckets<AF>>::len_to_store_bits(id.get_len(), level)
+                == 0
+            {
+                return None;
+            }
+            nodes = &stored_node.node_set;
+        }
+    }
+
+    #[cfg(feature = "debug-internals")]
+    fn node_debug<S: Stride>(
+        stored_node: &StoredNode<AF, S>,
+        stride: u8,
+    ) -> NodeDebug {
+        let (net, len) = stored_node.node_id.get_id();
+        NodeDebug {
+            node_id: Prefix::new(net.into_ipaddr(), len).unwrap_or_else(|p| {
+                panic!("can't convert node id {:?} into prefix.", p)
+            }),
+            stride,
+            pfxbitarr: stored_node.node.pfxbitarr.to_u64(),
+            ptrbitarr: stored_node.node.ptrbitarr.to_u64(),
+            muis: stored_node.node_set.1.read().unwrap().clone(),
+        }
+    }
+
     pub(crate) fn get_stride_for_id(&self, id: StrideNodeId<AF>) -> u8 {
         self.buckets.get_stride_for_id(id)
     }
@@ -1139,9 +2564,7 @@ impl<
             ((<AF>::BITS - (this_level - last_level)) % <AF>::BITS) as usize
         );
         // HASHING FUNCTION
-        ((id.get_id().0 << last_level)
-            >> ((<AF>::BITS - (this_level - last_level)) % <AF>::BITS))
-            .dangerously_truncate_to_u32() as usize
+        H::hash_node_id(id, last_level, this_level)
     }
 
     pub(crate) fn hash_prefix_id(id: PrefixId<AF>, level: u8) -> usize {
@@ -13,8 +13,9 @@ struct DefaultStore;
 impl<
         M: Meta,
         NB: NodeBuckets<IPv4>,
-        PB: PrefixBuckets<IPv4, M>
-    > fmt::Display for CustomAllocStorage<IPv4, M, NB, PB>
+        PB: PrefixBuckets<IPv4, M>,
+        H: NodeHasher,
+    > fmt::Display for CustomAllocStorage<IPv4, M, NB, PB, H>
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -28,8 +29,9 @@ impl<
 impl<
         M: Meta,
         NB: NodeBuckets<IPv6>,
-        PB: PrefixBuckets<IPv6, M>
-    > fmt::Display for CustomAllocStorage<IPv6, M, NB, PB>
+        PB: PrefixBuckets<IPv6, M>,
+        H: NodeHasher,
+    > fmt::Display for CustomAllocStorage<IPv6, M, NB, PB, H>
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
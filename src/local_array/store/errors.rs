@@ -1,5 +1,7 @@
 use std::fmt;
 
+use inetnum::addr::Prefix;
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum PrefixStoreError {
     NodeCreationMaxRetryError,
@@ -7,14 +9,37 @@ pub enum PrefixStoreError {
     StoreNotReadyError,
     PathSelectionOutdated,
     PrefixNotFound,
-    BestPathNotFound
+    BestPathNotFound,
+    // A store operation failed in a way that shouldn't happen, e.g. a node
+    // that was just created or looked up can't be retrieved. Carries the
+    // name of the failing operation and, if known, the prefix it was
+    // operating on.
+    StoreOperationFailed {
+        operation: &'static str,
+        prefix: Option<Prefix>,
+    },
+    // The bytes handed in to restore a withdrawn muis bitmap do not hold a
+    // valid serialized RoaringBitmap.
+    InvalidWithdrawnMuis,
+    // The length of the prefix to insert is outside of the accepted range
+    // of lengths configured for its address family.
+    PrefixLengthNotAccepted,
+    // The address family of the prefix, or of the method called, is not
+    // enabled for this store.
+    AddressFamilyDisabled,
+    // The StoreConfig handed to the store at construction is incoherent.
+    // Carries a description of what is wrong with it.
+    InvalidStoreConfig(String),
+    // A record for the (prefix, mui) combination to insert already exists,
+    // and the insert was not allowed to replace it.
+    RecordAlreadyExists,
 }
 
 impl std::error::Error for PrefixStoreError {}
 
 impl fmt::Display for PrefixStoreError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
+        match self {
             PrefixStoreError::NodeCreationMaxRetryError => write!(
                 f,
                 "Error: Maximum number of retries for node creation reached."
@@ -34,6 +59,44 @@ impl fmt::Display for PrefixStoreError {
             PrefixStoreError::BestPathNotFound => {
                 write!(f, "Error: The Prefix does not have a stored best path.")
             }
+            PrefixStoreError::StoreOperationFailed {
+                operation,
+                prefix: Some(prefix),
+            } => {
+                write!(
+                    f,
+                    "Error: Store operation '{}' failed for prefix {}.",
+                    operation, prefix
+                )
+            }
+            PrefixStoreError::StoreOperationFailed {
+                operation,
+                prefix: None,
+            } => {
+                write!(f, "Error: Store operation '{}' failed.", operation)
+            }
+            PrefixStoreError::InvalidWithdrawnMuis => {
+                write!(f, "Error: The withdrawn muis bitmap cannot be decoded.")
+            }
+            PrefixStoreError::PrefixLengthNotAccepted => {
+                write!(
+                    f,
+                    "Error: The length of the prefix is outside the accepted \
+                    range."
+                )
+            }
+            PrefixStoreError::AddressFamilyDisabled => {
+                write!(f, "Error: The address family is disabled for this store.")
+            }
+            PrefixStoreError::InvalidStoreConfig(reason) => {
+                write!(f, "Error: Invalid store configuration: {}.", reason)
+            }
+            PrefixStoreError::RecordAlreadyExists => {
+                write!(
+                    f,
+                    "Error: A record for this prefix and mui already exists."
+                )
+            }
         }
     }
 }
@@ -0,0 +1,145 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+
+use crate::prelude::Meta;
+
+// The number of independently locked shards of the interner. A shard is
+// picked by the hash of the key of a meta, so that concurrent inserts of
+// different metas mostly don't contend for the same lock.
+const SHARDS: usize = 16;
+
+// The number of misses after which the shard of the miss is swept for the
+// stale references of values that were dropped.
+const SWEEP_INTERVAL: usize = 1024;
+
+//------------ InternerStats -------------------------------------------------
+
+/// The statistics of the interner of the meta-data of the records in a
+/// store, see `intern_meta` in the
+/// [StoreConfig](crate::prelude::multi::StoreConfig).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct InternerStats {
+    /// The number of inserted records whose meta-data was replaced with an
+    /// already interned copy.
+    pub hits: usize,
+    /// The number of inserted records whose meta-data wasn't interned yet,
+    /// and was interned by the insert.
+    pub misses: usize,
+    /// The number of distinct meta-data values that are interned, and that
+    /// are still referenced by at least one record.
+    pub interned: usize,
+}
+
+//------------ MetaInterner --------------------------------------------------
+
+// Keeps one copy of every distinct meta-data value that is inserted in the
+// store, by the bytes returned by `Meta::intern_key`. The interner only
+// holds weak references, so a value is dropped when the last record that
+// references it is removed or replaced. The stale references are cleaned
+// up when their shard is searched for a value with the same hash, when the
+// shard is swept every SWEEP_INTERVAL misses, and by `stats`.
+#[derive(Debug)]
+pub struct MetaInterner<M: Meta> {
+    shards: Vec<Mutex<HashMap<u64, Vec<Weak<M>>>>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl<M: Meta> MetaInterner<M> {
+    pub fn new() -> Self {
+        Self {
+            shards: (0..SHARDS).map(|_| Mutex::new(HashMap::new())).collect(),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    // Return the interned copy of `meta`, interning `meta` if there isn't
+    // one yet. Returns `meta` itself, in an Err, if it doesn't have an
+    // intern key.
+    pub(crate) fn intern(&self, meta: M) -> Result<Arc<M>, M> {
+        let Some(key) = meta.intern_key() else {
+            return Err(meta);
+        };
+
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let mut shard = self.shards[hash as usize % SHARDS].lock().unwrap();
+        let entries = shard.entry(hash).or_default();
+
+        let mut interned = None;
+        entries.retain(|weak| match weak.upgrade() {
+            Some(m) => {
+                if interned.is_none() && m.intern_key() == Some(key) {
+                    interned = Some(m);
+                }
+                true
+            }
+            None => false,
+        });
+
+        if let Some(m) = interned {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(m);
+        }
+
+        let m = Arc::new(meta);
+        entries.push(Arc::downgrade(&m));
+        if (self.misses.fetch_add(1, Ordering::Relaxed) + 1) % SWEEP_INTERVAL
+            == 0
+        {
+            Self::sweep(&mut shard);
+        }
+        Ok(m)
+    }
+
+    // Remove the stale references from a shard, and the hashes that have
+    // no references left.
+    fn sweep(shard: &mut HashMap<u64, Vec<Weak<M>>>) {
+        shard.retain(|_, entries| {
+            entries.retain(|weak| weak.strong_count() > 0);
+            !entries.is_empty()
+        });
+    }
+
+    pub fn stats(&self) -> InternerStats {
+        let interned = self
+            .shards
+            .iter()
+            .map(|shard| {
+                let mut shard = shard.lock().unwrap();
+                Self::sweep(&mut shard);
+                shard.values().map(|entries| entries.len()).sum::<usize>()
+            })
+            .sum();
+
+        InternerStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            interned,
+        }
+    }
+}
+
+#[cfg(test)]
+impl<M: Meta> MetaInterner<M> {
+    // The number of hashes in all the shards, including those that only
+    // have stale references.
+    pub(crate) fn hashes_count(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.lock().unwrap().len())
+            .sum()
+    }
+}
+
+impl<M: Meta> Default for MetaInterner<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -6,10 +6,13 @@
 // storage (and some over the TreeBitMap nodes, the parent of the store),
 // as such all the iterators here are composed of iterators over the
 // individual nodes. The Node Iterators live in the node.rs file.
+use std::collections::VecDeque;
 use std::sync::atomic::Ordering;
 
-use super::atomic_types::{NodeBuckets, PrefixBuckets, PrefixSet};
-use super::custom_alloc::CustomAllocStorage;
+use super::atomic_types::{
+    NodeBuckets, NodeSet, PrefixBuckets, PrefixSet, StoredNode, StoredPrefix,
+};
+use super::custom_alloc::{CustomAllocStorage, NodeHasher};
 use crate::local_array::store::atomic_types::RouteStatus;
 use crate::prefix_record::PublicRecord;
 use crate::{
@@ -20,6 +23,7 @@ use crate::{
             NodeMoreSpecificChildIter, NodeMoreSpecificsPrefixIter, PrefixId,
             SizedStrideRef, Stride3, Stride4, Stride5, StrideNodeId,
         },
+        tree::Stride,
     },
     prefix_record::Meta,
 };
@@ -33,6 +37,8 @@ use roaring::RoaringBitmap;
 
 // Iterator over all the prefixes in the storage. This Iterator does *not* use
 // the tree, it iterates over all the length arrays in the CustomAllocStorage.
+// It yields references to the StoredPrefixes themselves, the methods that
+// start this iterator turn them into whatever the caller needs.
 
 pub(crate) struct PrefixIter<
     'a,
@@ -42,6 +48,8 @@ pub(crate) struct PrefixIter<
 > {
     prefixes: &'a PB,
     cur_len: u8,
+    // The last length to visit, AF::BITS for a full iteration.
+    end_len: u8,
     cur_bucket: &'a PrefixSet<AF, M>,
     cur_level: u8,
     // level depth of IPv4 as defined in rotonda-macros/maps.rs Option(parent,
@@ -54,7 +62,7 @@ pub(crate) struct PrefixIter<
 impl<'a, AF: AddressFamily + 'a, M: Meta + 'a, PB: PrefixBuckets<AF, M>>
     Iterator for PrefixIter<'a, AF, M, PB>
 {
-    type Item = (inetnum::addr::Prefix, Vec<PublicRecord<M>>);
+    type Item = &'a StoredPrefix<AF, M>;
 
     fn next(&mut self) -> Option<Self::Item> {
         trace!(
@@ -65,7 +73,7 @@ impl<'a, AF: AddressFamily + 'a, M: Meta + 'a, PB: PrefixBuckets<AF, M>>
         );
 
         loop {
-            if self.cur_len > AF::BITS {
+            if self.cur_len > self.end_len {
                 // This is the end, my friend
                 trace!("reached max length {}, returning None", self.cur_len);
                 return None;
@@ -78,6 +86,10 @@ impl<'a, AF: AddressFamily + 'a, M: Meta + 'a, PB: PrefixBuckets<AF, M>>
                 trace!("next length {}", self.cur_len + 1);
                 self.cur_len += 1;
 
+                if self.cur_len > self.end_len {
+                    return None;
+                }
+
                 // a new length, a new life reset the level depth and cursor,
                 // but also empty all the parents
                 self.cur_level = 0;
@@ -114,7 +126,7 @@ impl<'a, AF: AddressFamily + 'a, M: Meta + 'a, PB: PrefixBuckets<AF, M>>
                     self.cursor = 0;
                     self.parents = [None; 32];
 
-                    if self.cur_len > AF::BITS {
+                    if self.cur_len > self.end_len {
                         // This is the end, my friend
                         return None;
                     }
@@ -190,10 +202,7 @@ impl<'a, AF: AddressFamily + 'a, M: Meta + 'a, PB: PrefixBuckets<AF, M>>
                         //         p.record_map.as_records()
                         //     })
                         // {
-                        return Some((
-                            s_pfx.get_prefix_id().into_pub(),
-                            s_pfx.record_map.as_records(),
-                        ));
+                        return Some(s_pfx);
                         // } else {
                         //     panic!(
                         //         "No prefix here, but there's a child here?"
@@ -213,10 +222,7 @@ impl<'a, AF: AddressFamily + 'a, M: Meta + 'a, PB: PrefixBuckets<AF, M>>
                         //     })
                         // {
                         self.cursor += 1;
-                        return Some((
-                            s_pfx.get_prefix_id().into_pub(),
-                            s_pfx.record_map.as_records(),
-                        ));
+                        return Some(s_pfx);
                         // }
                     }
                 };
@@ -227,6 +233,58 @@ impl<'a, AF: AddressFamily + 'a, M: Meta + 'a, PB: PrefixBuckets<AF, M>>
     }
 }
 
+// ----------- BufferedBackIter ---------------------------------------------
+
+// Makes an iterator that can only go forward double-ended, and maps its
+// items with `f`. Going forward is done lazily on the wrapped iterator. On
+// the first call to `next_back` all the remaining items of the wrapped
+// iterator are collected into a buffer, so that they can be handed out from
+// both ends. This is meant for the iterators over the storage, that have no
+// order of their own to reverse. Their items are references into the
+// storage, so the buffer stays cheap: `f`, which does the expensive work
+// like cloning the records, only runs on the items that are handed out.
+
+pub(crate) struct BufferedBackIter<I: Iterator, F> {
+    inner: I,
+    buffer: Option<VecDeque<I::Item>>,
+    f: F,
+}
+
+impl<I: Iterator, B, F: FnMut(I::Item) -> B> BufferedBackIter<I, F> {
+    pub(crate) fn new(inner: I, f: F) -> Self {
+        Self {
+            inner,
+            buffer: None,
+            f,
+        }
+    }
+}
+
+impl<I: Iterator, B, F: FnMut(I::Item) -> B> Iterator
+    for BufferedBackIter<I, F>
+{
+    type Item = B;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.buffer {
+            Some(buffer) => buffer.pop_front(),
+            None => self.inner.next(),
+        }
+        .map(&mut self.f)
+    }
+}
+
+impl<I: Iterator, B, F: FnMut(I::Item) -> B> DoubleEndedIterator
+    for BufferedBackIter<I, F>
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.buffer
+            .get_or_insert_with(|| self.inner.by_ref().collect())
+            .pop_back()
+            .map(&mut self.f)
+    }
+}
+
 // ----------- Sized Wrappers -----------------------------------------------
 
 // These are enums to abstract over the Stride Size of the iterators. Each
@@ -290,8 +348,9 @@ pub(crate) struct MoreSpecificPrefixIter<
     M: Meta,
     NB: NodeBuckets<AF>,
     PB: PrefixBuckets<AF, M>,
+    H: NodeHasher,
 > {
-    store: &'a CustomAllocStorage<AF, M, NB, PB>,
+    store: &'a CustomAllocStorage<AF, M, NB, PB, H>,
     cur_ptr_iter: SizedNodeMoreSpecificIter<AF>,
     cur_pfx_iter: SizedPrefixIter<AF>,
     start_bit_span: BitSpan,
@@ -304,6 +363,9 @@ pub(crate) struct MoreSpecificPrefixIter<
     global_withdrawn_bmin: &'a RoaringBitmap,
     // Whether we should filter out the withdrawn records in the search result
     include_withdrawn: bool,
+    // If specified, no prefixes with a length greater than this are
+    // returned, and no nodes that can only host such prefixes are visited.
+    max_len: Option<u8>,
 }
 
 impl<
@@ -312,98 +374,22 @@ impl<
         M: Meta,
         NB: NodeBuckets<AF>,
         PB: PrefixBuckets<AF, M>,
-    > Iterator for MoreSpecificPrefixIter<'a, AF, M, NB, PB>
+        H: NodeHasher,
+    > MoreSpecificPrefixIter<'a, AF, M, NB, PB, H>
 {
-    type Item = (PrefixId<AF>, Vec<PublicRecord<M>>);
-
-    fn next(&mut self) -> Option<Self::Item> {
-        trace!("MoreSpecificsPrefixIter");
-
+    // The next more-specific prefix in the tree, without looking up its
+    // records. Prefixes longer than `max_len` are skipped, and child nodes
+    // that don't carry `mui` in their sub-tree are pruned, but the prefixes
+    // in the visited nodes are returned regardless of their records.
+    fn next_prefix_id(&mut self) -> Option<PrefixId<AF>> {
         loop {
             // first drain the current prefix iterator until empty.
-            let next_pfx = self.cur_pfx_iter.next();
-
-            if next_pfx.is_some() {
-                // If we have a mui, we have to deal slightly different with
-                // the records: There can only be one record for a (prefix,
-                // mui) combination, and the record may be filtered out by the
-                // global status of the mui, or its local status. In that case
-                // we don't return here (because that would result in a Prefix
-                // with an empty record vec).
-                if let Some(mui) = self.mui {
-                    if let Some(p) = self
-                        .store
-                        .non_recursive_retrieve_prefix(
-                            next_pfx.unwrap_or_else(|| {
-                                panic!(
-                                "BOOM! More-specific prefix {:?} disappeared \
-                                from the store",
-                                next_pfx
-                            )
-                            }),
-                            // self.guard,
-                        )
-                        .0
-                    {
-                        // We may either have to rewrite the local status with
-                        // the provided global status OR we may have to omit
-                        // all of the records with either global of local
-                        // withdrawn status.
-                        if self.include_withdrawn {
-                            if let Some(rec) = p
-                                .record_map
-                                .get_record_for_mui_with_rewritten_status(
-                                    mui,
-                                    self.global_withdrawn_bmin,
-                                    RouteStatus::Withdrawn,
-                                )
-                            {
-                                return Some((p.prefix, vec![rec]));
-                            }
-                        } else if let Some(rec) =
-                            p.record_map.get_record_for_active_mui(mui)
-                        {
-                            return Some((p.prefix, vec![rec]));
-                        }
-                    };
-                } else {
-                    return self
-                        .store
-                        .non_recursive_retrieve_prefix(
-                            next_pfx.unwrap_or_else(|| {
-                                panic!(
-                                "BOOM! More-specific prefix {:?} disappeared \
-                                from the store",
-                                next_pfx
-                            )
-                            }),
-                            // self.guard,
-                        )
-                        .0
-                        .map(|p| {
-                            // Just like the mui specific records, we may have
-                            // to either rewrite the local status (if the user
-                            // wants the withdrawn records) or omit them.
-                            if self.include_withdrawn {
-                                (
-                                    p.prefix,
-                                    p.record_map
-                                        .as_records_with_rewritten_status(
-                                            self.global_withdrawn_bmin,
-                                            RouteStatus::Withdrawn,
-                                        ),
-                                )
-                            } else {
-                                (
-                                    p.prefix,
-                                    p.record_map
-                                        .as_active_records_not_in_bmin(
-                                            self.global_withdrawn_bmin,
-                                        ),
-                                )
-                            }
-                        });
+            if let Some(pfx) = self.cur_pfx_iter.next() {
+                if self.max_len.is_some_and(|max_len| pfx.get_len() > max_len)
+                {
+                    continue;
                 }
+                return Some(pfx);
             }
 
             // Our current prefix iterator for this node is done, look for
@@ -426,6 +412,17 @@ impl<
             }
 
             if let Some(next_ptr) = next_ptr {
+                // A node hosts the prefixes that are longer than its start
+                // bit, so if that is at or beyond the maximum length, all
+                // the prefixes in the sub-tree formed by this child node
+                // are too long, prune it.
+                if let Some(max_len) = self.max_len {
+                    if next_ptr.get_len() >= max_len {
+                        trace!("prune node {} beyond max len", next_ptr);
+                        continue;
+                    }
+                }
+
                 let node = if self.mui.is_none() {
                     self.store.retrieve_node(next_ptr)
                 } else {
@@ -507,11 +504,103 @@ impl<
                             )
                             .wrap();
                     }
+                    // The child node does not exist, or it doesn't carry
+                    // the requested mui anywhere in its sub-tree. Skip it
+                    // and continue with its siblings.
                     None => {
-                        println!("no node here.");
-                        return None;
+                        trace!("no node here.");
+                        continue;
+                    }
+                };
+            }
+        }
+    }
+}
+
+impl<
+        'a,
+        AF: AddressFamily + 'a,
+        M: Meta,
+        NB: NodeBuckets<AF>,
+        PB: PrefixBuckets<AF, M>,
+        H: NodeHasher,
+    > Iterator for MoreSpecificPrefixIter<'a, AF, M, NB, PB, H>
+{
+    type Item = (PrefixId<AF>, Vec<PublicRecord<M>>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        trace!("MoreSpecificsPrefixIter");
+
+        loop {
+            let next_pfx = self.next_prefix_id()?;
+
+            // If we have a mui, we have to deal slightly different with
+            // the records: There can only be one record for a (prefix,
+            // mui) combination, and the record may be filtered out by the
+            // global status of the mui, or its local status. In that case
+            // we don't return here (because that would result in a Prefix
+            // with an empty record vec).
+            if let Some(mui) = self.mui {
+                if let Some(p) = self
+                    .store
+                    .non_recursive_retrieve_prefix(
+                        next_pfx,
+                        // self.guard,
+                    )
+                    .0
+                {
+                    // We may either have to rewrite the local status with
+                    // the provided global status OR we may have to omit
+                    // all of the records with either global of local
+                    // withdrawn status.
+                    if self.include_withdrawn {
+                        if let Some(rec) = p
+                            .record_map
+                            .get_record_for_mui_with_rewritten_status(
+                                mui,
+                                self.global_withdrawn_bmin,
+                                RouteStatus::Withdrawn,
+                            )
+                        {
+                            return Some((p.prefix, vec![rec]));
+                        }
+                    } else if let Some(rec) =
+                        p.record_map.get_record_for_active_mui(mui)
+                    {
+                        return Some((p.prefix, vec![rec]));
                     }
                 };
+            } else {
+                return self
+                    .store
+                    .non_recursive_retrieve_prefix(
+                        next_pfx,
+                        // self.guard,
+                    )
+                    .0
+                    .map(|p| {
+                        // Just like the mui specific records, we may have
+                        // to either rewrite the local status (if the user
+                        // wants the withdrawn records) or omit them.
+                        if self.include_withdrawn {
+                            (
+                                p.prefix,
+                                p.record_map
+                                    .as_records_with_rewritten_status(
+                                        self.global_withdrawn_bmin,
+                                        RouteStatus::Withdrawn,
+                                    ),
+                            )
+                        } else {
+                            (
+                                p.prefix,
+                                p.record_map
+                                    .as_active_records_not_in_bmin(
+                                        self.global_withdrawn_bmin,
+                                    ),
+                            )
+                        }
+                    });
             }
         }
     }
@@ -550,7 +639,10 @@ impl<'a, AF: AddressFamily + 'a, M: Meta + 'a, PB: PrefixBuckets<AF, M>>
 
     // This iterator moves down all prefix lengths, starting with the length
     // of the (search prefix - 1), looking for shorter prefixes, where the
-    // its bits are the same as the bits of the search prefix.
+    // its bits are the same as the bits of the search prefix. So the
+    // less-specifics are returned from the longest to the shortest, the
+    // `less_specifics_detailed_iter_from` method of the store guarantees
+    // this order.
     fn next(&mut self) -> Option<Self::Item> {
         trace!("search next less-specific for {:?}", self.cur_prefix_id);
 
@@ -700,6 +792,183 @@ impl<'a, AF: AddressFamily + 'a, M: Meta + 'a, PB: PrefixBuckets<AF, M>>
     }
 }
 
+// ----------- PrefixIdIter -------------------------------------------------
+
+// An iterator over the ids of all the prefixes in the tree bitmap. Unlike
+// the PrefixIter it doesn't go over the prefix store at all, it only walks
+// the NodeSets of the nodes, and reads the prefix ids from the pfxbitarr of
+// each node. So it does not see the records, or the withdrawn statuses, of
+// the prefixes. The NodeSets are walked length by length, and for each
+// length the chain of NodeSets is walked depth-first. The results are
+// unordered.
+
+// Walks the chain of NodeSets for one node length depth-first, and returns
+// the stored nodes it finds along the way.
+pub(crate) struct NodeSetIter<'a, AF: AddressFamily, S: Stride> {
+    // The NodeSets we're iterating over, together with the index of the
+    // next slot to look at in each of them. The last one is the deepest.
+    stack: Vec<(&'a NodeSet<AF, S>, usize)>,
+}
+
+impl<'a, AF: AddressFamily, S: Stride> NodeSetIter<'a, AF, S> {
+    fn new(root: &'a NodeSet<AF, S>) -> Self {
+        Self {
+            stack: vec![(root, 0)],
+        }
+    }
+}
+
+impl<'a, AF: AddressFamily, S: Stride> Iterator for NodeSetIter<'a, AF, S> {
+    type Item = &'a StoredNode<AF, S>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node_set, index)) = self.stack.last_mut() {
+            let node_set: &'a NodeSet<AF, S> = node_set;
+            if node_set.0.is_null() || *index >= node_set.0.size() {
+                self.stack.pop();
+                continue;
+            }
+            let cur_index = *index;
+            *index += 1;
+            if let Some(stored_node) = node_set.0.get(cur_index) {
+                if !stored_node.node_set.0.is_null() {
+                    self.stack.push((&stored_node.node_set, 0));
+                }
+                return Some(stored_node);
+            }
+        }
+        None
+    }
+}
+
+pub(crate) enum SizedNodeSetIter<'a, AF: AddressFamily> {
+    Stride3(NodeSetIter<'a, AF, Stride3>),
+    Stride4(NodeSetIter<'a, AF, Stride4>),
+    Stride5(NodeSetIter<'a, AF, Stride5>),
+}
+
+impl<'a, AF: AddressFamily> SizedNodeSetIter<'a, AF> {
+    // Returns an iterator over all the prefixes in the next node.
+    fn next(&mut self) -> Option<SizedPrefixIter<AF>> {
+        match self {
+            SizedNodeSetIter::Stride3(iter) => iter.next().map(|n| {
+                n.node
+                    .more_specific_pfx_iter(
+                        n.node_id,
+                        BitSpan::new(0, 0),
+                        false,
+                    )
+                    .wrap()
+            }),
+            SizedNodeSetIter::Stride4(iter) => iter.next().map(|n| {
+                n.node
+                    .more_specific_pfx_iter(
+                        n.node_id,
+                        BitSpan::new(0, 0),
+                        false,
+                    )
+                    .wrap()
+            }),
+            SizedNodeSetIter::Stride5(iter) => iter.next().map(|n| {
+                n.node
+                    .more_specific_pfx_iter(
+                        n.node_id,
+                        BitSpan::new(0, 0),
+                        false,
+                    )
+                    .wrap()
+            }),
+        }
+    }
+}
+
+pub(crate) struct PrefixIdIter<
+    'a,
+    AF: AddressFamily,
+    M: Meta,
+    NB: NodeBuckets<AF>,
+    PB: PrefixBuckets<AF, M>,
+    H: NodeHasher,
+> {
+    store: &'a CustomAllocStorage<AF, M, NB, PB, H>,
+    // The length of the nodes in the current NodeSet chain.
+    cur_len: u8,
+    // The index into the stride sizes of the stride of the nodes of the
+    // current length.
+    cur_stride: usize,
+    cur_node_iter: Option<SizedNodeSetIter<'a, AF>>,
+    cur_pfx_iter: Option<SizedPrefixIter<AF>>,
+}
+
+impl<
+        'a,
+        AF: AddressFamily + 'a,
+        M: Meta,
+        NB: NodeBuckets<AF>,
+        PB: PrefixBuckets<AF, M>,
+        H: NodeHasher,
+    > PrefixIdIter<'a, AF, M, NB, PB, H>
+{
+    fn node_set_iter_for_len(&self, len: u8) -> SizedNodeSetIter<'a, AF> {
+        let id = StrideNodeId::new_with_cleaned_id(AF::zero(), len);
+        match self.store.get_stride_for_id(id) {
+            3 => SizedNodeSetIter::Stride3(NodeSetIter::new(
+                self.store.buckets.get_store3(id),
+            )),
+            4 => SizedNodeSetIter::Stride4(NodeSetIter::new(
+                self.store.buckets.get_store4(id),
+            )),
+            _ => SizedNodeSetIter::Stride5(NodeSetIter::new(
+                self.store.buckets.get_store5(id),
+            )),
+        }
+    }
+}
+
+impl<
+        'a,
+        AF: AddressFamily + 'a,
+        M: Meta,
+        NB: NodeBuckets<AF>,
+        PB: PrefixBuckets<AF, M>,
+        H: NodeHasher,
+    > Iterator for PrefixIdIter<'a, AF, M, NB, PB, H>
+{
+    type Item = PrefixId<AF>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(pfx_iter) = &mut self.cur_pfx_iter {
+                if let Some(prefix_id) = pfx_iter.next() {
+                    return Some(prefix_id);
+                }
+                self.cur_pfx_iter = None;
+            }
+
+            if let Some(node_iter) = &mut self.cur_node_iter {
+                if let Some(pfx_iter) = node_iter.next() {
+                    self.cur_pfx_iter = Some(pfx_iter);
+                    continue;
+                }
+                // This NodeSet chain is exhausted, move to the next node
+                // length.
+                self.cur_node_iter = None;
+                self.cur_len +=
+                    self.store.get_stride_sizes()[self.cur_stride];
+                self.cur_stride += 1;
+            }
+
+            if self.cur_len >= AF::BITS
+                || self.cur_stride >= self.store.get_stride_sizes().len()
+            {
+                return None;
+            }
+            self.cur_node_iter =
+                Some(self.node_set_iter_for_len(self.cur_len));
+        }
+    }
+}
+
 // ----------- Iterator initialization methods for CustomAllocStorage -------
 
 // These are only the methods that are starting the iterations. All other
@@ -711,7 +980,8 @@ impl<
         M: crate::prefix_record::Meta,
         NB: NodeBuckets<AF>,
         PB: PrefixBuckets<AF, M>,
-    > CustomAllocStorage<AF, M, NB, PB>
+        H: NodeHasher,
+    > CustomAllocStorage<AF, M, NB, PB, H>
 {
     // Iterator over all more-specific prefixes, starting from the given
     // prefix at the given level and cursor.
@@ -722,10 +992,86 @@ impl<
         include_withdrawn: bool,
         guard: &'a Guard,
     ) -> impl Iterator<Item = (PrefixId<AF>, Vec<PublicRecord<M>>)> + '_ {
+        self.more_specific_prefix_iter_with_max_len(
+            start_prefix_id,
+            None,
+            mui,
+            include_withdrawn,
+            guard,
+        )
+    }
+
+    // Iterator over all more-specific prefixes with a length up to and
+    // including `max_len`, if specified, starting from the given prefix.
+    // Nodes that only host longer prefixes are not visited.
+    pub fn more_specific_prefix_iter_with_max_len(
+        &'a self,
+        start_prefix_id: PrefixId<AF>,
+        max_len: Option<u8>,
+        mui: Option<u32>,
+        include_withdrawn: bool,
+        guard: &'a Guard,
+    ) -> impl Iterator<Item = (PrefixId<AF>, Vec<PublicRecord<M>>)> + 'a {
+        self.new_more_specific_prefix_iter(
+            start_prefix_id,
+            max_len,
+            mui,
+            include_withdrawn,
+            guard,
+        )
+        .into_iter()
+        .flatten()
+    }
+
+    // The number of more-specific prefixes of `start_prefix_id`, counted
+    // from the nodes in its sub-tree, without retrieving their records. If
+    // `mui` is specified, the sub-trees that don't carry the mui are pruned
+    // with the mui indexes of the nodes, and only the prefixes that have a
+    // record for the mui are counted. The status of the records is not
+    // taken into account.
+    pub fn more_specifics_count(
+        &'a self,
+        start_prefix_id: PrefixId<AF>,
+        mui: Option<u32>,
+        guard: &'a Guard,
+    ) -> usize {
+        let Some(mut iter) = self.new_more_specific_prefix_iter(
+            start_prefix_id,
+            None,
+            mui,
+            true,
+            guard,
+        ) else {
+            return 0;
+        };
+
+        std::iter::from_fn(|| iter.next_prefix_id())
+            .filter(|pfx| {
+                mui.map_or(true, |mui| {
+                    self.non_recursive_retrieve_prefix(*pfx)
+                        .0
+                        .is_some_and(|p| p.record_map.contains_mui(mui))
+                })
+            })
+            .count()
+    }
+
+    fn new_more_specific_prefix_iter(
+        &'a self,
+        start_prefix_id: PrefixId<AF>,
+        max_len: Option<u8>,
+        mui: Option<u32>,
+        include_withdrawn: bool,
+        guard: &'a Guard,
+    ) -> Option<MoreSpecificPrefixIter<'a, AF, M, NB, PB, H>> {
         trace!("more specifics for {:?}", start_prefix_id);
 
         // A v4 /32 or a v4 /128 doesn't have more specific prefixes 🤓.
-        if start_prefix_id.get_len() >= AF::BITS {
+        // Neither does a prefix that is at least as long as the maximum
+        // length.
+        if start_prefix_id.get_len() >= AF::BITS
+            || max_len.is_some_and(|l| start_prefix_id.get_len() >= l)
+        {
             None
         } else {
             // calculate the node start_prefix_id lives in.
@@ -822,13 +1168,12 @@ impl<
                     global_withdrawn_bmin,
                     include_withdrawn,
                     mui,
+                    max_len,
                 })
             } else {
                 None
             }
         }
-        .into_iter()
-        .flatten()
     }
 
     // Iterator over all less-specific prefixes, starting from the given
@@ -875,18 +1220,314 @@ impl<
         .flatten()
     }
 
+    // Iterator over all the prefixes that contain `addr`, i.e. the host
+    // prefix for `addr` and all its less-specifics, from the most to the
+    // least specific. Every length is looked up directly in the prefix
+    // store. Only the prefixes that have active records, that are not
+    // globally withdrawn, are returned.
+    pub fn covering_prefix_iter(
+        &'a self,
+        addr: AF,
+        guard: &'a Guard,
+    ) -> impl Iterator<Item = (PrefixId<AF>, Vec<PublicRecord<M>>)> + 'a {
+        let global_withdrawn_bmin = self.withdrawn_muis_snapshot(guard);
+
+        (0..=AF::BITS).rev().filter_map(move |len| {
+            let prefix_id = PrefixId::new(addr.truncate_to_len(len), len);
+            self.non_recursive_retrieve_prefix(prefix_id)
+                .0
+                .map(|s_pfx| {
+                    s_pfx
+                        .record_map
+                        .as_active_records_not_in_bmin(global_withdrawn_bmin)
+                })
+                .filter(|recs| !recs.is_empty())
+                .map(|recs| (prefix_id, recs))
+        })
+    }
+
+    // Iterator over the pairs of (less-specific, more-specific) prefixes in
+    // the storage, where the less-specific is the nearest prefix covering
+    // the more-specific, and `conflict` returns true for at least one
+    // combination of their records. Only active records, that are not
+    // globally withdrawn, are considered, and prefixes without them are
+    // skipped, both as more-specific and as less-specific.
+    pub fn overlaps_iter(
+        &'a self,
+        conflict: impl Fn(&PublicRecord<M>, &PublicRecord<M>) -> bool + 'a,
+        guard: &'a Guard,
+    ) -> impl Iterator<Item = (PrefixId<AF>, PrefixId<AF>)> + 'a {
+        let global_withdrawn_bmin = self.withdrawn_muis_snapshot(guard);
+
+        self.stored_prefixes_iter().filter_map(move |s_pfx| {
+            let more_spec = s_pfx.get_prefix_id();
+            let more_spec_recs = s_pfx
+                .record_map
+                .as_active_records_not_in_bmin(global_withdrawn_bmin);
+            if more_spec_recs.is_empty() {
+                return None;
+            }
+
+            // The nearest less-specific with active records, looked up
+            // directly in the prefix store for every shorter length.
+            let (less_spec, less_spec_recs) =
+                (0..more_spec.get_len()).rev().find_map(|len| {
+                    let prefix_id = PrefixId::new(
+                        more_spec.get_net().truncate_to_len(len),
+                        len,
+                    );
+                    self.non_recursive_retrieve_prefix(prefix_id)
+                        .0
+                        .map(|s_pfx| {
+                            s_pfx.record_map.as_active_records_not_in_bmin(
+                                global_withdrawn_bmin,
+                            )
+                        })
+                        .filter(|recs| !recs.is_empty())
+                        .map(|recs| (prefix_id, recs))
+                })?;
+
+            less_spec_recs
+                .iter()
+                .any(|l| more_spec_recs.iter().any(|m| conflict(l, m)))
+                .then_some((less_spec, more_spec))
+        })
+    }
+
     // Iterator over all the prefixes in the storage.
     pub fn prefixes_iter(
         &'a self,
+    ) -> impl DoubleEndedIterator<Item = (Prefix, Vec<PublicRecord<M>>)> + 'a
+    {
+        BufferedBackIter::new(self.stored_prefixes_iter(), |s_pfx| {
+            (
+                s_pfx.get_prefix_id().into_pub(),
+                s_pfx.record_map.as_records(),
+            )
+        })
+    }
+
+    // A snapshot of all the prefixes in the storage, with the statuses of
+    // the records rewritten to Withdrawn for the muis that are globally
+    // withdrawn. All the prefixes and their records are collected when this
+    // is called, against one snapshot of the global withdrawn index, so
+    // nothing that happens after it returns shows up in the iterator.
+    pub fn prefixes_iter_with_guard(
+        &'a self,
+        guard: &'a Guard,
+    ) -> impl DoubleEndedIterator<Item = (Prefix, Vec<PublicRecord<M>>)> + 'a
+    {
+        let bmin = self.withdrawn_muis_snapshot(guard);
+        self.stored_prefixes_iter()
+            .map(|s_pfx| {
+                (
+                    s_pfx.get_prefix_id().into_pub(),
+                    s_pfx.record_map.as_records_with_rewritten_status(
+                        bmin,
+                        RouteStatus::Withdrawn,
+                    ),
+                )
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    // Iterator over all the prefixes in the storage that have records with
+    // `status`, with only those records. If `apply_global_status` is set,
+    // the records for globally withdrawn muis are considered Withdrawn,
+    // otherwise only the local status of the records is taken into account.
+    pub fn prefixes_iter_with_status(
+        &'a self,
+        status: RouteStatus,
+        apply_global_status: bool,
+        guard: &'a Guard,
+    ) -> impl Iterator<Item = (Prefix, Vec<PublicRecord<M>>)> + 'a {
+        let bmin = self.withdrawn_muis_snapshot(guard);
+        self.stored_prefixes_iter().filter_map(move |s_pfx| {
+            let mut recs = if apply_global_status {
+                s_pfx.record_map.as_records_with_rewritten_status(
+                    bmin,
+                    RouteStatus::Withdrawn,
+                )
+            } else {
+                s_pfx.record_map.as_records()
+            };
+            recs.retain(|r| r.status == status);
+            if recs.is_empty() {
+                None
+            } else {
+                Some((s_pfx.get_prefix_id().into_pub(), recs))
+            }
+        })
+    }
+
+    // Iterator over all the prefixes in the storage that have records with
+    // an ltime greater than `since_ltime`, with only those records. The
+    // statuses of the records are rewritten for the globally withdrawn muis,
+    // like `prefixes_iter_with_guard` does.
+    pub fn changed_since(
+        &'a self,
+        since_ltime: u64,
+        guard: &'a Guard,
+    ) -> impl Iterator<Item = (Prefix, Vec<PublicRecord<M>>)> + 'a {
+        let bmin = self.withdrawn_muis_snapshot(guard);
+        self.stored_prefixes_iter().filter_map(move |s_pfx| {
+            let mut recs = s_pfx.record_map.as_records_with_rewritten_status(
+                bmin,
+                RouteStatus::Withdrawn,
+            );
+            recs.retain(|r| r.ltime > since_ltime);
+            if recs.is_empty() {
+                None
+            } else {
+                Some((s_pfx.get_prefix_id().into_pub(), recs))
+            }
+        })
+    }
+
+    // Iterator over all the prefixes with length `len` in the storage, with
+    // the statuses of the records rewritten for the globally withdrawn muis,
+    // like `prefixes_iter_with_guard`. Returns an empty iterator if `len` is
+    // larger than AF::BITS.
+    pub fn prefixes_iter_for_len(
+        &'a self,
+        len: u8,
+        guard: &'a Guard,
     ) -> impl Iterator<Item = (Prefix, Vec<PublicRecord<M>>)> + 'a {
-        PrefixIter {
+        let bmin = self.withdrawn_muis_snapshot(guard);
+        (len <= AF::BITS)
+            .then(|| self.stored_prefixes_iter_for_len(len))
+            .into_iter()
+            .flatten()
+            .map(move |s_pfx| {
+                (
+                    s_pfx.get_prefix_id().into_pub(),
+                    s_pfx.record_map.as_records_with_rewritten_status(
+                        bmin,
+                        RouteStatus::Withdrawn,
+                    ),
+                )
+            })
+    }
+
+    // Move the records of all the prefixes out of the storage, leaving the
+    // record maps of the prefixes empty. This is meant for a store that is
+    // being torn down, since the prefixes stay in the tree, but lose their
+    // records.
+    pub fn take_prefix_records(
+        &'a self,
+    ) -> Vec<(Prefix, Vec<PublicRecord<M>>)> {
+        self.stored_prefixes_iter()
+            .map(|s_pfx| {
+                (
+                    s_pfx.get_prefix_id().into_pub(),
+                    s_pfx.record_map.take_records(),
+                )
+            })
+            .collect()
+    }
+
+    // Iterator over the ids of all the prefixes in the tree bitmap. This
+    // only reads the pfxbitarr of the nodes, the prefix store is only
+    // touched to look up the default route, which lives in no node.
+    pub fn prefix_ids_iter(
+        &'a self,
+    ) -> impl Iterator<Item = PrefixId<AF>> + 'a {
+        self.default_route_stored_prefix()
+            .map(|p| p.get_prefix_id())
+            .into_iter()
+            .chain(PrefixIdIter {
+                store: self,
+                cur_len: 0,
+                cur_stride: 0,
+                cur_node_iter: None,
+                cur_pfx_iter: None,
+            })
+    }
+
+    // Iterator over all the prefixes in the storage that have a record for
+    // `mui`, with only that record, filtered like the more-specifics
+    // iterator filters the records for a mui. The prefixes are found
+    // through the mui indexes of the nodes, except for the default route,
+    // which lives in no node, and is looked up directly.
+    pub fn prefixes_iter_for_mui(
+        &'a self,
+        mui: u32,
+        include_withdrawn: bool,
+        guard: &'a Guard,
+    ) -> impl Iterator<Item = (PrefixId<AF>, Vec<PublicRecord<M>>)> + 'a {
+        let default_route = self.default_route_stored_prefix().and_then(|p| {
+            if include_withdrawn {
+                p.record_map.get_record_for_mui_with_rewritten_status(
+                    mui,
+                    self.withdrawn_muis_snapshot(guard),
+                    RouteStatus::Withdrawn,
+                )
+            } else {
+                p.record_map.get_record_for_active_mui(mui)
+            }
+            .map(|rec| (p.get_prefix_id(), vec![rec]))
+        });
+
+        default_route.into_iter().chain(self.more_specific_prefix_iter_from(
+            PrefixId::new(AF::zero(), 0),
+            Some(mui),
+            include_withdrawn,
+            guard,
+        ))
+    }
+
+    // Iterator over all the prefixes in the storage whose stored path
+    // selections are outdated, i.e. the record map for the prefix was
+    // modified after the best and backup path were last calculated.
+    pub fn outdated_prefixes_iter(
+        &'a self,
+        guard: &'a Guard,
+    ) -> impl Iterator<Item = Prefix> + 'a {
+        self.stored_prefixes_iter()
+            .filter(|s_pfx| s_pfx.is_ps_outdated(guard))
+            .map(|s_pfx| s_pfx.get_prefix_id().into_pub())
+    }
+
+    // Iterator over references to all the StoredPrefixes in the storage.
+    // The default route has no place in the prefix buckets that PrefixIter
+    // walks, so it is looked up directly, and comes first.
+    pub(crate) fn stored_prefixes_iter(
+        &'a self,
+    ) -> impl Iterator<Item = &'a StoredPrefix<AF, M>> + 'a {
+        self.default_route_stored_prefix().into_iter().chain(PrefixIter {
             prefixes: &self.prefixes,
             cur_bucket: self.prefixes.get_root_prefix_set(0),
             cur_len: 0,
+            end_len: AF::BITS,
             cur_level: 0,
             cursor: 0,
             parents: [None; 32],
-        }
+        })
+    }
+
+    // Iterator over references to the StoredPrefixes with length `len` in
+    // the storage. Only the prefix buckets for that length are visited, or
+    // for length zero, the default route only.
+    // `len` must not be larger than AF::BITS.
+    pub(crate) fn stored_prefixes_iter_for_len(
+        &'a self,
+        len: u8,
+    ) -> impl Iterator<Item = &'a StoredPrefix<AF, M>> + 'a {
+        let default_route = if len == 0 {
+            self.default_route_stored_prefix()
+        } else {
+            None
+        };
+        default_route.into_iter().chain(PrefixIter {
+            prefixes: &self.prefixes,
+            cur_bucket: self.prefixes.get_root_prefix_set(len),
+            cur_len: len,
+            end_len: len,
+            cur_level: 0,
+            cursor: 0,
+            parents: [None; 32],
+        })
     }
 }
 
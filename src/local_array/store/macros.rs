@@ -85,17 +85,21 @@ macro_rules! impl_search_level_for_mui {
                         Some(this_node) => {
                             let StoredNode { node_id, node, node_set, .. } = this_node;
 
-                            // early return if the mui is not in the index
-                            // stored in this node, meaning the mui does not
-                            // appear anywhere in the sub-tree formed from
-                            // this node.
-                            let bmin = node_set.1.read().unwrap(); // load(Ordering::Acquire, guard).deref()
-                            if !bmin.contains($mui) {
-                                return None;
-                            }
-
                             if $id == *node_id {
                                 // YES, It's the one we're looking for!
+
+                                // Return None if the mui is not in the index
+                                // stored in this node, meaning the mui does
+                                // not appear anywhere in the sub-tree formed
+                                // from this node. The index of a node that
+                                // we only pass because it collided with ours
+                                // says nothing about our sub-tree, so it is
+                                // not checked.
+                                let bmin = node_set.1.read().unwrap();
+                                if !bmin.contains($mui) {
+                                    return None;
+                                }
+
                                 return Some(SizedStrideRef::$stride(&node));
                             };
                             // Meh, it's not, but we can a go to the next
@@ -169,7 +173,8 @@ macro_rules! retrieve_node_mut_closure {
                         let next_level = <NB as NodeBuckets<AF>>::len_to_store_bits(
                             $id.get_id().1, level + 1
                         );
-                        let node_set = NodeSet::init(next_level - this_level);
+                        // next_level is 0 for the last level, there's no next NodeSet then.
+                        let node_set = NodeSet::init(next_level.saturating_sub(this_level));
 
                         // See if we can create the node
                         (node, _) = nodes.0.get_or_init(index, || StoredNode {
@@ -282,7 +287,8 @@ macro_rules! store_node_closure {
 
                         trace!("multi uniq id {}", multi_uniq_id);
 
-                        let node_set = NodeSet::init(next_level - this_level);
+                        // next_level is 0 for the last level, there's no next NodeSet then.
+                        let node_set = NodeSet::init(next_level.saturating_sub(this_level));
 
                         let ptrbitarr = new_node.ptrbitarr.load();
                         let pfxbitarr = new_node.pfxbitarr.load();
@@ -4,6 +4,7 @@ pub mod iterators;
 
 pub(crate) mod atomic_types;
 pub(crate) mod default_store;
+pub(crate) mod interner;
 pub(crate) mod oncebox;
 
 pub use default_store::DefaultStore;
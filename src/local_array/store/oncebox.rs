@@ -79,6 +79,12 @@ impl<T> OnceBoxSlice<T> {
         self.ptr.load(Ordering::Relaxed).is_null()
     }
 
+    // The number of slots in this slice, whether they are initialized or
+    // not.
+    pub fn size(&self) -> usize {
+        1 << self.p2_size
+    }
+
     pub fn get(&self, idx: usize) -> Option<&T> {
         let ptr = self.ptr.load(Ordering::Relaxed);
         if ptr.is_null() {
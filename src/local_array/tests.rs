@@ -56,3 +56,105 @@ fn test_af_2() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+//------------ Meta interner ------------------------------------------------
+
+#[test]
+fn test_interner_sweeps_stale_hashes() -> Result<(), Box<dyn Error>> {
+    use crate::local_array::store::interner::MetaInterner;
+    use crate::prelude::Meta;
+
+    #[derive(Clone, Debug)]
+    struct Bytes(Vec<u8>);
+
+    impl std::fmt::Display for Bytes {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "{:?}", self.0)
+        }
+    }
+
+    impl Meta for Bytes {
+        type Orderable<'a> = &'a [u8];
+        type TBI = ();
+
+        fn as_orderable(&self, _tbi: Self::TBI) -> &[u8] {
+            &self.0
+        }
+
+        fn intern_key(&self) -> Option<&[u8]> {
+            Some(&self.0)
+        }
+    }
+
+    let interner = MetaInterner::<Bytes>::new();
+
+    // Values that are dropped right away leave their hashes behind, until
+    // their shard is swept.
+    for i in 0..1000_u32 {
+        drop(interner.intern(Bytes(i.to_be_bytes().to_vec())));
+    }
+    let kept = (0..10_u32)
+        .map(|i| interner.intern(Bytes(vec![i as u8])))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| "no intern key")?;
+    assert_eq!(interner.hashes_count(), 1010);
+
+    let stats = interner.stats();
+    assert_eq!(stats.interned, 10);
+    assert_eq!(stats.misses, 1010);
+    assert_eq!(interner.hashes_count(), 10);
+
+    // The shard of every 1024th miss is swept.
+    for i in 0..2048_u32 {
+        drop(interner.intern(Bytes(i.to_be_bytes().to_vec())));
+    }
+    assert!(interner.hashes_count() < 10 + 2048);
+
+    drop(kept);
+    assert_eq!(interner.stats().interned, 0);
+    assert_eq!(interner.hashes_count(), 0);
+
+    Ok(())
+}
+
+//------------ Node mui index -----------------------------------------------
+
+#[test]
+fn test_poisoned_rbm_indexes() -> Result<(), Box<dyn Error>> {
+    use crate::local_array::atomic_stride::Stride4;
+    use crate::local_array::node::TreeBitMapNode;
+    use crate::local_array::store::atomic_types::{NodeSet, StoredNode};
+    use crate::local_array::tree::StrideNodeId;
+    use crate::IPv4;
+
+    let root = NodeSet::<IPv4, Stride4>::init(2);
+    for index in 0..2 {
+        root.0.get_or_init(index, || StoredNode {
+            node_id: StrideNodeId::new_with_cleaned_id(
+                (index as u32) << 28,
+                4,
+            ),
+            node: TreeBitMapNode::new(),
+            node_set: NodeSet::init(2),
+        });
+    }
+    assert_eq!(root.poisoned_rbm_indexes(false), 0);
+
+    // Panic while holding the write lock of the index of the second node.
+    let node = root.0.get(1).ok_or("no node")?;
+    let res = std::thread::scope(|s| {
+        s.spawn(|| {
+            let _rbm = node.node_set.1.write().unwrap();
+            panic!("panic while updating the index");
+        })
+        .join()
+    });
+    assert!(res.is_err());
+
+    assert_eq!(root.poisoned_rbm_indexes(false), 1);
+    assert_eq!(root.poisoned_rbm_indexes(true), 1);
+    assert_eq!(root.poisoned_rbm_indexes(false), 0);
+    assert!(node.node_set.1.read().is_ok());
+
+    Ok(())
+}
@@ -9,7 +9,10 @@ use std::sync::atomic::{
 use std::{fmt::Debug, marker::PhantomData};
 
 use crate::af::AddressFamily;
-use crate::custom_alloc::{CustomAllocStorage, UpsertReport};
+use crate::custom_alloc::{
+    CustomAllocStorage, DefaultNodeHasher, NodeHasher, UpsertMode,
+    UpsertReport,
+};
 use crate::insert_match;
 use crate::local_array::store::atomic_types::{NodeBuckets, PrefixBuckets};
 
@@ -356,8 +359,9 @@ pub struct TreeBitMap<
     M: Meta,
     NB: NodeBuckets<AF>,
     PB: PrefixBuckets<AF, M>,
+    H: NodeHasher = DefaultNodeHasher,
 > {
-    pub store: CustomAllocStorage<AF, M, NB, PB>,
+    pub store: CustomAllocStorage<AF, M, NB, PB, H>,
 }
 
 impl<
@@ -366,12 +370,12 @@ impl<
         M: Meta,
         NB: NodeBuckets<AF>,
         PB: PrefixBuckets<AF, M>,
-    > TreeBitMap<AF, M, NB, PB>
+        H: NodeHasher,
+    > TreeBitMap<AF, M, NB, PB, H>
 {
-    pub fn new(
-    ) -> Result<TreeBitMap<AF, M, NB, PB>, Box<dyn std::error::Error>> {
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
         let root_node =
-            match CustomAllocStorage::<AF, M, NB, PB>::get_first_stride_size()
+            match CustomAllocStorage::<AF, M, NB, PB, H>::get_first_stride_size()
             {
                 3 => SizedStrideNode::Stride3(TreeBitMapNode {
                     ptrbitarr: AtomicStride2(AtomicU8::new(0)),
@@ -397,7 +401,7 @@ impl<
             };
 
         Ok(TreeBitMap {
-            store: CustomAllocStorage::<AF, M, NB, PB>::init(root_node)?,
+            store: CustomAllocStorage::<AF, M, NB, PB, H>::init(root_node)?,
         })
     }
 
@@ -437,13 +441,91 @@ impl<
         pfx: PrefixId<AF>,
         record: PublicRecord<M>,
         update_path_selections: Option<M::TBI>,
+    ) -> Result<UpsertReport, PrefixStoreError> {
+        self.upsert(
+            pfx,
+            record,
+            update_path_selections,
+            UpsertMode::Replace,
+        )
+    }
+
+    // Insert the record like `insert`, but return RecordAlreadyExists,
+    // leaving the store unchanged, if the prefix already has a record for
+    // the mui of `record`.
+    pub fn try_insert(
+        &self,
+        pfx: PrefixId<AF>,
+        record: PublicRecord<M>,
+        update_path_selections: Option<M::TBI>,
+    ) -> Result<UpsertReport, PrefixStoreError> {
+        self.upsert(
+            pfx,
+            record,
+            update_path_selections,
+            UpsertMode::InsertOnly,
+        )
+    }
+
+    // Insert the record like `insert`, but if it is a withdrawal for a mui
+    // that has no record for the prefix yet, leave the store unchanged and
+    // return a NoOp outcome. Whether the mui has a record is checked under
+    // the lock on the records of the prefix.
+    pub fn insert_skip_unknown_withdrawal(
+        &self,
+        pfx: PrefixId<AF>,
+        record: PublicRecord<M>,
+        update_path_selections: Option<M::TBI>,
+    ) -> Result<UpsertReport, PrefixStoreError> {
+        self.upsert(
+            pfx,
+            record,
+            update_path_selections,
+            UpsertMode::SkipUnknownWithdrawal,
+        )
+    }
+
+    fn upsert(
+        &self,
+        pfx: PrefixId<AF>,
+        record: PublicRecord<M>,
+        update_path_selections: Option<M::TBI>,
+        mode: UpsertMode,
+    ) -> Result<UpsertReport, PrefixStoreError> {
+        #[cfg(feature = "tracing")]
+        let span = tracing::span!(
+            tracing::Level::TRACE,
+            "insert",
+            af = if AF::BITS == 32 { "ipv4" } else { "ipv6" },
+            len = pfx.get_len(),
+            retry_count = tracing::field::Empty,
+        )
+        .entered();
+
+        let res =
+            self.insert_in_tree(pfx, record, update_path_selections, mode);
+
+        #[cfg(feature = "tracing")]
+        if let Ok(report) = &res {
+            span.record("retry_count", report.cas_count);
+        }
+
+        res
+    }
+
+    fn insert_in_tree(
+        &self,
+        pfx: PrefixId<AF>,
+        record: PublicRecord<M>,
+        update_path_selections: Option<M::TBI>,
+        mode: UpsertMode,
         // user_data: Option<&<M as MergeUpdate>::UserDataIn>,
     ) -> Result<UpsertReport, PrefixStoreError> {
         let guard = &epoch::pin();
         // let record = MultiMapValue::new(meta, ltime, status);
 
         if pfx.get_len() == 0 {
-            let res = self.update_default_route_prefix_meta(record, guard)?;
+            let res = self.update_default_route_prefix_meta(record, mode, guard)?;
             return Ok(res);
         }
 
@@ -451,6 +533,7 @@ impl<
         let mut cur_i = self.store.get_root_node_id();
         let mut level: u8 = 0;
         let mut acc_retry_count = 0;
+        let mut acc_backoff_count = 0;
 
         loop {
             let stride = self.store.get_stride_sizes()[level as usize];
@@ -482,11 +565,13 @@ impl<
                 pfx;
                 record;
                 update_path_selections; // perform an update for the paths in this record
+                mode;
                 stride_start; // the length at the start of the stride a.k.a. start_bit
                 stride;
                 cur_i;
                 level;
                 acc_retry_count;
+                acc_backoff_count;
                 // Strides to create match arm for; stats level
                 Stride3; 0,
                 Stride4; 1,
@@ -494,16 +579,19 @@ impl<
             ];
 
             match node_result {
-                Ok((next_id, retry_count)) => {
+                Ok((next_id, retry_count, backoff_count)) => {
                     cur_i = next_id;
                     level += 1;
                     acc_retry_count += retry_count;
+                    acc_backoff_count += backoff_count;
                 }
                 Err(err) => {
+                    self.store.counters.inc_fatal_node_errors_count();
                     if log_enabled!(log::Level::Error) {
-                        error!("{} failing to store (intermediate) node {}. Giving up this node. This shouldn't happen!",
+                        error!("{} failing to store (intermediate) node {} for prefix {}. Giving up this node. This shouldn't happen!",
                             std::thread::current().name().unwrap_or("unnamed-thread"),
                             cur_i,
+                            pfx.into_pub(),
                         );
                         error!(
                             "{} {}",
@@ -545,6 +633,7 @@ impl<
     fn update_default_route_prefix_meta(
         &self,
         record: PublicRecord<M>,
+        mode: UpsertMode,
         guard: &epoch::Guard,
         // user_data: Option<&<M as MergeUpdate>::UserDataIn>,
     ) -> Result<UpsertReport, PrefixStoreError> {
@@ -582,6 +671,7 @@ impl<
             record,
             // Do not update the path selection for the default route.
             None,
+            mode,
             guard,
             // user_data,
         )
@@ -668,7 +758,8 @@ impl<
         M: Meta,
         NB: NodeBuckets<AF>,
         PB: PrefixBuckets<AF, M>,
-    > Default for TreeBitMap<AF, M, NB, PB>
+        H: NodeHasher,
+    > Default for TreeBitMap<AF, M, NB, PB, H>
 {
     fn default() -> Self {
         Self::new().unwrap()
@@ -682,11 +773,14 @@ impl<
         M: Meta,
         NB: NodeBuckets<AF>,
         PB: PrefixBuckets<AF, M>,
-    > std::fmt::Display for TreeBitMap<AF, M, NB, PB>
+        H: NodeHasher,
+    > std::fmt::Display for TreeBitMap<AF, M, NB, PB, H>
 {
     fn fmt(&self, _f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(_f, "{} prefixes created", self.store.get_prefixes_count())?;
-        writeln!(_f, "{} nodes created", self.store.get_nodes_count())?;
+        let report = self.store.stats_report();
+
+        writeln!(_f, "{} prefixes created", report.prefixes)?;
+        writeln!(_f, "{} nodes created", report.nodes)?;
         writeln!(_f)?;
 
         writeln!(
@@ -718,11 +812,16 @@ impl<
                 .collect::<Vec<(usize, u8)>>()
         );
 
-        for crate::stats::CreatedNodes {
-            depth_level: len,
-            count: prefix_count,
-        } in self.store.counters.get_prefix_stats()
+        for crate::custom_alloc::LenStatsReport {
+            len,
+            prefixes: prefix_count,
+            prefixes_occupied_pct,
+            ..
+        } in report.lengths
         {
+            if prefix_count == 0 {
+                continue;
+            }
             let max_pfx = u128::overflowing_pow(2, len as u32);
             let n = (prefix_count as u32 / SCALE) as usize;
 
@@ -744,9 +843,7 @@ impl<
             write!(
                 _f,
                 " {}/{} {:.2}%",
-                prefix_count,
-                max_pfx.0,
-                (prefix_count as f64 / max_pfx.0 as f64) * 100.0
+                prefix_count, max_pfx.0, prefixes_occupied_pct
             )?;
 
             writeln!(_f)?;
@@ -774,7 +774,15 @@ where
             match_type,
             less_specifics: if options.include_less_specifics {
                 less_specifics_vec.map(|vec| {
+                    // The less-specifics were collected while descending the
+                    // tree, so the last one is the longest.
+                    let skip = if options.stop_at_first_less_specific {
+                        vec.len().saturating_sub(1)
+                    } else {
+                        0
+                    };
                     vec.iter()
+                        .skip(skip)
                         .map(|p| self.retrieve_prefix(p.get_part()).unwrap())
                         .collect::<RecordSingleSet<Store::Meta>>()
                 })
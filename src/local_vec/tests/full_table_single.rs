@@ -131,7 +131,9 @@ mod full_table {
                         include_withdrawn: false,
                         include_less_specifics: false,
                         include_more_specifics: false,
-                        mui: None
+                        mui: None,
+                        debug_path: false,
+                        stop_at_first_less_specific: false,
                     },
                 );
 
@@ -169,7 +171,9 @@ mod full_table {
                                 include_withdrawn: false,
                                 include_less_specifics: false,
                                 include_more_specifics: false,
-                                mui: None
+                                mui: None,
+                                debug_path: false,
+                                stop_at_first_less_specific: false,
                             },
                         );
                         if let Some(_pfx) = res.prefix {
@@ -59,7 +59,9 @@ mod tests {
                     include_withdrawn: false,
                     include_less_specifics: false,
                     include_more_specifics: true,
-                    mui: None
+                    mui: None,
+                    debug_path: false,
+                    stop_at_first_less_specific: false,
                 },
             );
             println!("em/m-s: {:#?}", found_result);
@@ -135,7 +137,9 @@ mod tests {
                     include_withdrawn: false,
                     include_less_specifics: false,
                     include_more_specifics: true,
-                    mui: None
+                    mui: None,
+                    debug_path: false,
+                    stop_at_first_less_specific: false,
                 },
             );
             println!("em/m-s: {}", found_result);
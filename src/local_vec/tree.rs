@@ -3,7 +3,7 @@ use std::{
     marker::PhantomData,
 };
 
-use crate::{af::{AddressFamily, Zero}, custom_alloc::UpsertReport};
+use crate::{af::{AddressFamily, Zero}, custom_alloc::{UpsertOutcome, UpsertReport}};
 use crate::local_vec::node::TreeBitMapNode;
 use crate::local_vec::storage_backend::StorageBackend;
 use crate::match_node_for_strides_with_local_vec;
@@ -340,7 +340,14 @@ where
         match self.store.retrieve_prefix_mut(update_node_idx) {
             Some(update_pfx) => {
                 update_pfx.meta = meta;
-                Ok(UpsertReport { cas_count: 0, prefix_new: false, mui_new: false, mui_count: 0 })
+                Ok(UpsertReport {
+                    cas_count: 0,
+                    backoff_count: 0,
+                    prefix_new: false,
+                    mui_new: false,
+                    mui_count: 0,
+                    outcome: UpsertOutcome::Replaced,
+                })
                 // <Store::Meta>::merge_update(&mut update_pfx.meta, meta)
             }
             // TODO
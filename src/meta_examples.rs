@@ -2,6 +2,7 @@
 
 use inetnum::asn::Asn;
 
+use crate::prelude::multi::MaxLenOrigin;
 use crate::Meta;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -82,6 +83,18 @@ impl Meta for NoMeta {
     fn as_orderable(&self, _tbi: Self::TBI) {}
 }
 
+impl AsRef<[u8]> for NoMeta {
+    fn as_ref(&self) -> &[u8] {
+        &[]
+    }
+}
+
+impl From<Vec<u8>> for NoMeta {
+    fn from(_value: Vec<u8>) -> Self {
+        NoMeta::Empty
+    }
+}
+
 
 // impl MergeUpdate for NoMeta {
 //     type UserDataIn = ();
@@ -102,4 +115,38 @@ impl Meta for NoMeta {
 //     ) -> Result<(Self, Self::UserDataOut), Box<dyn std::error::Error>> {
 //         Ok((NoMeta::Empty, ()))
 //     }
-// }
\ No newline at end of file
+// }
+
+//------------ Vrp Metadata impl --------------------------------------------
+
+/// A Validated ROA Payload: the AS number that is authorized to originate
+/// the prefix it is stored for, and the maximum length of its announcements.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Vrp {
+    pub asn: u32,
+    pub max_len: u8,
+}
+
+impl Meta for Vrp {
+    type Orderable<'a> = Asn;
+    type TBI = ();
+    fn as_orderable(&self, _tbi: Self::TBI) -> Asn {
+        self.asn.into()
+    }
+}
+
+impl MaxLenOrigin for Vrp {
+    fn origin(&self) -> u32 {
+        self.asn
+    }
+
+    fn max_len(&self) -> u8 {
+        self.max_len
+    }
+}
+
+impl std::fmt::Display for Vrp {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "AS{} max-len {}", self.asn, self.max_len)
+    }
+}
@@ -233,12 +233,24 @@ pub struct PublicRecord<M> {
     pub multi_uniq_id: u32,
     pub ltime: u64,
     pub status: RouteStatus,
+    /// The status of the record itself. This differs from `status` for a
+    /// record that is returned with its status rewritten to `Withdrawn`,
+    /// because its `multi_uniq_id` is globally withdrawn. It is ignored by
+    /// `insert`.
+    pub local_status: RouteStatus,
     pub meta: M,
 }
 
 impl<M> PublicRecord<M> {
     pub fn new(multi_uniq_id: u32, ltime: u64, status: RouteStatus, meta: M) -> Self {
-        Self { meta, multi_uniq_id, ltime, status }
+        Self { meta, multi_uniq_id, ltime, status, local_status: status }
+    }
+
+    /// Whether this record is withdrawn because its `multi_uniq_id` is
+    /// globally withdrawn, while its own status isn't `Withdrawn`.
+    pub fn is_globally_withdrawn(&self) -> bool {
+        self.status == RouteStatus::Withdrawn
+            && self.local_status != RouteStatus::Withdrawn
     }
 }
 
@@ -257,9 +269,10 @@ impl<M: Clone> From<(u32, MultiMapValue<M>)> for PublicRecord<M> {
     fn from(value: (u32, MultiMapValue<M>)) -> Self {
         Self {
             multi_uniq_id: value.0,
-            meta: value.1.meta,
+            meta: value.1.meta.into_inner(),
             ltime: value.1.ltime,
             status: value.1.status,
+            local_status: value.1.status,
         }
     }
 }
@@ -526,6 +539,15 @@ impl<M: Meta> RecordSet<M> {
     pub fn len(&self) -> usize {
         self.v4.len() + self.v6.len()
     }
+
+    // The prefix with the greatest length in the set, if any.
+    pub(crate) fn longest_prefix(&self) -> Option<Prefix> {
+        self.v4
+            .iter()
+            .chain(self.v6.iter())
+            .map(|r| r.prefix)
+            .max_by_key(|p| p.len())
+    }
 }
 
 impl<M: Meta> Default for RecordSet<M> {
@@ -745,6 +767,17 @@ where
         type TBI: Copy;
 
         fn as_orderable(&self, tbi: Self::TBI) -> Self::Orderable<'_>;
+
+        /// The bytes that identify this meta-data for interning, see
+        /// `intern_meta` in the
+        /// [StoreConfig](crate::prelude::multi::StoreConfig). Two values
+        /// with the same key are considered equal, so that a store may
+        /// keep only one of them. Types that implement `AsRef<[u8]>` will
+        /// typically return `Some(self.as_ref())`. The default returns
+        /// None, and meta-data without a key is never interned.
+        fn intern_key(&self) -> Option<&[u8]> {
+            None
+        }
     }
 
 impl Meta for inetnum::asn::Asn {
@@ -754,4 +787,87 @@ impl Meta for inetnum::asn::Asn {
     fn as_orderable(&self, _tbi: Self::TBI) -> inetnum::asn::Asn {
         *self
     }
-}
\ No newline at end of file
+}
+
+//------------ BestPathPolicy -----------------------------------------------
+
+/// Trait for a best path selection policy that is independent of the
+/// [Meta] implementation of the records.
+///
+/// Unlike the ordering based on `M::TBI`, a policy can be chosen at
+/// runtime, e.g. to maintain several RIBs with different selection
+/// policies from one store. It is implemented for all closures with the
+/// signature of `compare`.
+pub trait BestPathPolicy<M> {
+    /// Compares two records. `Ordering::Less` means that `a` is preferred
+    /// over `b`. Of the records that compare equal, the one with the lowest
+    /// `multi_uniq_id` is preferred.
+    fn compare(&self, a: &PublicRecord<M>, b: &PublicRecord<M>) -> Ordering;
+}
+
+impl<M, F> BestPathPolicy<M> for F
+where
+    F: Fn(&PublicRecord<M>, &PublicRecord<M>) -> Ordering,
+{
+    fn compare(&self, a: &PublicRecord<M>, b: &PublicRecord<M>) -> Ordering {
+        self(a, b)
+    }
+}
+
+//------------ MaxLenOrigin -------------------------------------------------
+
+/// Trait for meta-data that authorizes an origin AS to announce a prefix,
+/// and its more-specifics up to a maximum length, like a Validated ROA
+/// Payload in the RPKI.
+///
+/// A store with this meta-data can be used for route origin validation
+/// with `validate_origin`.
+pub trait MaxLenOrigin {
+    /// The AS number that is authorized to originate the prefix.
+    fn origin(&self) -> u32;
+    /// The maximum length of the prefixes the origin AS may announce.
+    fn max_len(&self) -> u8;
+}
+
+// Wraps a record to order it according to a BestPathPolicy, so that it can
+// be fed to the generic best and backup path selection of routecore.
+pub(crate) struct PolicyOrdered<'a, M> {
+    pub(crate) record: PublicRecord<M>,
+    policy: &'a dyn BestPathPolicy<M>,
+}
+
+impl<'a, M> PolicyOrdered<'a, M> {
+    pub(crate) fn new(
+        record: PublicRecord<M>,
+        policy: &'a dyn BestPathPolicy<M>,
+    ) -> Self {
+        Self { record, policy }
+    }
+}
+
+impl<M> PartialEq for PolicyOrdered<'_, M> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<M> Eq for PolicyOrdered<'_, M> {}
+
+impl<M> PartialOrd for PolicyOrdered<'_, M> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Records that the policy considers equal are ordered by their mui, like
+// the built-in best path selection does, so that the lowest mui wins a tie
+// regardless of the iteration order of the record map.
+impl<M> Ord for PolicyOrdered<'_, M> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.policy
+            .compare(&self.record, &other.record)
+            .then_with(|| {
+                self.record.multi_uniq_id.cmp(&other.record.multi_uniq_id)
+            })
+    }
+}
@@ -2,7 +2,11 @@ pub use crate::{AddressFamily, IPv4, IPv6};
 
 pub use crate::prefix_record::{Meta, PublicPrefixRecord as PrefixRecord};
 pub use crate::stride::{Stride3, Stride4, Stride5};
-pub use crate::{MatchOptions, MatchType, QueryResult};
+pub use crate::{
+    AfSet, AutoBestPath, DifferingPrefix, MatchOptions, MatchPathNode,
+    MatchType, QueryCostEstimate, QueryResult, RpkiState, StoreConfig,
+    StoreDiff, StoreEvent,
+};
 pub use inetnum::addr::Prefix;
 
 pub mod multi {
@@ -16,16 +20,28 @@ pub mod multi {
 
     pub use crate::local_array::store::atomic_types::RouteStatus;
     pub use crate::local_array::store::atomic_types::{
-        NodeBuckets, NodeSet, PrefixBuckets, PrefixSet,
+        MultiMapView, NodeBuckets, NodeSet, PrefixBuckets, PrefixSet,
     };
     pub use crate::local_array::store::errors::PrefixStoreError;
     pub use crate::local_array::tree::{PrefixId, StrideNodeId, TreeBitMap};
+    pub use crate::prefix_record::{BestPathPolicy, MaxLenOrigin};
     pub use crate::prefix_record::PublicRecord as Record;
 
-    pub use crate::custom_alloc::CustomAllocStorage;
     pub use crate::custom_alloc::{
-        Counters, StoreStats, Upsert, UpsertReport,
+        CustomAllocStorage, DefaultNodeHasher, NodeHasher,
     };
+    pub use crate::custom_alloc::{
+        AfStatsReport, ChainDepths, ChainStats, Counters, FillFactors,
+        HealthReport, InsertPreview, LenStatsReport, LevelFill, MemoryStats,
+        NodeReport, StatsReport, StoreStats, Upsert, UpsertOutcome,
+        UpsertReport,
+    };
+    pub use crate::local_array::store::interner::{
+        InternerStats, MetaInterner,
+    };
+    pub use crate::stats::ChainDepth;
+    #[cfg(feature = "debug-internals")]
+    pub use crate::custom_alloc::NodeDebug;
 
     pub use routecore::bgp::path_selection::TiebreakerInfo;
 }
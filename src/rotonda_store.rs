@@ -1,8 +1,9 @@
 use std::{fmt, slice};
 
-use crate::prefix_record::{PublicRecord, RecordSet};
+use crate::prefix_record::{PublicPrefixRecord, PublicRecord, RecordSet};
 pub use crate::prefix_record::{PublicPrefixSingleRecord, Meta, RecordSingleSet};
 use crate::{prefix_record::InternalPrefixRecord, stats::StrideStats};
+use crate::local_array::store::errors::PrefixStoreError;
 
 use inetnum::addr::Prefix;
 
@@ -59,6 +60,223 @@ impl<'a> std::fmt::Debug for Strides<'a> {
     }
 }
 
+//------------ StoreConfig ---------------------------------------------------
+
+// The default for `max_retries_before_yield` in the StoreConfig.
+pub(crate) const DEFAULT_MAX_RETRIES_BEFORE_YIELD: u32 = 32;
+
+/// Configuration for the [MultiThreadedStore]
+///
+/// A store created with [MultiThreadedStore::new] uses the default
+/// configuration, use [MultiThreadedStore::new_with_config] to create one
+/// with another configuration.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct StoreConfig {
+    /// The address families the store holds prefixes for. Inserting a
+    /// prefix, or changing the status of records, for another family
+    /// returns an `AddressFamilyDisabled` error.
+    pub address_families: AfSet,
+    /// The range of prefix lengths (both inclusive) of the IPv4 prefixes
+    /// that are accepted by `insert`. Inserting a prefix with a length
+    /// outside this range returns a `PrefixLengthNotAccepted` error. None
+    /// accepts all lengths.
+    pub accepted_len_range_v4: Option<(u8, u8)>,
+    /// The range of prefix lengths (both inclusive) of the IPv6 prefixes
+    /// that are accepted by `insert`. None accepts all lengths.
+    pub accepted_len_range_v6: Option<(u8, u8)>,
+    /// Whether the store sends a [StoreEvent] to its subscribers for every
+    /// mutation. Off by default, since it costs a clone of every inserted
+    /// record.
+    pub emit_events: bool,
+    /// Whether `insert` ignores a record with a `Withdrawn` status for a
+    /// (prefix, mui) combination that isn't in the store. If set, such an
+    /// insert doesn't create the prefix, and returns an `UpsertReport`
+    /// with a `NoOp` outcome. If not set (the default), the prefix is
+    /// created and the withdrawn record is stored.
+    pub ignore_withdrawal_of_unknown: bool,
+    /// Whether `insert` calculates the best and backup path for the
+    /// prefix, see [AutoBestPath].
+    pub auto_best_path: AutoBestPath,
+    /// The number of times a thread retries to update the bitmaps of a node
+    /// in the tree on contention with other writers, before it starts to
+    /// yield to other threads between the retries, instead of spinning.
+    /// The number of times it yielded is reported in the `backoff_count`
+    /// of the [UpsertReport](crate::custom_alloc::UpsertReport). If a
+    /// thread has to retry 2^20 times, the insert fails with a
+    /// `NodeCreationMaxRetryError`. Defaults to 32.
+    pub max_retries_before_yield: u32,
+    /// Whether the store keeps only one copy of the meta-data of records
+    /// that have the same [intern_key](crate::Meta::intern_key), shared
+    /// by all those records, instead of a copy for every record. This
+    /// saves memory if many records carry the same meta-data, at the
+    /// price of a lookup on every insert. Meta-data without an intern key
+    /// is never shared. The records returned by queries still carry their
+    /// own copy of the meta-data. Off by default.
+    pub intern_meta: bool,
+}
+
+impl Default for StoreConfig {
+    fn default() -> Self {
+        Self {
+            address_families: AfSet::default(),
+            accepted_len_range_v4: None,
+            accepted_len_range_v6: None,
+            emit_events: false,
+            ignore_withdrawal_of_unknown: false,
+            auto_best_path: AutoBestPath::default(),
+            max_retries_before_yield: DEFAULT_MAX_RETRIES_BEFORE_YIELD,
+            intern_meta: false,
+        }
+    }
+}
+
+impl StoreConfig {
+    /// Whether the address family of `prefix` is enabled in this
+    /// configuration.
+    pub fn accepts_af(&self, prefix: &Prefix) -> bool {
+        match prefix.addr() {
+            std::net::IpAddr::V4(_) => self.address_families.has_v4(),
+            std::net::IpAddr::V6(_) => self.address_families.has_v6(),
+        }
+    }
+
+    /// Whether the length of `prefix` is accepted by this configuration.
+    pub fn accepts_len(&self, prefix: &Prefix) -> bool {
+        let range = match prefix.addr() {
+            std::net::IpAddr::V4(_) => self.accepted_len_range_v4,
+            std::net::IpAddr::V6(_) => self.accepted_len_range_v6,
+        };
+        range.map_or(true, |(min, max)| {
+            (min..=max).contains(&prefix.len())
+        })
+    }
+
+    /// Checks whether this configuration is coherent, i.e. whether the
+    /// accepted ranges of prefix lengths are non-empty and fit in the
+    /// lengths of their address family. [MultiThreadedStore::new_with_config]
+    /// calls this, and returns the error if it fails.
+    pub fn validate(&self) -> Result<(), PrefixStoreError> {
+        let ranges = [
+            ("IPv4", self.accepted_len_range_v4, 32),
+            ("IPv6", self.accepted_len_range_v6, 128),
+        ];
+        for (af, range, bits) in ranges {
+            if let Some((min, max)) = range {
+                if min > max {
+                    return Err(PrefixStoreError::InvalidStoreConfig(
+                        format!(
+                            "accepted {} length range {}-{} is empty",
+                            af, min, max
+                        ),
+                    ));
+                }
+                if max > bits {
+                    return Err(PrefixStoreError::InvalidStoreConfig(
+                        format!(
+                            "accepted {} length range {}-{} exceeds the \
+                            maximum length of {}",
+                            af, min, max, bits
+                        ),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The address families a [MultiThreadedStore] holds prefixes for
+///
+/// The trees for both families always exist, but the tree for a disabled
+/// family stays empty, and an empty tree hardly takes any memory, since its
+/// buckets are allocated when the first prefix is stored in them.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum AfSet {
+    /// Both IPv4 and IPv6.
+    #[default]
+    Both,
+    /// IPv4 only.
+    Ipv4Only,
+    /// IPv6 only.
+    Ipv6Only,
+}
+
+impl AfSet {
+    /// Whether IPv4 is in this set.
+    pub fn has_v4(&self) -> bool {
+        matches!(self, AfSet::Both | AfSet::Ipv4Only)
+    }
+
+    /// Whether IPv6 is in this set.
+    pub fn has_v6(&self) -> bool {
+        matches!(self, AfSet::Both | AfSet::Ipv6Only)
+    }
+}
+
+/// When a [MultiThreadedStore] calculates the best and backup path for a
+/// prefix that a record is inserted for
+///
+/// Calculating the best path on every insert is expensive during a bulk
+/// load, like a full table, where most prefixes receive many records. With
+/// `Deferred` the inserts only mark the best path of their prefix as
+/// outdated, so that `recalculate_outdated_best_paths` can calculate them
+/// all at once after the load.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum AutoBestPath {
+    /// Never calculate the best path on insert, the `update_path_selections`
+    /// argument of `insert` is ignored.
+    Off,
+    /// Calculate the best path on insert if the `update_path_selections`
+    /// argument of `insert` is set.
+    #[default]
+    OnInsert,
+    /// Never calculate the best path on insert, but mark it as outdated,
+    /// also for newly created prefixes, so that `outdated_best_paths`
+    /// and `recalculate_outdated_best_paths` include all the prefixes that
+    /// were inserted into.
+    Deferred,
+}
+
+impl std::fmt::Display for AfSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            AfSet::Both => write!(f, "ipv4+ipv6"),
+            AfSet::Ipv4Only => write!(f, "ipv4"),
+            AfSet::Ipv6Only => write!(f, "ipv6"),
+        }
+    }
+}
+
+//------------ StoreEvent ----------------------------------------------------
+
+/// A mutation of the [MultiThreadedStore]
+///
+/// If the store was created with `emit_events` set in its [StoreConfig],
+/// these are sent to all the receivers created with
+/// [MultiThreadedStore::subscribe], after the mutation succeeded.
+#[derive(Debug, Clone)]
+pub enum StoreEvent<M> {
+    /// A record for a new (prefix, mui) combination was inserted.
+    Inserted { prefix: Prefix, record: PublicRecord<M> },
+    /// The existing record for a (prefix, mui) combination was replaced.
+    Replaced { prefix: Prefix, record: PublicRecord<M> },
+    /// The local status of the record for (prefix, mui) was set to
+    /// Withdrawn.
+    Withdrawn { prefix: Prefix, mui: u32 },
+    /// The local status of the record for (prefix, mui) was set to Active.
+    Activated { prefix: Prefix, mui: u32 },
+    /// The local status of the record for (prefix, mui) was set to Stale.
+    Stale { prefix: Prefix, mui: u32 },
+    /// The mui was withdrawn globally, for the IPv4 prefixes, the IPv6
+    /// prefixes, or both.
+    MuiWithdrawn { mui: u32, ipv4: bool, ipv6: bool },
+    /// The mui was made active globally, for the IPv4 prefixes, or the IPv6
+    /// prefixes.
+    MuiActivated { mui: u32, ipv4: bool, ipv6: bool },
+    /// The local status of all the records for the mui was set to Stale.
+    MuiStale { mui: u32 },
+}
+
 //------------ MatchOptions / MatchType -------------------------------------
 
 /// Options for the `match_prefix` method
@@ -74,7 +292,10 @@ impl<'a> std::fmt::Debug for Strides<'a> {
 pub struct MatchOptions {
     /// The requested [MatchType]
     pub match_type: MatchType,
-    /// Unused
+    /// Whether to include withdrawn records in the query result. The
+    /// status of the records for globally withdrawn `multi_uniq_id`s is
+    /// rewritten to `Withdrawn`, their own status is kept in the
+    /// `local_status` field of the records.
     pub include_withdrawn: bool,
     /// Whether to include all less-specific records in the query result
     pub include_less_specifics: bool,
@@ -82,7 +303,17 @@ pub struct MatchOptions {
     pub include_more_specifics: bool,
     /// Whether to return records for a specific multi_uniq_id, None indicates
     /// all records.
-    pub mui: Option<u32>
+    pub mui: Option<u32>,
+    /// Whether to return the path of nodes through the tree for the search
+    /// prefix in the query result, see [MatchPathNode]. This is meant for
+    /// debugging only, collecting the path costs a lookup for every node on
+    /// it. The [SingleThreadedStore] ignores this option.
+    pub debug_path: bool,
+    /// Whether to return only the first, i.e. the longest, less-specific
+    /// in the query result, instead of all of them, if
+    /// `include_less_specifics` is set. Useful for a longest prefix match
+    /// that only needs the immediate cover of the resulting prefix.
+    pub stop_at_first_less_specific: bool,
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
@@ -108,6 +339,67 @@ impl std::fmt::Display for MatchType {
     }
 }
 
+/// The approximate size of the result of a `match_prefix` query, as
+/// returned by `estimate_query_cost`, without running the query.
+///
+/// The estimate is based on the number of prefixes per prefix length in
+/// the store only, it doesn't take the `mui` and `include_withdrawn`
+/// options into account, so the actual result may be smaller.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct QueryCostEstimate {
+    /// The maximum number of less-specific prefixes in the result, i.e.
+    /// the number of populated prefix lengths shorter than the search
+    /// prefix. Zero if `include_less_specifics` is not set.
+    pub less_specifics: usize,
+    /// The approximate number of more-specific prefixes in the result.
+    /// This assumes the prefixes of each length are spread evenly over the
+    /// address space. Zero if `include_more_specifics` is not set.
+    pub more_specifics: usize,
+}
+
+/// The outcome of a route origin validation with `validate_origin`, as
+/// defined in RFC 6811.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum RpkiState {
+    /// A covering record authorizes the origin AS for the prefix length.
+    Valid,
+    /// There are covering records, but none of them authorizes the origin
+    /// AS for the prefix length.
+    Invalid,
+    /// There are no covering records for the prefix.
+    NotFound,
+}
+
+impl std::fmt::Display for RpkiState {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RpkiState::Valid => write!(f, "valid"),
+            RpkiState::Invalid => write!(f, "invalid"),
+            RpkiState::NotFound => write!(f, "not-found"),
+        }
+    }
+}
+
+//------------- MatchPathNode -----------------------------------------------
+
+/// A node on the path through the tree for the search prefix of a query.
+///
+/// Returned in the [QueryResult] if `debug_path` is set in the
+/// [MatchOptions]. The path starts at the root node, and ends at the node
+/// that hosts the search prefix, or at the last node that exists on the way
+/// to it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MatchPathNode {
+    /// The id of the node, expressed as the prefix formed by the bits of the
+    /// node id and its length.
+    pub node_id: Prefix,
+    /// The bits of the search prefix that fall within this node, right
+    /// aligned.
+    pub bits: u32,
+    /// The number of bits of the search prefix that fall within this node.
+    pub len: u8,
+}
+
 
 //------------ PrefixRecordIter ---------------------------------------------
 
@@ -167,8 +459,15 @@ pub struct QueryResult<M: crate::prefix_record::Meta> {
     pub prefix_meta: Vec<PublicRecord<M>>,
     /// The less-specifics of the resulting prefix together with their meta data
     pub less_specifics: Option<RecordSet<M>>,
+    /// The longest of the less-specifics, i.e. the immediate cover of the
+    /// resulting prefix, or of the search prefix if there is no resulting
+    /// prefix. Only set if less-specifics were requested.
+    pub covering: Option<Prefix>,
     /// The more-specifics of the resulting prefix together with their meta data
     pub more_specifics: Option<RecordSet<M>>,
+    /// The nodes on the path through the tree for the search prefix, if
+    /// `debug_path` was set in the [MatchOptions]
+    pub debug_path: Option<Vec<MatchPathNode>>,
 }
 
 impl<M: Meta> fmt::Display for QueryResult<M> {
@@ -200,3 +499,41 @@ impl<M: Meta> fmt::Display for QueryResult<M> {
         })
     }
 }
+
+//------------- StoreDiff ---------------------------------------------------
+
+/// The differences between the records in two stores.
+///
+/// Returned by [MultiThreadedStore::diff]. Records are compared on their
+/// `multi_uniq_id`, `ltime`, local `status` and meta-data. All the vectors
+/// are ordered by prefix, the records in them by `multi_uniq_id`.
+#[derive(Clone, Debug)]
+pub struct StoreDiff<M: Meta> {
+    /// The prefixes with records that only appear in the first store.
+    pub only_in_self: Vec<PublicPrefixRecord<M>>,
+    /// The prefixes with records that only appear in the second store.
+    pub only_in_other: Vec<PublicPrefixRecord<M>>,
+    /// The prefixes that appear in both stores, with different records.
+    pub differing: Vec<DifferingPrefix<M>>,
+}
+
+impl<M: Meta> StoreDiff<M> {
+    /// Whether the two stores hold exactly the same records.
+    pub fn is_empty(&self) -> bool {
+        self.only_in_self.is_empty()
+            && self.only_in_other.is_empty()
+            && self.differing.is_empty()
+    }
+}
+
+/// A prefix that appears in both stores of a [StoreDiff], with different
+/// records.
+#[derive(Clone, Debug)]
+pub struct DifferingPrefix<M: Meta> {
+    /// The prefix.
+    pub prefix: Prefix,
+    /// The records for the prefix in the first store.
+    pub in_self: Vec<PublicRecord<M>>,
+    /// The records for the prefix in the second store.
+    pub in_other: Vec<PublicRecord<M>>,
+}
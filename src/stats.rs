@@ -152,3 +152,40 @@ impl Debug for CreatedNodes {
         f.write_fmt(format_args!("/{}: {}", &self.depth_level, &self.count))
     }
 }
+
+// The depth of the chains of buckets for one prefix length or node length,
+// i.e. how many levels below the root array had to be descended into to
+// reach the stored elements of that length. An element in the root array
+// has a depth of 0.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ChainDepth {
+    pub len: u8,
+    // The number of stored elements of this length.
+    pub count: usize,
+    // The sum of the depths of all the stored elements of this length.
+    pub total_depth: usize,
+    pub max_depth: u8,
+}
+
+impl ChainDepth {
+    pub fn new(len: u8) -> Self {
+        Self {
+            len,
+            ..Default::default()
+        }
+    }
+
+    pub fn inc(&mut self, depth: u8) {
+        self.count += 1;
+        self.total_depth += depth as usize;
+        self.max_depth = self.max_depth.max(depth);
+    }
+
+    pub fn mean_depth(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.total_depth as f64 / self.count as f64
+        }
+    }
+}
@@ -3,6 +3,7 @@ use rotonda_store::prelude::multi::PrefixStoreError;
 use rotonda_store::prelude::multi::Record;
 use rotonda_store::prelude::multi::RouteStatus;
 use rotonda_store::MatchOptions;
+use rotonda_store::{AutoBestPath, StoreConfig};
 use inetnum::asn::Asn;
 use routecore::bgp::aspath::HopPath;
 use routecore::bgp::path_attributes::BgpIdentifier;
@@ -101,7 +102,9 @@ fn test_best_path_1() -> Result<(), Box<dyn std::error::Error>> {
             include_withdrawn: false,
             include_less_specifics: false,
             include_more_specifics: false,
-            mui: None
+            mui: None,
+            debug_path: false,
+            stop_at_first_less_specific: false,
         },
         &rotonda_store::epoch::pin()
     );
@@ -127,4 +130,314 @@ fn test_best_path_1() -> Result<(), Box<dyn std::error::Error>> {
     assert_eq!(best_path.unwrap().unwrap().multi_uniq_id, 1);
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_best_path_for_mui() -> Result<(), Box<dyn std::error::Error>> {
+    crate::common::init();
+
+    let tree_bitmap = MultiThreadedStore::<Ipv4Route>::new()?;
+
+    let pfxs = [
+        Prefix::from_str("185.34.0.0/16")?,
+        Prefix::from_str("17.0.0.0/16")?,
+    ];
+    // This prefix does not have a record for mui 1.
+    let other_pfx = Prefix::from_str("33.0.0.0/16")?;
+
+    let mut pa_map = PaMap::empty();
+    pa_map.set::<LocalPref>(routecore::bgp::types::LocalPref(50));
+    pa_map.set::<Origin>(routecore::bgp::types::Origin(routecore::bgp::types::OriginType::Egp));
+
+    let mut asns_insert = vec![];
+    for mui in 1..4 {
+        let tbi = TiebreakerInfo::new(
+            RouteSource::Ebgp,
+            None,
+            Asn::from(65400),
+            BgpIdentifier::from([0; 4]),
+            std::net::IpAddr::V4(Ipv4Addr::new(192, 168, 12, mui as u8))
+        );
+        asns_insert.push(Asn::from(65400 + mui));
+        pa_map.set::<HopPath>(HopPath::from(asns_insert.clone()));
+        let rec = Record::new(mui, 0, RouteStatus::Active, Ipv4Route(mui, pa_map.clone(), tbi));
+
+        for pfx in &pfxs {
+            tree_bitmap.insert(pfx, rec.clone(), None)?;
+        }
+        if mui > 1 {
+            tree_bitmap.insert(&other_pfx, rec, None)?;
+        }
+    }
+
+    let guard = &rotonda_store::epoch::pin();
+    let count = tree_bitmap.recalculate_best_paths_for_mui(1, &(), guard)?;
+    assert_eq!(count, 2);
+
+    for pfx in &pfxs {
+        let best_path = tree_bitmap.best_path(pfx, guard);
+        assert_eq!(best_path.unwrap().unwrap().multi_uniq_id, 1);
+    }
+
+    let best_path = tree_bitmap.best_path(&other_pfx, guard);
+    assert_eq!(best_path.unwrap().err().unwrap(), PrefixStoreError::BestPathNotFound);
+
+    // The batch lookup returns the same, in the order of the input.
+    let unknown_pfx = Prefix::from_str("44.0.0.0/16")?;
+    let best_paths = tree_bitmap.best_paths(
+        &[pfxs[1], other_pfx, unknown_pfx, pfxs[0]],
+        guard
+    );
+    assert_eq!(best_paths.len(), 4);
+    assert_eq!(best_paths[0].as_ref().unwrap().as_ref().unwrap().multi_uniq_id, 1);
+    assert_eq!(best_paths[1].as_ref().unwrap().as_ref().err(), Some(&PrefixStoreError::BestPathNotFound));
+    assert!(best_paths[2].is_none());
+    assert_eq!(best_paths[3].as_ref().unwrap().as_ref().unwrap().multi_uniq_id, 1);
+
+    Ok(())
+}
+
+#[test]
+fn test_outdated_best_paths() -> Result<(), Box<dyn std::error::Error>> {
+    crate::common::init();
+
+    let tree_bitmap = MultiThreadedStore::<Ipv4Route>::new()?;
+
+    let pfx = Prefix::from_str("185.34.0.0/16")?;
+    let other_pfx = Prefix::from_str("17.0.0.0/16")?;
+
+    let mut pa_map = PaMap::empty();
+    pa_map.set::<LocalPref>(routecore::bgp::types::LocalPref(50));
+    pa_map.set::<Origin>(routecore::bgp::types::Origin(routecore::bgp::types::OriginType::Egp));
+    pa_map.set::<HopPath>(HopPath::from(vec![Asn::from(65400)]));
+
+    let tbi = TiebreakerInfo::new(
+        RouteSource::Ebgp,
+        None,
+        Asn::from(65400),
+        BgpIdentifier::from([0; 4]),
+        std::net::IpAddr::V4(Ipv4Addr::new(192, 168, 12, 1))
+    );
+
+    // Modifying the records of an existing prefix marks its best path as
+    // outdated.
+    for mui in 1..3 {
+        let rec = Record::new(mui, 0, RouteStatus::Active, Ipv4Route(mui, pa_map.clone(), tbi));
+        tree_bitmap.insert(&pfx, rec, None)?;
+    }
+    let rec = Record::new(1, 0, RouteStatus::Active, Ipv4Route(1, pa_map.clone(), tbi));
+    tree_bitmap.insert(&other_pfx, rec, None)?;
+
+    let guard = &rotonda_store::epoch::pin();
+    assert_eq!(tree_bitmap.outdated_best_paths(guard).collect::<Vec<_>>(), vec![pfx]);
+
+    for p in tree_bitmap.outdated_best_paths(guard).collect::<Vec<_>>() {
+        tree_bitmap.calculate_and_store_best_and_backup_path(&p, &(), guard)?;
+    }
+    assert_eq!(tree_bitmap.outdated_best_paths(guard).count(), 0);
+
+    // Re-inserting an identical record still outdates the best path, and
+    // recalculating an unchanged best path clears the outdated flag again.
+    let rec = Record::new(2, 0, RouteStatus::Active, Ipv4Route(2, pa_map.clone(), tbi));
+    tree_bitmap.insert(&pfx, rec, None)?;
+    assert!(tree_bitmap.is_ps_outdated(&pfx, guard)?);
+
+    tree_bitmap.calculate_and_store_best_and_backup_path(&pfx, &(), guard)?;
+    assert!(!tree_bitmap.is_ps_outdated(&pfx, guard)?);
+
+    Ok(())
+}
+
+#[test]
+fn test_auto_best_path() -> Result<(), Box<dyn std::error::Error>> {
+    crate::common::init();
+
+    let pfx = Prefix::from_str("185.34.0.0/16")?;
+    let other_pfx = Prefix::from_str("17.0.0.0/16")?;
+
+    let mut pa_map = PaMap::empty();
+    pa_map.set::<LocalPref>(routecore::bgp::types::LocalPref(50));
+    pa_map.set::<Origin>(routecore::bgp::types::Origin(routecore::bgp::types::OriginType::Egp));
+    pa_map.set::<HopPath>(HopPath::from(vec![Asn::from(65400)]));
+
+    let tbi = TiebreakerInfo::new(
+        RouteSource::Ebgp,
+        None,
+        Asn::from(65400),
+        BgpIdentifier::from([0; 4]),
+        std::net::IpAddr::V4(Ipv4Addr::new(192, 168, 12, 1))
+    );
+    let guard = &rotonda_store::epoch::pin();
+
+    // Deferred marks the best paths of both new and existing prefixes as
+    // outdated, without calculating them.
+    let tree_bitmap = MultiThreadedStore::<Ipv4Route>::new_with_config(StoreConfig {
+        auto_best_path: AutoBestPath::Deferred,
+        ..Default::default()
+    })?;
+    for mui in 1..3 {
+        let rec = Record::new(mui, 0, RouteStatus::Active, Ipv4Route(mui, pa_map.clone(), tbi));
+        tree_bitmap.insert(&pfx, rec, Some(()))?;
+    }
+    let rec = Record::new(1, 0, RouteStatus::Active, Ipv4Route(1, pa_map.clone(), tbi));
+    tree_bitmap.insert(&other_pfx, rec, Some(()))?;
+
+    assert_eq!(tree_bitmap.outdated_best_paths(guard).count(), 2);
+    assert_eq!(
+        tree_bitmap.best_path(&pfx, guard).unwrap().err(),
+        Some(PrefixStoreError::BestPathNotFound)
+    );
+
+    assert_eq!(tree_bitmap.recalculate_outdated_best_paths(&(), guard)?, 2);
+    assert_eq!(tree_bitmap.outdated_best_paths(guard).count(), 0);
+    assert!(tree_bitmap.best_path(&pfx, guard).unwrap().is_ok());
+    assert_eq!(
+        tree_bitmap.best_path(&other_pfx, guard).unwrap()?.multi_uniq_id,
+        1
+    );
+
+    // Off ignores the path selection argument of insert.
+    let tree_bitmap = MultiThreadedStore::<Ipv4Route>::new_with_config(StoreConfig {
+        auto_best_path: AutoBestPath::Off,
+        ..Default::default()
+    })?;
+    let rec = Record::new(1, 0, RouteStatus::Active, Ipv4Route(1, pa_map.clone(), tbi));
+    tree_bitmap.insert(&pfx, rec, Some(()))?;
+    assert_eq!(
+        tree_bitmap.best_path(&pfx, guard).unwrap().err(),
+        Some(PrefixStoreError::BestPathNotFound)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_ecmp_paths() -> Result<(), Box<dyn std::error::Error>> {
+    crate::common::init();
+
+    let tree_bitmap = MultiThreadedStore::<Ipv4Route>::new()?;
+
+    let pfx = Prefix::from_str("185.34.0.0/16")?;
+    let pfx_v6 = Prefix::from_str("2a04:b900::/29")?;
+    let unknown_pfx = Prefix::from_str("17.0.0.0/16")?;
+
+    let mut pa_map = PaMap::empty();
+    pa_map.set::<LocalPref>(routecore::bgp::types::LocalPref(50));
+    pa_map.set::<Origin>(routecore::bgp::types::Origin(routecore::bgp::types::OriginType::Egp));
+    pa_map.set::<HopPath>(HopPath::from(vec![Asn::from(65400)]));
+
+    let tbi = TiebreakerInfo::new(
+        RouteSource::Ebgp,
+        None,
+        Asn::from(65400),
+        BgpIdentifier::from([0; 4]),
+        std::net::IpAddr::V4(Ipv4Addr::new(192, 168, 12, 1))
+    );
+
+    // Mui 1 has a longer AS path, the records for muis 2, 3 and 4 are
+    // tied for the best path.
+    let mut longer_pa_map = pa_map.clone();
+    longer_pa_map.set::<HopPath>(
+        HopPath::from(vec![Asn::from(65400), Asn::from(65401)])
+    );
+    let rec = Record::new(1, 0, RouteStatus::Active, Ipv4Route(1, longer_pa_map, tbi));
+    tree_bitmap.insert(&pfx, rec.clone(), None)?;
+    tree_bitmap.insert(&pfx_v6, rec, None)?;
+    for mui in 2..5 {
+        let rec = Record::new(mui, 0, RouteStatus::Active, Ipv4Route(mui, pa_map.clone(), tbi));
+        tree_bitmap.insert(&pfx, rec.clone(), None)?;
+        tree_bitmap.insert(&pfx_v6, rec, None)?;
+    }
+
+    let guard = &rotonda_store::epoch::pin();
+    let ecmp_muis = |p: &Prefix| {
+        tree_bitmap.ecmp_paths(p, guard).map(|recs| {
+            recs.iter().map(|r| r.multi_uniq_id).collect::<Vec<_>>()
+        })
+    };
+
+    assert_eq!(ecmp_muis(&pfx), None);
+    assert_eq!(tree_bitmap.calculate_and_store_ecmp(&pfx, &(), guard)?, vec![2, 3, 4]);
+    assert_eq!(tree_bitmap.calculate_and_store_ecmp(&pfx_v6, &(), guard)?, vec![2, 3, 4]);
+    assert_eq!(ecmp_muis(&pfx), Some(vec![2, 3, 4]));
+    assert_eq!(ecmp_muis(&pfx_v6), Some(vec![2, 3, 4]));
+
+    assert_eq!(
+        tree_bitmap.calculate_and_store_ecmp(&unknown_pfx, &(), guard),
+        Err(PrefixStoreError::PrefixNotFound)
+    );
+    assert_eq!(ecmp_muis(&unknown_pfx), None);
+
+    // The best and backup path are left alone by the ECMP calculation,
+    // and the lowest mui of the tied records is the best path.
+    assert!(tree_bitmap.best_path(&pfx, guard).unwrap().is_err());
+    tree_bitmap.calculate_and_store_best_and_backup_path(&pfx, &(), guard)?;
+    assert_eq!(tree_bitmap.best_path(&pfx, guard).unwrap()?.multi_uniq_id, 2);
+    assert_eq!(ecmp_muis(&pfx), Some(vec![2, 3, 4]));
+
+    // Records that are no longer active are left out, but a tied record
+    // that is inserted later only shows up after recalculating.
+    tree_bitmap.mark_mui_as_withdrawn_for_prefix(&pfx, 3)?;
+    let rec = Record::new(5, 0, RouteStatus::Active, Ipv4Route(5, pa_map.clone(), tbi));
+    tree_bitmap.insert(&pfx, rec, None)?;
+    assert_eq!(ecmp_muis(&pfx), Some(vec![2, 4]));
+
+    tree_bitmap.calculate_and_store_ecmp(&pfx, &(), guard)?;
+    assert_eq!(ecmp_muis(&pfx), Some(vec![2, 4, 5]));
+    assert_eq!(ecmp_muis(&pfx_v6), Some(vec![2, 3, 4]));
+
+    Ok(())
+}
+
+#[test]
+fn test_best_path_with_tied_records() -> Result<(), Box<dyn std::error::Error>> {
+    crate::common::init();
+
+    let tree_bitmap = MultiThreadedStore::<Ipv4Route>::new()?;
+    let pfx = Prefix::from_str("185.34.0.0/16")?;
+
+    let mut pa_map = PaMap::empty();
+    pa_map.set::<LocalPref>(routecore::bgp::types::LocalPref(50));
+    pa_map.set::<Origin>(routecore::bgp::types::Origin(routecore::bgp::types::OriginType::Egp));
+    pa_map.set::<HopPath>(HopPath::from(vec![Asn::from(65400)]));
+
+    let tbi = TiebreakerInfo::new(
+        RouteSource::Ebgp,
+        None,
+        Asn::from(65400),
+        BgpIdentifier::from([0; 4]),
+        std::net::IpAddr::V4(Ipv4Addr::new(192, 168, 12, 1))
+    );
+
+    // Enough records to make it unlikely that the record map iterates over
+    // them in the order of their muis.
+    for mui in (1..=32).rev() {
+        let rec = Record::new(mui, 0, RouteStatus::Active, Ipv4Route(mui, pa_map.clone(), tbi));
+        tree_bitmap.insert(&pfx, rec, None)?;
+    }
+
+    let guard = &rotonda_store::epoch::pin();
+
+    // All records are tied, so the lowest muis win.
+    let no_preference = |_: &Record<Ipv4Route>, _: &Record<Ipv4Route>| std::cmp::Ordering::Equal;
+    for _ in 0..8 {
+        assert_eq!(
+            tree_bitmap.calculate_best_path_with(&pfx, &no_preference, guard)?,
+            (Some(1), Some(2))
+        );
+    }
+
+    // The records for muis 4, 8, .., 32 are tied for the best path.
+    let multiple_of_four = |a: &Record<Ipv4Route>, b: &Record<Ipv4Route>| {
+        (a.meta.0 % 4).cmp(&(b.meta.0 % 4))
+    };
+    for _ in 0..8 {
+        assert_eq!(
+            tree_bitmap.calculate_best_path_with(&pfx, &multiple_of_four, guard)?,
+            (Some(4), Some(8))
+        );
+    }
+    assert_eq!(tree_bitmap.best_path(&pfx, guard).unwrap()?.multi_uniq_id, 4);
+
+    Ok(())
+}
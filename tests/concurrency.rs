@@ -2,8 +2,8 @@ use std::{str::FromStr, sync::atomic::Ordering};
 
 use inetnum::{addr::Prefix, asn::Asn};
 use rotonda_store::{
-    prelude::multi::{Record, RouteStatus},
-    MatchOptions, MultiThreadedStore,
+    prelude::multi::{PrefixStoreError, Record, RouteStatus},
+    MatchOptions, MultiThreadedStore, StoreConfig,
 };
 
 mod common {
@@ -330,6 +330,8 @@ fn test_concurrent_updates_1() -> Result<(), Box<dyn std::error::Error>> {
         include_less_specifics: false,
         include_more_specifics: false,
         mui: None,
+        debug_path: false,
+        stop_at_first_less_specific: false,
     };
 
     for pfx in pfx_vec_2 {
@@ -605,6 +607,8 @@ fn test_concurrent_updates_2() -> Result<(), Box<dyn std::error::Error>> {
         include_less_specifics: false,
         include_more_specifics: false,
         mui: None,
+        debug_path: false,
+        stop_at_first_less_specific: false,
     };
 
     for pfx in wd_pfxs {
@@ -627,6 +631,8 @@ fn test_concurrent_updates_2() -> Result<(), Box<dyn std::error::Error>> {
         include_less_specifics: false,
         include_more_specifics: true,
         mui: None,
+        debug_path: false,
+        stop_at_first_less_specific: false,
     };
 
     let pfx = Prefix::from_str("0.0.0.0/0").unwrap();
@@ -646,3 +652,243 @@ fn test_concurrent_updates_2() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+fn test_concurrent_try_insert() -> Result<(), Box<dyn std::error::Error>> {
+    crate::common::init();
+
+    let pfxs = vec![
+        Prefix::from_str("0.0.0.0/0")?,
+        Prefix::from_str("185.36.0.0/16")?,
+        Prefix::from_str("185.36.16.0/24")?,
+        Prefix::from_str("2001:db8::/32")?,
+    ];
+
+    let tree_bitmap = std::sync::Arc::new(MultiThreadedStore::<Asn>::new()?);
+
+    // All threads race to insert the same (prefix, mui) combinations, only
+    // one of them may succeed for each.
+    let inserted: usize = (0..4_u32)
+        .map(|n| {
+            let tree_bitmap = tree_bitmap.clone();
+            let pfxs = pfxs.clone();
+
+            std::thread::Builder::new()
+                .name(n.to_string())
+                .spawn(move || {
+                    let mut inserted = 0;
+                    for pfx in pfxs.iter() {
+                        for mui in 1..=2 {
+                            match tree_bitmap.try_insert(
+                                pfx,
+                                Record::new(
+                                    mui,
+                                    n as u64,
+                                    RouteStatus::Active,
+                                    (65500 + n).into(),
+                                ),
+                            ) {
+                                Ok(_) => inserted += 1,
+                                Err(e) => assert_eq!(
+                                    e,
+                                    PrefixStoreError::RecordAlreadyExists
+                                ),
+                            }
+                        }
+                    }
+                    inserted
+                })
+                .unwrap()
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .map(|t| t.join().unwrap())
+        .sum();
+
+    assert_eq!(inserted, pfxs.len() * 2);
+
+    let match_options = MatchOptions {
+        match_type: rotonda_store::MatchType::ExactMatch,
+        include_withdrawn: false,
+        include_less_specifics: false,
+        include_more_specifics: false,
+        mui: None,
+        debug_path: false,
+        stop_at_first_less_specific: false,
+    };
+
+    let guard = rotonda_store::epoch::pin();
+    for pfx in pfxs.iter() {
+        let res = tree_bitmap.match_prefix(pfx, &match_options, &guard);
+        assert_eq!(res.prefix_meta.len(), 2);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_concurrent_insert_with_yield(
+) -> Result<(), Box<dyn std::error::Error>> {
+    crate::common::init();
+
+    // Yield on the first retry already, so that contention on the bitmaps
+    // of the nodes goes through the yielding path.
+    let tree_bitmap = std::sync::Arc::new(
+        MultiThreadedStore::<Asn>::new_with_config(StoreConfig {
+            max_retries_before_yield: 0,
+            ..Default::default()
+        })?,
+    );
+
+    // All threads insert into the same nodes, with different sub-prefixes.
+    let inserted: usize = (0..4_u32)
+        .map(|n| {
+            let tree_bitmap = tree_bitmap.clone();
+
+            std::thread::Builder::new()
+                .name(n.to_string())
+                .spawn(move || {
+                    let mut inserted = 0;
+                    for i in 0..64_u32 {
+                        let pfx = Prefix::new_relaxed(
+                            std::net::Ipv4Addr::from(
+                                0xb924_0000 | (i * 4 + n) << 8,
+                            )
+                            .into(),
+                            24,
+                        )
+                        .unwrap();
+                        let report = tree_bitmap
+                            .insert(
+                                &pfx,
+                                Record::new(
+                                    1,
+                                    0,
+                                    RouteStatus::Active,
+                                    (65500 + n).into(),
+                                ),
+                                None,
+                            )
+                            .unwrap();
+                        assert!(report.backoff_count <= report.cas_count);
+                        inserted += 1;
+                    }
+                    inserted
+                })
+                .unwrap()
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .map(|t| t.join().unwrap())
+        .sum();
+
+    assert_eq!(inserted, 256);
+    assert_eq!(tree_bitmap.prefixes_v4_count_for_len(24), 256);
+
+    Ok(())
+}
+
+#[test]
+fn test_concurrent_mui_index() -> Result<(), Box<dyn std::error::Error>> {
+    crate::common::init();
+
+    let tree_bitmap = std::sync::Arc::new(MultiThreadedStore::<Asn>::new()?);
+    let cover = Prefix::from_str("185.36.0.0/16")?;
+
+    // Every thread inserts for its own mui, into the same nodes, so that
+    // the bitmap indexes of these nodes are updated concurrently with
+    // different muis.
+    let _: Vec<_> = (0..8_u32)
+        .map(|n| {
+            let tree_bitmap = tree_bitmap.clone();
+
+            std::thread::Builder::new()
+                .name(n.to_string())
+                .spawn(move || {
+                    let record = Record::new(
+                        n + 1,
+                        0,
+                        RouteStatus::Active,
+                        (65500 + n).into(),
+                    );
+                    tree_bitmap.insert(&cover, record.clone(), None).unwrap();
+                    for i in 0..32_u32 {
+                        let pfx = Prefix::new_relaxed(
+                            std::net::Ipv4Addr::from(
+                                0xb924_0000 | (i * 8 + n) << 8,
+                            )
+                            .into(),
+                            24,
+                        )
+                        .unwrap();
+                        tree_bitmap
+                            .insert(&pfx, record.clone(), None)
+                            .unwrap();
+                    }
+                })
+                .unwrap()
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .map(|t| t.join().unwrap())
+        .collect();
+
+    // The bitmap index of the node that hosts the covering prefix has all
+    // the muis.
+    #[cfg(feature = "debug-internals")]
+    assert_eq!(
+        tree_bitmap.inspect_node(&cover).unwrap().muis,
+        (1..=8_u32).collect()
+    );
+
+    // The more-specifics for a mui are only found if the mui made it into
+    // the bitmap index of every node on their path, so a lost update would
+    // drop them from the result.
+    let guard = rotonda_store::epoch::pin();
+    for n in 0..8_u32 {
+        let res = tree_bitmap.match_prefix(
+            &cover,
+            &MatchOptions {
+                match_type: rotonda_store::MatchType::ExactMatch,
+                include_withdrawn: false,
+                include_less_specifics: false,
+                include_more_specifics: true,
+                mui: Some(n + 1),
+                debug_path: false,
+                stop_at_first_less_specific: false,
+            },
+            &guard,
+        );
+        assert_eq!(res.prefix_meta.len(), 1);
+
+        let more_specifics = res.more_specifics.unwrap();
+        assert_eq!(more_specifics.len(), 32);
+        for rec in more_specifics.iter() {
+            let third_octet = match rec.prefix.addr() {
+                std::net::IpAddr::V4(addr) => addr.octets()[2] as u32,
+                _ => unreachable!(),
+            };
+            assert_eq!(third_octet % 8, n);
+            assert_eq!(rec.meta.len(), 1);
+            assert_eq!(rec.meta[0].multi_uniq_id, n + 1);
+        }
+    }
+
+    // A mui that was never inserted isn't in any bitmap index.
+    let res = tree_bitmap.match_prefix(
+        &cover,
+        &MatchOptions {
+            match_type: rotonda_store::MatchType::ExactMatch,
+            include_withdrawn: false,
+            include_less_specifics: false,
+            include_more_specifics: true,
+            mui: Some(100),
+            debug_path: false,
+            stop_at_first_less_specific: false,
+        },
+        &guard,
+    );
+    assert!(res.more_specifics.map_or(true, |ms| ms.is_empty()));
+
+    Ok(())
+}
@@ -104,7 +104,9 @@ mod tests {
                         include_withdrawn: false,
                         include_less_specifics: false,
                         include_more_specifics: false,
-                        mui: None
+                        mui: None,
+                        debug_path: false,
+                        stop_at_first_less_specific: false,
                     },
                     guard
                 );
@@ -143,7 +145,9 @@ mod tests {
                                 include_withdrawn: false,
                                 include_less_specifics: false,
                                 include_more_specifics: false,
-                                mui: None
+                                mui: None,
+                                debug_path: false,
+                                stop_at_first_less_specific: false,
                             },
                             guard,
                         );
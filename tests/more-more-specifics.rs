@@ -60,6 +60,8 @@ mod tests {
                     include_less_specifics: false,
                     include_more_specifics: true,
                     mui: None,
+                    debug_path: false,
+                    stop_at_first_less_specific: false,
                 },
                 guard
             );
@@ -137,7 +139,9 @@ mod tests {
                     include_withdrawn: false,
                     include_less_specifics: false,
                     include_more_specifics: true,
-                    mui: None
+                    mui: None,
+                    debug_path: false,
+                    stop_at_first_less_specific: false,
                 },
                 guard
             );
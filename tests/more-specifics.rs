@@ -189,7 +189,9 @@ mod tests {
                     include_withdrawn: false,
                     include_less_specifics: false,
                     include_more_specifics: true,
-                    mui: None
+                    mui: None,
+                    debug_path: false,
+                    stop_at_first_less_specific: false,
                 },
                 guard
             );
@@ -212,4 +214,120 @@ mod tests {
         }
         Ok(())
     }
+
+    // Prefixes of all lengths from /8 up to /32 below a few addresses, so
+    // that there are more-specifics that do and that don't start on a
+    // stride boundary.
+    fn more_specifics_test_prefixes() -> Result<Vec<Prefix>, Box<dyn Error>> {
+        let mut pfxs = vec![];
+        for addr in [
+            std::net::Ipv4Addr::new(10, 0, 0, 0),
+            std::net::Ipv4Addr::new(10, 0, 0, 255),
+            std::net::Ipv4Addr::new(10, 96, 17, 3),
+            std::net::Ipv4Addr::new(10, 255, 255, 255),
+            std::net::Ipv4Addr::new(11, 170, 85, 128),
+        ] {
+            for len in 8..=32 {
+                pfxs.push(Prefix::new_relaxed(addr.into(), len)?);
+            }
+        }
+        pfxs.sort();
+        pfxs.dedup();
+        Ok(pfxs)
+    }
+
+    // The more-specifics of a prefix that doesn't end on a stride boundary
+    // live in the child nodes whose nibble starts with the remaining bits
+    // of the prefix. Compare the iterator with a filter of all the
+    // prefixes for search prefixes of every length.
+    #[test]
+    fn test_more_specifics_iter_from_any_length() -> Result<(), Box<dyn Error>>
+    {
+        let tree_bitmap = MultiThreadedStore::<PrefixAs>::new()?;
+        let pfxs = more_specifics_test_prefixes()?;
+        for pfx in &pfxs {
+            tree_bitmap.insert(
+                pfx,
+                Record::new(1, 0, RouteStatus::Active, PrefixAs(1)),
+                None,
+            )?;
+        }
+
+        let guard = &epoch::pin();
+        for search_pfx in pfxs.iter().chain(&[
+            Prefix::new_relaxed(
+                std::net::Ipv4Addr::new(0, 0, 0, 0).into(),
+                0,
+            )?,
+            Prefix::new_relaxed(
+                std::net::Ipv4Addr::new(10, 0, 0, 0).into(),
+                7,
+            )?,
+        ]) {
+            let mut found = tree_bitmap
+                .more_specifics_iter_from(search_pfx, None, false, guard)
+                .map(|p| p.prefix)
+                .collect::<Vec<_>>();
+            found.sort();
+            let expected = pfxs
+                .iter()
+                .filter(|p| {
+                    p.len() > search_pfx.len() && search_pfx.covers(**p)
+                })
+                .copied()
+                .collect::<Vec<_>>();
+            assert_eq!(found, expected, "more-specifics of {}", search_pfx);
+        }
+
+        Ok(())
+    }
+
+    // With a mui, a prefix without a record for it is skipped, but the
+    // other prefixes in the same node are still returned.
+    #[test]
+    fn test_more_specifics_iter_from_for_mui() -> Result<(), Box<dyn Error>> {
+        let tree_bitmap = MultiThreadedStore::<PrefixAs>::new()?;
+        let pfxs = more_specifics_test_prefixes()?;
+        for (i, pfx) in pfxs.iter().enumerate() {
+            let mui = i as u32 % 3 + 1;
+            tree_bitmap.insert(
+                pfx,
+                Record::new(mui, 0, RouteStatus::Active, PrefixAs(mui)),
+                None,
+            )?;
+        }
+
+        let guard = &epoch::pin();
+        for mui in 1..=3 {
+            for search_pfx in &pfxs {
+                let mut found = tree_bitmap
+                    .more_specifics_iter_from(
+                        search_pfx,
+                        Some(mui),
+                        false,
+                        guard,
+                    )
+                    .map(|p| p.prefix)
+                    .collect::<Vec<_>>();
+                found.sort();
+                let expected = pfxs
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, p)| {
+                        *i as u32 % 3 + 1 == mui
+                            && p.len() > search_pfx.len()
+                            && search_pfx.covers(**p)
+                    })
+                    .map(|(_, p)| *p)
+                    .collect::<Vec<_>>();
+                assert_eq!(
+                    found, expected,
+                    "more-specifics of {} for mui {}",
+                    search_pfx, mui
+                );
+            }
+        }
+
+        Ok(())
+    }
 }
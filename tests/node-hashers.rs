@@ -0,0 +1,128 @@
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+use inetnum::addr::Prefix;
+use rotonda_store::{
+    meta_examples::PrefixAs, prelude::multi::*, prelude::*, MatchOptions,
+    MatchType,
+};
+
+mod common {
+    use std::io::Write;
+
+    pub fn init() {
+        let _ = env_logger::builder()
+            .format(|buf, record| writeln!(buf, "{}", record.args()))
+            .is_test(true)
+            .try_init();
+    }
+}
+
+#[stride_sizes((IPv4, [4, 4, 4, 4, 4, 4, 4, 4]))]
+struct HashedTree;
+
+// A hasher that mirrors the slice of bits for a level, so that the
+// indexes are unique for node ids that share the bits above it, as
+// required by NodeHasher, but differ from the DefaultNodeHasher.
+struct MirrorNodeHasher;
+
+impl NodeHasher for MirrorNodeHasher {
+    fn hash_node_id<AF: AddressFamily>(
+        id: StrideNodeId<AF>,
+        last_level: u8,
+        this_level: u8,
+    ) -> usize {
+        let mask = (1 << (this_level - last_level)) - 1;
+        mask - DefaultNodeHasher::hash_node_id(id, last_level, this_level)
+    }
+}
+
+fn check_node_hasher<H: NodeHasher>(
+    pfxs: &[Prefix],
+) -> Result<ChainDepths, Box<dyn std::error::Error>> {
+    let tree = HashedTree::<PrefixAs, H>::new()?;
+    for (i, pfx) in pfxs.iter().enumerate() {
+        tree.insert(
+            PrefixId::from(*pfx),
+            Record::new(1, 0, RouteStatus::Active, PrefixAs(i as u32)),
+            None,
+        )?;
+    }
+
+    let options = MatchOptions {
+        match_type: MatchType::ExactMatch,
+        include_withdrawn: false,
+        include_less_specifics: false,
+        include_more_specifics: false,
+        mui: None,
+        debug_path: false,
+        stop_at_first_less_specific: false,
+    };
+    let guard = &epoch::pin();
+    for (i, pfx) in pfxs.iter().enumerate() {
+        let res = tree.match_prefix_by_store_direct(
+            PrefixId::from(*pfx),
+            &options,
+            None,
+            guard,
+        );
+        assert_eq!(res.prefix, Some(*pfx));
+        assert_eq!(res.prefix_meta[0].meta, PrefixAs(i as u32));
+    }
+
+    let all = tree.more_specifics_from(
+        PrefixId::from(Prefix::from_str("0.0.0.0/0")?),
+        None,
+        false,
+        guard,
+    );
+    assert_eq!(all.more_specifics.map(|m| m.len()), Some(pfxs.len()));
+
+    let less_specifics = tree.less_specifics_from(
+        PrefixId::from(Prefix::from_str("10.1.2.3/32")?),
+        None,
+        false,
+        guard,
+    );
+    let mut less_specifics = less_specifics
+        .less_specifics
+        .unwrap()
+        .iter()
+        .map(|r| r.prefix)
+        .collect::<Vec<_>>();
+    less_specifics.sort_by_key(|pfx| pfx.len());
+    assert_eq!(
+        less_specifics,
+        vec![
+            Prefix::from_str("10.0.0.0/8")?,
+            Prefix::from_str("10.1.0.0/16")?,
+            Prefix::from_str("10.1.2.0/24")?,
+        ]
+    );
+
+    Ok(tree.store.chain_depths())
+}
+
+#[test]
+fn test_node_hashers() -> Result<(), Box<dyn std::error::Error>> {
+    crate::common::init();
+
+    let mut pfxs = vec![Prefix::from_str("10.0.0.0/8")?];
+    for a in 0..16_u8 {
+        pfxs.push(Prefix::new_relaxed([10, a, 0, 0].into(), 16)?);
+        for b in 0..16_u8 {
+            pfxs.push(Prefix::new_relaxed([10, a, b, 0].into(), 24)?);
+            pfxs.push(Prefix::new_relaxed([10, a, b, b].into(), 32)?);
+        }
+    }
+    pfxs.push(Prefix::from_str("10.1.2.3/32")?);
+
+    let default = check_node_hasher::<DefaultNodeHasher>(&pfxs)?;
+    let mirror = check_node_hasher::<MirrorNodeHasher>(&pfxs)?;
+
+    // A valid hasher only permutes the slots within a bucket, so the
+    // chain depths of the nodes are the same for all of them.
+    assert_eq!(default.nodes, mirror.nodes);
+
+    Ok(())
+}
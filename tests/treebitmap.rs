@@ -48,6 +48,8 @@ mod tests {
                 include_less_specifics: true,
                 include_more_specifics: false,
                 mui: None,
+                debug_path: false,
+                stop_at_first_less_specific: false,
             },
             guard,
         );
@@ -81,6 +83,8 @@ mod tests {
                 include_less_specifics: true,
                 include_more_specifics: false,
                 mui: None,
+                debug_path: false,
+                stop_at_first_less_specific: false,
             },
             guard,
         );
@@ -343,6 +347,8 @@ mod tests {
                     include_less_specifics: false,
                     include_more_specifics: false,
                     mui: None,
+                    debug_path: false,
+                    stop_at_first_less_specific: false,
                 },
                 guard,
             );
@@ -358,6 +364,8 @@ mod tests {
                 include_less_specifics: true,
                 include_more_specifics: false,
                 mui: None,
+                debug_path: false,
+                stop_at_first_less_specific: false,
             },
             guard,
         );
@@ -438,6 +446,8 @@ mod tests {
                             include_less_specifics: false,
                             include_more_specifics: false,
                             mui: None,
+                            debug_path: false,
+                            stop_at_first_less_specific: false,
                         },
                         guard,
                     );
@@ -506,6 +516,8 @@ mod tests {
                                 include_less_specifics: false,
                                 include_more_specifics: false,
                                 mui: Some(mui),
+                                debug_path: false,
+                                stop_at_first_less_specific: false,
                             },
                             guard,
                         );
@@ -552,6 +564,8 @@ mod tests {
                 include_less_specifics: false,
                 include_more_specifics: false,
                 mui: None,
+                debug_path: false,
+                stop_at_first_less_specific: false,
             },
             guard,
         );
@@ -564,6 +578,14 @@ mod tests {
             .collect::<Vec<_>>();
         assert_eq!(wd_rec.len(), 1);
         assert_eq!(wd_rec[0].multi_uniq_id, 1);
+        // Withdrawn through the global status, not by its own status.
+        assert_eq!(wd_rec[0].local_status, RouteStatus::Active);
+        assert!(wd_rec[0].is_globally_withdrawn());
+        assert!(all_recs_for_pfx
+            .prefix_meta
+            .iter()
+            .filter(|r| r.multi_uniq_id != 1)
+            .all(|r| !r.is_globally_withdrawn()));
 
         let active_recs_for_pfx = tree_bitmap.match_prefix(
             &Prefix::from_str("1.0.0.0/16")?,
@@ -573,6 +595,8 @@ mod tests {
                 include_less_specifics: false,
                 include_more_specifics: false,
                 mui: None,
+                debug_path: false,
+                stop_at_first_less_specific: false,
             },
             guard,
         );
@@ -651,6 +675,8 @@ mod tests {
                 include_less_specifics: false,
                 include_more_specifics: true,
                 mui: None,
+                debug_path: false,
+                stop_at_first_less_specific: false,
             },
             guard,
         );
@@ -689,6 +715,8 @@ mod tests {
                 include_less_specifics: false,
                 include_more_specifics: true,
                 mui: None,
+                debug_path: false,
+                stop_at_first_less_specific: false,
             },
             guard,
         );
@@ -729,6 +757,8 @@ mod tests {
                 include_less_specifics: false,
                 include_more_specifics: true,
                 mui: None,
+                debug_path: false,
+                stop_at_first_less_specific: false,
             },
             guard,
         );
@@ -779,6 +809,8 @@ mod tests {
                 include_less_specifics: false,
                 include_more_specifics: true,
                 mui: None,
+                debug_path: false,
+                stop_at_first_less_specific: false,
             },
             guard,
         );
@@ -828,6 +860,8 @@ mod tests {
                 include_less_specifics: true,
                 include_more_specifics: false,
                 mui: None,
+                debug_path: false,
+                stop_at_first_less_specific: false,
             },
             guard,
         );
@@ -852,6 +886,8 @@ mod tests {
                 include_less_specifics: true,
                 include_more_specifics: false,
                 mui: None,
+                debug_path: false,
+                stop_at_first_less_specific: false,
             },
             guard,
         );
@@ -868,4 +904,2000 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_with_prefix_records() -> Result<(), Box<dyn std::error::Error>> {
+        crate::common::init();
+
+        let tree_bitmap = MultiThreadedStore::<PrefixAs>::new()?;
+        let pfx = Prefix::from_str("185.34.0.0/16")?;
+
+        for mui in [1, 2] {
+            tree_bitmap.insert(
+                &pfx,
+                Record::new(mui, 0, RouteStatus::Active, PrefixAs(65000)),
+                None,
+            )?;
+        }
+
+        // Read-modify-write the records: bump the ASN of every record.
+        let replaced = tree_bitmap.with_prefix_records(&pfx, |view| {
+            let mut replaced = 0;
+            for mut rec in view.records() {
+                rec.meta = PrefixAs(rec.meta.0 + rec.multi_uniq_id);
+                if view.replace(rec).is_some() {
+                    replaced += 1;
+                }
+            }
+            // There's no record for mui 3, so this does nothing.
+            assert!(view
+                .replace(Record::new(
+                    3,
+                    0,
+                    RouteStatus::Active,
+                    PrefixAs(65003)
+                ))
+                .is_none());
+            assert!(view.set_status(2, RouteStatus::Withdrawn));
+            replaced
+        })?;
+        assert_eq!(replaced, 2);
+
+        let guard = &epoch::pin();
+        let res = tree_bitmap.match_prefix(
+            &pfx,
+            &MatchOptions {
+                match_type: MatchType::ExactMatch,
+                include_withdrawn: true,
+                include_less_specifics: false,
+                include_more_specifics: false,
+                mui: None,
+                debug_path: false,
+                stop_at_first_less_specific: false,
+            },
+            guard,
+        );
+        let mut recs = res.prefix_meta;
+        recs.sort_by_key(|r| r.multi_uniq_id);
+        assert_eq!(recs.len(), 2);
+        assert_eq!(recs[0].meta, PrefixAs(65001));
+        assert_eq!(recs[0].status, RouteStatus::Active);
+        assert_eq!(recs[1].meta, PrefixAs(65002));
+        assert_eq!(recs[1].status, RouteStatus::Withdrawn);
+
+        assert!(tree_bitmap.is_ps_outdated(&pfx, guard)?);
+
+        assert_eq!(
+            tree_bitmap.with_prefix_records(
+                &Prefix::from_str("17.0.0.0/16")?,
+                |view| view.len()
+            ),
+            Err(PrefixStoreError::PrefixNotFound)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compact_memory() -> Result<(), Box<dyn std::error::Error>> {
+        crate::common::init();
+
+        let tree_bitmap = MultiThreadedStore::<PrefixAs>::new()?;
+        for pfx in ["185.34.0.0/16", "17.0.0.0/16", "33.0.0.0/16"] {
+            for mui in 0..20 {
+                tree_bitmap.insert(
+                    &Prefix::from_str(pfx)?,
+                    Record::new(mui, 0, RouteStatus::Active, PrefixAs(mui)),
+                    None,
+                )?;
+            }
+        }
+
+        let stats = tree_bitmap.compact_memory();
+        assert_eq!(stats.prefixes, 3);
+
+        // Removing records leaves the capacity of the record maps as it
+        // was, so compacting shrinks all of them.
+        assert_eq!(tree_bitmap.retain(|_, rec| rec.multi_uniq_id < 2), 54);
+        let stats = tree_bitmap.compact_memory();
+        assert_eq!(stats.prefixes, 3);
+        assert_eq!(stats.shrunk_maps, 3);
+        assert!(stats.reclaimed_bytes > 0);
+
+        // Everything fits already, so there's nothing left to reclaim.
+        let stats = tree_bitmap.compact_memory();
+        assert_eq!(stats.prefixes, 3);
+        assert_eq!(stats.shrunk_maps, 0);
+        assert_eq!(stats.reclaimed_bytes, 0);
+
+        assert_eq!(tree_bitmap.prefixes_count(), 3);
+        assert_eq!(tree_bitmap.records_count(), 6);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "debug-internals")]
+    #[test]
+    fn test_inspect_node() -> Result<(), Box<dyn std::error::Error>> {
+        crate::common::init();
+
+        let tree_bitmap = MultiThreadedStore::<PrefixAs>::new()?;
+        let pfx = Prefix::from_str("185.34.0.0/16")?;
+        for mui in [1, 2] {
+            tree_bitmap.insert(
+                &pfx,
+                Record::new(mui, 0, RouteStatus::Active, PrefixAs(65000)),
+                None,
+            )?;
+        }
+
+        // With the default strides (5, 5, 4, 3, ..) a /16 lives in the
+        // stride 3 node for 185.32.0.0/14.
+        let node = tree_bitmap.inspect_node(&pfx).unwrap();
+        assert_eq!(node.node_id, Prefix::from_str("185.32.0.0/14")?);
+        assert_eq!(node.stride, 3);
+        assert_ne!(node.pfxbitarr, 0);
+        assert_eq!(node.ptrbitarr, 0);
+        assert_eq!(node.muis.iter().collect::<Vec<_>>(), vec![1, 2]);
+
+        assert!(tree_bitmap
+            .inspect_node(&Prefix::from_str("17.0.0.0/16")?)
+            .is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_upsert_outcome() -> Result<(), Box<dyn std::error::Error>> {
+        crate::common::init();
+
+        let tree_bitmap = MultiThreadedStore::<PrefixAs>::new()?;
+        let pfx = Prefix::from_str("185.34.0.0/16")?;
+
+        let report = tree_bitmap.insert(
+            &pfx,
+            Record::new(1, 0, RouteStatus::Active, PrefixAs(65001)),
+            None,
+        )?;
+        assert!(report.prefix_new);
+        assert_eq!(report.outcome, UpsertOutcome::Inserted);
+
+        // A new mui for an existing prefix is an insert as well.
+        let report = tree_bitmap.insert(
+            &pfx,
+            Record::new(2, 0, RouteStatus::Active, PrefixAs(65002)),
+            None,
+        )?;
+        assert!(!report.prefix_new);
+        assert_eq!(report.outcome, UpsertOutcome::Inserted);
+
+        let report = tree_bitmap.insert(
+            &pfx,
+            Record::new(1, 0, RouteStatus::Withdrawn, PrefixAs(65001)),
+            None,
+        )?;
+        assert!(!report.prefix_new);
+        assert_eq!(report.outcome, UpsertOutcome::Replaced);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ignore_withdrawal_of_unknown(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        crate::common::init();
+
+        let tree_bitmap =
+            MultiThreadedStore::<PrefixAs>::new_with_config(StoreConfig {
+                ignore_withdrawal_of_unknown: true,
+                ..Default::default()
+            })?;
+        let pfx = Prefix::from_str("185.34.0.0/16")?;
+        let withdrawal =
+            Record::new(1, 0, RouteStatus::Withdrawn, PrefixAs(65001));
+
+        // The prefix doesn't exist, so nothing happens.
+        let report = tree_bitmap.insert(&pfx, withdrawal.clone(), None)?;
+        assert_eq!(report.outcome, UpsertOutcome::NoOp);
+        assert_eq!(report.mui_count, 0);
+        assert_eq!(tree_bitmap.prefixes_count(), 0);
+        assert!(tree_bitmap.prefixes_iter().next().is_none());
+
+        // The prefix exists, but not for this mui.
+        tree_bitmap.insert(
+            &pfx,
+            Record::new(2, 0, RouteStatus::Active, PrefixAs(65002)),
+            None,
+        )?;
+        let report = tree_bitmap.insert(&pfx, withdrawal.clone(), None)?;
+        assert_eq!(report.outcome, UpsertOutcome::NoOp);
+        assert_eq!(report.mui_count, 1);
+        assert_eq!(tree_bitmap.records_count(), 1);
+
+        // A withdrawal for a known (prefix, mui) goes through.
+        tree_bitmap.insert(
+            &pfx,
+            Record::new(1, 0, RouteStatus::Active, PrefixAs(65001)),
+            None,
+        )?;
+        let report = tree_bitmap.insert(&pfx, withdrawal, None)?;
+        assert_eq!(report.outcome, UpsertOutcome::Replaced);
+        assert_eq!(tree_bitmap.records_count(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_store_config() {
+        crate::common::init();
+
+        assert!(StoreConfig::default().validate().is_ok());
+        assert!(StoreConfig {
+            accepted_len_range_v4: Some((8, 32)),
+            accepted_len_range_v6: Some((0, 128)),
+            ..Default::default()
+        }
+        .validate()
+        .is_ok());
+
+        for config in [
+            StoreConfig {
+                accepted_len_range_v4: Some((24, 8)),
+                ..Default::default()
+            },
+            StoreConfig {
+                accepted_len_range_v4: Some((8, 33)),
+                ..Default::default()
+            },
+            StoreConfig {
+                accepted_len_range_v6: Some((16, 129)),
+                ..Default::default()
+            },
+        ] {
+            assert!(matches!(
+                config.validate(),
+                Err(PrefixStoreError::InvalidStoreConfig(_))
+            ));
+            assert!(MultiThreadedStore::<PrefixAs>::new_with_config(config)
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn test_count_more_specifics() -> Result<(), Box<dyn std::error::Error>> {
+        crate::common::init();
+
+        let tree_bitmap = MultiThreadedStore::<PrefixAs>::new()?;
+        let guard = &epoch::pin();
+
+        for (pfx, mui) in [
+            ("185.49.140.0/22", 1),
+            ("185.49.140.0/24", 1),
+            ("185.49.141.0/24", 2),
+            ("185.49.142.128/25", 1),
+            ("185.49.144.0/24", 1),
+        ] {
+            tree_bitmap.insert(
+                &Prefix::from_str(pfx)?,
+                Record::new(mui, 0, RouteStatus::Active, PrefixAs(65001)),
+                None,
+            )?;
+        }
+
+        // The search prefixes start at different positions within the
+        // strides of the nodes that host them.
+        for (pfx, all, mui_1) in [
+            ("185.49.0.0/16", 5, 4),
+            ("185.49.128.0/19", 5, 4),
+            ("185.49.136.0/21", 4, 3),
+            ("185.49.140.0/22", 3, 2),
+            ("185.49.140.0/23", 2, 1),
+            ("185.49.142.0/23", 1, 1),
+            ("185.49.142.0/24", 1, 1),
+            ("185.49.143.0/24", 0, 0),
+        ] {
+            let pfx = Prefix::from_str(pfx)?;
+            assert_eq!(
+                tree_bitmap.count_more_specifics(&pfx, None, guard),
+                all
+            );
+            assert_eq!(
+                tree_bitmap.count_more_specifics(&pfx, Some(1), guard),
+                mui_1
+            );
+            assert_eq!(
+                tree_bitmap
+                    .more_specifics_iter_from(&pfx, None, false, guard)
+                    .count(),
+                all
+            );
+        }
+
+        Ok(())
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct BytesMeta(Vec<u8>);
+
+    impl std::fmt::Display for BytesMeta {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "{:?}", self.0)
+        }
+    }
+
+    impl Meta for BytesMeta {
+        type Orderable<'a> = &'a [u8];
+        type TBI = ();
+
+        fn as_orderable(&self, _tbi: Self::TBI) -> &[u8] {
+            &self.0
+        }
+
+        fn intern_key(&self) -> Option<&[u8]> {
+            Some(&self.0)
+        }
+    }
+
+    impl AsRef<[u8]> for BytesMeta {
+        fn as_ref(&self) -> &[u8] {
+            &self.0
+        }
+    }
+
+    impl From<Vec<u8>> for BytesMeta {
+        fn from(value: Vec<u8>) -> Self {
+            BytesMeta(value)
+        }
+    }
+
+    #[test]
+    fn test_dump_and_load_binary() -> Result<(), Box<dyn std::error::Error>> {
+        crate::common::init();
+
+        let tree_bitmap = MultiThreadedStore::<BytesMeta>::new()?;
+        for (pfx, mui, ltime, status, meta) in [
+            ("0.0.0.0/0", 1, 10, RouteStatus::Active, vec![]),
+            ("185.34.0.0/16", 1, 20, RouteStatus::Active, vec![1, 2, 3]),
+            ("185.34.0.0/16", 2, 30, RouteStatus::Withdrawn, vec![4]),
+            ("185.34.10.0/24", 3, 40, RouteStatus::Stale, vec![5; 300]),
+            ("2a04:b900::/29", 1, 50, RouteStatus::InActive, vec![6, 7]),
+        ] {
+            tree_bitmap.insert(
+                &Prefix::from_str(pfx)?,
+                Record::new(mui, ltime, status, BytesMeta(meta)),
+                None,
+            )?;
+        }
+        tree_bitmap.mark_mui_as_withdrawn_v4(3)?;
+
+        let mut dump = vec![];
+        tree_bitmap.dump_binary(&mut dump)?;
+
+        let restored = MultiThreadedStore::<BytesMeta>::load_binary(
+            StoreConfig::default(),
+            &mut dump.as_slice(),
+        )?;
+
+        assert_eq!(restored.exact_prefixes_count(), 4);
+        let default_route = restored.default_route_v4(&epoch::pin()).unwrap();
+        assert_eq!(default_route.len(), 1);
+        assert_eq!(default_route[0].ltime, 10);
+        assert!(restored.mui_is_withdrawn_v4(3));
+        assert!(!restored.mui_is_withdrawn_v6(3));
+        for (pfx, records) in
+            tree_bitmap.prefixes_iter().map(|p| (p.prefix, p.meta))
+        {
+            let mut restored_records = restored
+                .prefixes_iter()
+                .find(|p| p.prefix == pfx)
+                .unwrap()
+                .meta;
+            restored_records.sort_by_key(|r| r.multi_uniq_id);
+            let mut records = records;
+            records.sort_by_key(|r| r.multi_uniq_id);
+            assert_eq!(records.len(), restored_records.len());
+            for (r, rr) in records.iter().zip(restored_records.iter()) {
+                assert_eq!(r.multi_uniq_id, rr.multi_uniq_id);
+                assert_eq!(r.ltime, rr.ltime);
+                assert_eq!(r.status, rr.status);
+                assert_eq!(r.meta, rr.meta);
+            }
+        }
+
+        // A truncated dump, or a config that doesn't accept the prefixes,
+        // are errors.
+        assert!(MultiThreadedStore::<BytesMeta>::load_binary(
+            StoreConfig::default(),
+            &mut &dump[..dump.len() - 1],
+        )
+        .is_err());
+        assert!(MultiThreadedStore::<BytesMeta>::load_binary(
+            StoreConfig {
+                address_families: AfSet::Ipv4Only,
+                ..Default::default()
+            },
+            &mut dump.as_slice(),
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregate() -> Result<(), Box<dyn std::error::Error>> {
+        crate::common::init();
+
+        let tree_bitmap = MultiThreadedStore::<PrefixAs>::new()?;
+        let guard = &epoch::pin();
+
+        for (pfx, asn) in [
+            ("192.0.0.0/22", 65001),
+            // Covered by the /22 with the same AS, absorbed.
+            ("192.0.1.0/24", 65001),
+            // Covered by the /22 with another AS, kept.
+            ("192.0.2.0/24", 65002),
+            // Siblings that merge into 192.0.4.0/23, which merges with the
+            // /23 below into 192.0.4.0/22, which in turn merges with the
+            // first /22 into 192.0.0.0/21.
+            ("192.0.4.0/24", 65001),
+            ("192.0.5.0/24", 65001),
+            ("192.0.6.0/23", 65001),
+            // Its sibling 192.0.8.0/24 has another AS, so it isn't merged.
+            ("192.0.9.0/24", 65001),
+            ("192.0.8.0/24", 65003),
+            // Not a more-specific of the root.
+            ("198.51.100.0/24", 65001),
+        ] {
+            tree_bitmap.insert(
+                &Prefix::from_str(pfx)?,
+                Record::new(1, 0, RouteStatus::Active, PrefixAs(asn)),
+                None,
+            )?;
+        }
+
+        let aggs = tree_bitmap.aggregate(
+            &Prefix::from_str("192.0.0.0/16")?,
+            |a, b| a == b,
+            guard,
+        );
+
+        let mut expected = vec![
+            Prefix::from_str("192.0.0.0/21")?,
+            Prefix::from_str("192.0.2.0/24")?,
+            Prefix::from_str("192.0.8.0/24")?,
+            Prefix::from_str("192.0.9.0/24")?,
+        ];
+        expected.sort();
+        assert_eq!(aggs, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ipv6_only_store() -> Result<(), Box<dyn std::error::Error>> {
+        crate::common::init();
+
+        let tree_bitmap =
+            MultiThreadedStore::<PrefixAs>::new_with_config(StoreConfig {
+                address_families: AfSet::Ipv6Only,
+                ..Default::default()
+            })?;
+
+        let record = Record::new(1, 0, RouteStatus::Active, PrefixAs(65001));
+        let pfx_v4 = Prefix::from_str("185.34.0.0/16")?;
+        let pfx_v6 = Prefix::from_str("2a04:b900::/29")?;
+
+        assert_eq!(
+            tree_bitmap.insert(&pfx_v4, record.clone(), None).err(),
+            Some(PrefixStoreError::AddressFamilyDisabled)
+        );
+        assert_eq!(
+            tree_bitmap.mark_mui_as_withdrawn_v4(1),
+            Err(PrefixStoreError::AddressFamilyDisabled)
+        );
+        assert_eq!(
+            tree_bitmap.mark_mui_as_withdrawn_for_prefix(&pfx_v4, 1),
+            Err(PrefixStoreError::AddressFamilyDisabled)
+        );
+
+        tree_bitmap.insert(&pfx_v6, record, None)?;
+        tree_bitmap.mark_mui_as_withdrawn_v6(1)?;
+
+        assert_eq!(tree_bitmap.prefixes_v4_count(), 0);
+        assert_eq!(tree_bitmap.prefixes_v6_count(), 1);
+
+        Ok(())
+    }
+
+    mod wide_root {
+        use rotonda_store::prelude::multi::*;
+        use rotonda_store::prelude::*;
+
+        // The default strides, with larger root arrays.
+        #[create_store((
+            [5, 5, 4, 3, 3, 3, 3, 3, 3, 3],
+            [4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4,
+            4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4],
+            12,
+            8
+        ))]
+        struct WideRootStore;
+    }
+
+    #[test]
+    fn test_custom_root_bits() -> Result<(), Box<dyn std::error::Error>> {
+        crate::common::init();
+
+        let tree_bitmap = wide_root::WideRootStore::<PrefixAs>::new()?;
+        let pfxs = [
+            "10.0.0.0/8",
+            "185.34.0.0/16",
+            "185.35.0.0/16",
+            "185.34.10.0/24",
+            "185.34.10.128/25",
+            "185.34.10.129/32",
+        ]
+        .map(|p| Prefix::from_str(p).unwrap());
+
+        for pfx in &pfxs {
+            tree_bitmap.insert(
+                pfx,
+                Record::new(1, 0, RouteStatus::Active, PrefixAs(65000)),
+                None,
+            )?;
+        }
+
+        assert_eq!(tree_bitmap.prefixes_count(), pfxs.len());
+        assert_eq!(tree_bitmap.prefixes_iter().count(), pfxs.len());
+
+        let guard = &epoch::pin();
+        for pfx in &pfxs {
+            let res = tree_bitmap.match_prefix(
+                pfx,
+                &MatchOptions {
+                    match_type: MatchType::ExactMatch,
+                    include_withdrawn: false,
+                    include_less_specifics: true,
+                    include_more_specifics: false,
+                    mui: None,
+                    debug_path: false,
+                    stop_at_first_less_specific: false,
+                },
+                guard,
+            );
+            assert_eq!(res.prefix, Some(*pfx));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prefixes_iter_for_len() -> Result<(), Box<dyn std::error::Error>>
+    {
+        crate::common::init();
+
+        let tree_bitmap = MultiThreadedStore::<PrefixAs>::new()?;
+        let record = Record::new(1, 0, RouteStatus::Active, PrefixAs(65000));
+
+        // Enough prefixes per length to spill over into the deeper levels
+        // of the prefix buckets.
+        for a in 0..=255_u8 {
+            for len in [8, 16, 24] {
+                tree_bitmap.insert(
+                    &Prefix::new_relaxed(
+                        std::net::Ipv4Addr::new(a, a, a, 0).into(),
+                        len,
+                    )?,
+                    record.clone(),
+                    None,
+                )?;
+            }
+        }
+
+        let guard = &epoch::pin();
+        for len in 0..=32 {
+            let mut pfxs = tree_bitmap
+                .prefixes_iter_for_len(len, guard)
+                .map(|p| p.prefix)
+                .collect::<Vec<_>>();
+            pfxs.sort();
+            let mut expected = tree_bitmap
+                .prefixes_iter()
+                .map(|p| p.prefix)
+                .filter(|p| p.len() == len)
+                .collect::<Vec<_>>();
+            expected.sort();
+
+            assert_eq!(
+                pfxs.len(),
+                tree_bitmap.prefixes_v4_count_for_len(len)
+            );
+            assert_eq!(pfxs, expected);
+        }
+
+        assert_eq!(tree_bitmap.prefixes_iter_for_len(24, guard).count(), 256);
+        assert_eq!(tree_bitmap.prefixes_iter_for_len(200, guard).count(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_match_debug_path() -> Result<(), Box<dyn std::error::Error>> {
+        crate::common::init();
+
+        let tree_bitmap = MultiThreadedStore::<PrefixAs>::new()?;
+        let pfx = Prefix::from_str("185.34.10.0/24")?;
+        tree_bitmap.insert(
+            &pfx,
+            Record::new(1, 0, RouteStatus::Active, PrefixAs(65000)),
+            None,
+        )?;
+
+        let guard = &epoch::pin();
+        let mut options = MatchOptions {
+            match_type: MatchType::LongestMatch,
+            include_withdrawn: false,
+            include_less_specifics: false,
+            include_more_specifics: false,
+            mui: None,
+            debug_path: false,
+            stop_at_first_less_specific: false,
+        };
+
+        let res = tree_bitmap.match_prefix(&pfx, &options, guard);
+        assert!(res.debug_path.is_none());
+
+        options.debug_path = true;
+        let res = tree_bitmap.match_prefix(&pfx, &options, guard);
+        let path = res.debug_path.unwrap();
+
+        // The path starts at the root node, and ends at the node hosting
+        // the prefix, with all the bits of the prefix spread out over the
+        // nodes.
+        assert_eq!(path[0].node_id, Prefix::from_str("0.0.0.0/0")?);
+        assert_eq!(path.iter().map(|n| n.len).sum::<u8>(), pfx.len());
+        for node in &path {
+            assert!(node.node_id.covers(pfx));
+        }
+        let last = path.last().unwrap();
+        assert_eq!(last.node_id.len() + last.len, pfx.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_match_covering() -> Result<(), Box<dyn std::error::Error>> {
+        crate::common::init();
+
+        let tree_bitmap = MultiThreadedStore::<PrefixAs>::new()?;
+        for pfx in ["185.0.0.0/8", "185.34.0.0/16", "185.34.10.0/23"] {
+            tree_bitmap.insert(
+                &Prefix::from_str(pfx)?,
+                Record::new(1, 0, RouteStatus::Active, PrefixAs(65000)),
+                None,
+            )?;
+        }
+
+        let guard = &epoch::pin();
+        let mut options = MatchOptions {
+            match_type: MatchType::ExactMatch,
+            include_withdrawn: false,
+            include_less_specifics: true,
+            include_more_specifics: false,
+            mui: None,
+            debug_path: false,
+            stop_at_first_less_specific: false,
+        };
+
+        // No exact match, but the nearest cover is still reported.
+        let pfx = Prefix::from_str("185.34.10.0/24")?;
+        let res = tree_bitmap.match_prefix(&pfx, &options, guard);
+        assert_eq!(res.match_type, MatchType::EmptyMatch);
+        assert_eq!(res.less_specifics.as_ref().unwrap().len(), 3);
+        assert_eq!(res.covering, Some(Prefix::from_str("185.34.10.0/23")?));
+
+        // For an exact match the cover is the longest less-specific of the
+        // matched prefix itself.
+        let pfx = Prefix::from_str("185.34.0.0/16")?;
+        let res = tree_bitmap.match_prefix(&pfx, &options, guard);
+        assert_eq!(res.match_type, MatchType::ExactMatch);
+        assert_eq!(res.covering, Some(Prefix::from_str("185.0.0.0/8")?));
+
+        let pfx = Prefix::from_str("185.0.0.0/8")?;
+        let res = tree_bitmap.match_prefix(&pfx, &options, guard);
+        assert_eq!(res.covering, None);
+
+        options.include_less_specifics = false;
+        let pfx = Prefix::from_str("185.34.10.0/24")?;
+        let res = tree_bitmap.match_prefix(&pfx, &options, guard);
+        assert_eq!(res.covering, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stop_at_first_less_specific(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        crate::common::init();
+
+        let tree_bitmap = MultiThreadedStore::<PrefixAs>::new()?;
+        for pfx in ["185.0.0.0/8", "185.34.0.0/16", "185.34.10.0/23"] {
+            tree_bitmap.insert(
+                &Prefix::from_str(pfx)?,
+                Record::new(1, 0, RouteStatus::Active, PrefixAs(65000)),
+                None,
+            )?;
+        }
+
+        let guard = &epoch::pin();
+        let mut options = MatchOptions {
+            match_type: MatchType::LongestMatch,
+            include_withdrawn: false,
+            include_less_specifics: true,
+            include_more_specifics: false,
+            mui: None,
+            debug_path: false,
+            stop_at_first_less_specific: true,
+        };
+
+        let pfx = Prefix::from_str("185.34.10.0/24")?;
+        let res = tree_bitmap.match_prefix(&pfx, &options, guard);
+        assert_eq!(res.match_type, MatchType::LongestMatch);
+        assert_eq!(res.prefix, Some(Prefix::from_str("185.34.10.0/23")?));
+        let less_specifics = res.less_specifics.unwrap();
+        assert_eq!(less_specifics.len(), 1);
+        assert_eq!(
+            less_specifics.v4[0].prefix,
+            Prefix::from_str("185.34.0.0/16")?
+        );
+
+        options.stop_at_first_less_specific = false;
+        let res = tree_bitmap.match_prefix(&pfx, &options, guard);
+        assert_eq!(res.less_specifics.unwrap().len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_whole_store_walks_include_default_route(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        crate::common::init();
+
+        let tree_bitmap = MultiThreadedStore::<PrefixAs>::new()?;
+        for (pfx, mui, ltime) in
+            [("0.0.0.0/0", 1, 10), ("10.0.0.0/8", 1, 20), ("::/0", 2, 30)]
+        {
+            tree_bitmap.insert(
+                &Prefix::from_str(pfx)?,
+                Record::new(mui, ltime, RouteStatus::Active, PrefixAs(mui)),
+                None,
+            )?;
+        }
+        let default_v4 = Prefix::from_str("0.0.0.0/0")?;
+        let default_v6 = Prefix::from_str("::/0")?;
+        let guard = &epoch::pin();
+
+        let changed = tree_bitmap
+            .changed_since(0, guard)
+            .map(|p| p.prefix)
+            .collect::<Vec<_>>();
+        assert_eq!(changed.len(), 3);
+        assert!(changed.contains(&default_v4));
+        assert!(changed.contains(&default_v6));
+
+        let changed = tree_bitmap
+            .changed_since(10, guard)
+            .map(|p| p.prefix)
+            .collect::<Vec<_>>();
+        assert!(!changed.contains(&default_v4));
+        assert!(changed.contains(&default_v6));
+
+        let ids = tree_bitmap
+            .prefix_ids_iter_v4()
+            .map(|id| id.into_pub())
+            .collect::<Vec<_>>();
+        assert_eq!(ids.len(), 2);
+        assert!(ids.contains(&default_v4));
+        assert_eq!(tree_bitmap.prefix_ids_iter_v6().count(), 1);
+        assert_eq!(tree_bitmap.exact_prefixes_count(), 3);
+
+        assert_eq!(tree_bitmap.prefixes_iter().count(), 3);
+        assert_eq!(tree_bitmap.prefixes_iter().rev().count(), 3);
+        assert_eq!(tree_bitmap.prefixes_iter_for_len(0, guard).count(), 2);
+        assert_eq!(tree_bitmap.prefixes_with_record_count_over(0).count(), 3);
+        assert_eq!(tree_bitmap.records_count(), 3);
+
+        let mui_1 = tree_bitmap
+            .iter_records_for_mui(1, false, guard)
+            .map(|p| p.prefix)
+            .collect::<Vec<_>>();
+        assert_eq!(mui_1.len(), 2);
+        assert!(mui_1.contains(&default_v4));
+
+        assert_eq!(
+            tree_bitmap.recalculate_best_paths_for_mui(1, &(), guard)?,
+            2
+        );
+        assert_eq!(tree_bitmap.compact_memory().prefixes, 3);
+
+        let records = tree_bitmap.into_prefix_records().collect::<Vec<_>>();
+        assert_eq!(records.len(), 3);
+        assert!(records.iter().any(|p| p.prefix == default_v4));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mark_mui_as_stale_and_sweep(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        crate::common::init();
+
+        let tree_bitmap = MultiThreadedStore::<PrefixAs>::new()?;
+        for (pfx, mui, status) in [
+            ("0.0.0.0/0", 1, RouteStatus::Active),
+            ("10.0.0.0/8", 1, RouteStatus::Active),
+            ("10.1.0.0/16", 1, RouteStatus::Withdrawn),
+            ("10.2.0.0/16", 1, RouteStatus::InActive),
+            ("10.3.0.0/16", 2, RouteStatus::Active),
+        ] {
+            tree_bitmap.insert(
+                &Prefix::from_str(pfx)?,
+                Record::new(mui, 10, status, PrefixAs(mui)),
+                None,
+            )?;
+        }
+
+        let status_of = |pfx: &str, mui: u32| {
+            let pfx = Prefix::from_str(pfx).unwrap();
+            tree_bitmap
+                .prefixes_iter()
+                .find(|p| p.prefix == pfx)
+                .and_then(|p| p.get_record_for_mui(mui).map(|r| r.status))
+        };
+
+        // Only the Active records for mui 1 are marked, including the one
+        // for the default route.
+        assert_eq!(tree_bitmap.mark_mui_as_stale(1), 2);
+        assert_eq!(status_of("0.0.0.0/0", 1), Some(RouteStatus::Stale));
+        assert_eq!(status_of("10.0.0.0/8", 1), Some(RouteStatus::Stale));
+        assert_eq!(status_of("10.1.0.0/16", 1), Some(RouteStatus::Withdrawn));
+        assert_eq!(status_of("10.2.0.0/16", 1), Some(RouteStatus::InActive));
+        assert_eq!(status_of("10.3.0.0/16", 2), Some(RouteStatus::Active));
+
+        // The withdrawn record doesn't come back in query results.
+        let guard = &epoch::pin();
+        let options = MatchOptions {
+            match_type: MatchType::ExactMatch,
+            include_withdrawn: false,
+            include_less_specifics: false,
+            include_more_specifics: false,
+            mui: None,
+            debug_path: false,
+            stop_at_first_less_specific: false,
+        };
+        assert!(tree_bitmap
+            .match_prefix(&Prefix::from_str("10.1.0.0/16")?, &options, guard)
+            .prefix_meta
+            .is_empty());
+
+        // Marking again doesn't find any Active records for mui 1.
+        assert_eq!(tree_bitmap.mark_mui_as_stale(1), 0);
+
+        // 10.0.0.0/8 is refreshed, the default route is swept.
+        tree_bitmap.insert(
+            &Prefix::from_str("10.0.0.0/8")?,
+            Record::new(1, 20, RouteStatus::Active, PrefixAs(1)),
+            None,
+        )?;
+        assert_eq!(tree_bitmap.sweep_stale(20)?, 1);
+        assert_eq!(status_of("0.0.0.0/0", 1), Some(RouteStatus::Withdrawn));
+        assert_eq!(status_of("10.0.0.0/8", 1), Some(RouteStatus::Active));
+        assert_eq!(status_of("10.1.0.0/16", 1), Some(RouteStatus::Withdrawn));
+        assert_eq!(status_of("10.2.0.0/16", 1), Some(RouteStatus::InActive));
+        assert_eq!(tree_bitmap.sweep_stale(u64::MAX)?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_retain_records() -> Result<(), Box<dyn std::error::Error>> {
+        crate::common::init();
+
+        let tree_bitmap =
+            MultiThreadedStore::<PrefixAs>::new_with_config(StoreConfig {
+                emit_events: true,
+                ..Default::default()
+            })?;
+        let events = tree_bitmap.subscribe();
+
+        for (pfx, mui, asn) in [
+            ("0.0.0.0/0", 1, 65001),
+            ("0.0.0.0/0", 2, 65002),
+            ("10.0.0.0/8", 1, 65001),
+            ("10.0.0.0/8", 2, 65002),
+            ("::/0", 1, 65001),
+            ("2001:db8::/32", 3, 65001),
+        ] {
+            tree_bitmap.insert(
+                &Prefix::from_str(pfx)?,
+                Record::new(mui, 0, RouteStatus::Active, PrefixAs(asn)),
+                None,
+            )?;
+        }
+        while events.try_recv().is_ok() {}
+
+        // Drop everything from AS65001, including the default routes.
+        assert_eq!(tree_bitmap.retain(|_, rec| rec.meta.0 != 65001), 4);
+        assert_eq!(tree_bitmap.records_count(), 2);
+        assert!(tree_bitmap.prefixes_iter().all(|p| p
+            .meta
+            .iter()
+            .all(|r| r.multi_uniq_id == 2 && r.meta.0 == 65002)));
+
+        // The prefixes stay in the store, without records.
+        assert_eq!(tree_bitmap.prefixes_count(), 4);
+
+        // The predicate sees the prefix of the record.
+        let dflt = Prefix::from_str("0.0.0.0/0")?;
+        assert_eq!(tree_bitmap.retain(|pfx, _| *pfx != dflt), 1);
+        assert_eq!(tree_bitmap.records_count(), 1);
+
+        assert_eq!(tree_bitmap.retain(|_, _| false), 1);
+        assert_eq!(tree_bitmap.records_count(), 0);
+        assert_eq!(tree_bitmap.retain(|_, _| false), 0);
+
+        // retain doesn't send events.
+        assert!(events.try_recv().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_records_count() -> Result<(), Box<dyn std::error::Error>> {
+        crate::common::init();
+
+        let tree_bitmap = MultiThreadedStore::<PrefixAs>::new()?;
+        assert_eq!(tree_bitmap.records_count(), 0);
+
+        for (pfx, mui, status) in [
+            ("0.0.0.0/0", 1, RouteStatus::Active),
+            ("0.0.0.0/0", 2, RouteStatus::Withdrawn),
+            ("::/0", 1, RouteStatus::Active),
+            ("10.0.0.0/8", 1, RouteStatus::Active),
+            ("2001:db8::/32", 3, RouteStatus::Active),
+        ] {
+            tree_bitmap.insert(
+                &Prefix::from_str(pfx)?,
+                Record::new(mui, 0, status, PrefixAs(mui)),
+                None,
+            )?;
+        }
+
+        // The records of the default routes, and the locally withdrawn
+        // record, are counted.
+        assert_eq!(tree_bitmap.prefixes_count(), 4);
+        assert_eq!(tree_bitmap.records_count(), 5);
+
+        // So are the records for a globally withdrawn mui.
+        tree_bitmap.mark_mui_as_withdrawn_v4(1)?;
+        assert_eq!(tree_bitmap.records_count(), 5);
+
+        // Replacing a record doesn't change the count.
+        tree_bitmap.insert(
+            &Prefix::from_str("0.0.0.0/0")?,
+            Record::new(2, 1, RouteStatus::Active, PrefixAs(2)),
+            None,
+        )?;
+        assert_eq!(tree_bitmap.prefixes_count(), 4);
+        assert_eq!(tree_bitmap.records_count(), 5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_events_only_on_change() -> Result<(), Box<dyn std::error::Error>>
+    {
+        crate::common::init();
+
+        let tree_bitmap =
+            MultiThreadedStore::<PrefixAs>::new_with_config(StoreConfig {
+                emit_events: true,
+                ..Default::default()
+            })?;
+        let events = tree_bitmap.subscribe();
+        let pfx = Prefix::from_str("10.0.0.0/8")?;
+        let pfx_v6 = Prefix::from_str("2001:db8::/32")?;
+
+        for p in [pfx, pfx_v6] {
+            tree_bitmap.insert(
+                &p,
+                Record::new(1, 0, RouteStatus::Active, PrefixAs(65001)),
+                None,
+            )?;
+            assert!(matches!(
+                events.try_recv()?,
+                StoreEvent::Inserted { prefix, .. } if prefix == p
+            ));
+        }
+
+        // Marking a record with the status it already has sends nothing.
+        tree_bitmap.mark_mui_as_active_for_prefix(&pfx, 1)?;
+        tree_bitmap.mark_mui_as_withdrawn_for_prefix(&pfx, 1)?;
+        tree_bitmap.mark_mui_as_withdrawn_for_prefix(&pfx, 1)?;
+        tree_bitmap.mark_mui_as_stale_for_prefix(&pfx, 1)?;
+        tree_bitmap.mark_mui_as_active_for_prefix(&pfx, 1)?;
+        tree_bitmap.mark_mui_as_stale_for_prefix(&pfx, 1)?;
+        tree_bitmap.mark_mui_as_stale_for_prefix(&pfx, 1)?;
+        assert!(matches!(
+            events.try_recv()?,
+            StoreEvent::Withdrawn { prefix, mui: 1 } if prefix == pfx
+        ));
+        assert!(matches!(
+            events.try_recv()?,
+            StoreEvent::Activated { prefix, mui: 1 } if prefix == pfx
+        ));
+        assert!(matches!(
+            events.try_recv()?,
+            StoreEvent::Stale { prefix, mui: 1 } if prefix == pfx
+        ));
+        assert!(events.try_recv().is_err());
+
+        // Only the IPv6 record is still Active.
+        assert_eq!(tree_bitmap.mark_mui_as_stale(1), 1);
+        assert!(matches!(
+            events.try_recv()?,
+            StoreEvent::MuiStale { mui: 1 }
+        ));
+        assert_eq!(tree_bitmap.mark_mui_as_stale(1), 0);
+        assert_eq!(tree_bitmap.mark_mui_as_stale(2), 0);
+        assert!(events.try_recv().is_err());
+
+        // The global status of the mui.
+        tree_bitmap.mark_mui_as_active_v4(1)?;
+        tree_bitmap.mark_mui_as_withdrawn_v4(1)?;
+        tree_bitmap.mark_mui_as_withdrawn_v4(1)?;
+        tree_bitmap.mark_mui_as_withdrawn_at_v4(1, 10)?;
+        assert!(matches!(
+            events.try_recv()?,
+            StoreEvent::MuiWithdrawn {
+                mui: 1,
+                ipv4: true,
+                ipv6: false
+            }
+        ));
+        assert!(events.try_recv().is_err());
+        assert_eq!(tree_bitmap.mui_withdrawn_at_v4(1), Some(10));
+
+        tree_bitmap.mark_mui_as_withdrawn(1)?;
+        assert!(matches!(
+            events.try_recv()?,
+            StoreEvent::MuiWithdrawn {
+                mui: 1,
+                ipv4: false,
+                ipv6: true
+            }
+        ));
+        tree_bitmap.mark_mui_as_withdrawn(1)?;
+        assert!(events.try_recv().is_err());
+
+        tree_bitmap.mark_mui_as_active_v6(1)?;
+        tree_bitmap.mark_mui_as_active_v6(1)?;
+        assert!(matches!(
+            events.try_recv()?,
+            StoreEvent::MuiActivated {
+                mui: 1,
+                ipv4: false,
+                ipv6: true
+            }
+        ));
+        assert!(events.try_recv().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_events_without_emit_events(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        crate::common::init();
+
+        let tree_bitmap = MultiThreadedStore::<PrefixAs>::new()?;
+        assert!(!tree_bitmap.config().emit_events);
+        let events = tree_bitmap.subscribe();
+        let pfx = Prefix::from_str("10.0.0.0/8")?;
+
+        let record = Record::new(1, 0, RouteStatus::Active, PrefixAs(65001));
+        tree_bitmap.insert(&pfx, record.clone(), None)?;
+        tree_bitmap.insert(&pfx, record, None)?;
+        tree_bitmap.mark_mui_as_withdrawn_for_prefix(&pfx, 1)?;
+        tree_bitmap.mark_mui_as_active_for_prefix(&pfx, 1)?;
+        assert_eq!(tree_bitmap.mark_mui_as_stale(1), 1);
+        tree_bitmap.mark_mui_as_withdrawn(1)?;
+        tree_bitmap.mark_mui_as_active_v4(1)?;
+
+        assert!(matches!(
+            events.try_recv(),
+            Err(std::sync::mpsc::TryRecvError::Empty)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_events_concurrent_inserts(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        crate::common::init();
+
+        let tree_bitmap =
+            MultiThreadedStore::<PrefixAs>::new_with_config(StoreConfig {
+                emit_events: true,
+                ..Default::default()
+            })?;
+        let events = tree_bitmap.subscribe();
+        // This subscriber goes away before anything is sent, and is
+        // dropped by the store on the first event.
+        drop(tree_bitmap.subscribe());
+
+        std::thread::scope(|s| {
+            for mui in 0..4_u32 {
+                let tree_bitmap = &tree_bitmap;
+                s.spawn(move || {
+                    for i in 0..=255_u8 {
+                        let pfx = Prefix::new(
+                            std::net::Ipv4Addr::new(10, i, 0, 0).into(),
+                            16,
+                        )
+                        .unwrap();
+                        tree_bitmap
+                            .insert(
+                                &pfx,
+                                Record::new(
+                                    mui,
+                                    0,
+                                    RouteStatus::Active,
+                                    PrefixAs(mui),
+                                ),
+                                None,
+                            )
+                            .unwrap();
+                    }
+                });
+            }
+        });
+
+        // Every record is new, so every insert sends exactly one Inserted
+        // event, for its own (prefix, mui).
+        let mut seen = std::collections::HashSet::new();
+        while let Ok(event) = events.try_recv() {
+            match event {
+                StoreEvent::Inserted { prefix, record } => {
+                    assert_eq!(record.meta.0, record.multi_uniq_id);
+                    assert!(seen.insert((prefix, record.multi_uniq_id)));
+                }
+                e => panic!("unexpected event {:?}", e),
+            }
+        }
+        assert_eq!(seen.len(), 4 * 256);
+        assert_eq!(tree_bitmap.records_count(), 4 * 256);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ignore_withdrawal_of_unknown_is_atomic(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        crate::common::init();
+
+        let tree_bitmap =
+            MultiThreadedStore::<PrefixAs>::new_with_config(StoreConfig {
+                ignore_withdrawal_of_unknown: true,
+                ..Default::default()
+            })?;
+        let withdrawal =
+            Record::new(1, 0, RouteStatus::Withdrawn, PrefixAs(65001));
+
+        let mut pfxs = vec![Prefix::from_str("0.0.0.0/0")?];
+        pfxs.extend((0..=255_u8).map(|i| {
+            Prefix::new(std::net::Ipv4Addr::new(10, i, 0, 0).into(), 16)
+                .unwrap()
+        }));
+        for pfx in &pfxs {
+            for mui in [1, 2] {
+                tree_bitmap.insert(
+                    pfx,
+                    Record::new(mui, 0, RouteStatus::Active, PrefixAs(mui)),
+                    None,
+                )?;
+            }
+        }
+
+        // The records for mui 1 are removed while its withdrawals come in.
+        // A withdrawal that arrives after the removal of its record must
+        // not bring the record back.
+        std::thread::scope(|s| {
+            s.spawn(|| tree_bitmap.retain(|_, rec| rec.multi_uniq_id != 1));
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..8 {
+                        for pfx in &pfxs {
+                            tree_bitmap
+                                .insert(pfx, withdrawal.clone(), None)
+                                .unwrap();
+                        }
+                    }
+                });
+            }
+        });
+
+        // Any withdrawal that arrived after the retain is a NoOp.
+        for pfx in &pfxs {
+            let report = tree_bitmap.insert(pfx, withdrawal.clone(), None)?;
+            assert_eq!(report.outcome, UpsertOutcome::NoOp);
+            assert_eq!(report.mui_count, 1);
+        }
+        assert_eq!(tree_bitmap.records_count(), pfxs.len());
+        assert!(tree_bitmap
+            .prefixes_iter()
+            .all(|p| p.meta.iter().all(|r| r.multi_uniq_id == 2)));
+
+        // try_insert never stores a withdrawal.
+        let known = Record::new(2, 1, RouteStatus::Withdrawn, PrefixAs(2));
+        assert!(matches!(
+            tree_bitmap.try_insert(&pfxs[0], known),
+            Err(PrefixStoreError::RecordAlreadyExists)
+        ));
+        let report = tree_bitmap.try_insert(&pfxs[0], withdrawal)?;
+        assert_eq!(report.outcome, UpsertOutcome::NoOp);
+        assert_eq!(tree_bitmap.records_count(), pfxs.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prefixes_iter_both_ends() -> Result<(), Box<dyn std::error::Error>>
+    {
+        crate::common::init();
+
+        let tree_bitmap = MultiThreadedStore::<PrefixAs>::new()?;
+        for pfx in [
+            "0.0.0.0/0",
+            "10.0.0.0/8",
+            "10.1.0.0/16",
+            "10.1.1.0/24",
+            "192.0.2.0/24",
+            "::/0",
+            "2001:db8::/32",
+            "2001:db8:1::/48",
+        ] {
+            tree_bitmap.insert(
+                &Prefix::from_str(pfx)?,
+                Record::new(1, 0, RouteStatus::Active, PrefixAs(65001)),
+                None,
+            )?;
+        }
+
+        let forward = tree_bitmap
+            .prefixes_iter()
+            .map(|p| p.prefix)
+            .collect::<Vec<_>>();
+        assert_eq!(forward.len(), 8);
+
+        // Start from either end, take a few steps forward first, or
+        // alternate from the start, until the two ends meet.
+        for skip in 0..=forward.len() {
+            let mut iter = tree_bitmap.prefixes_iter();
+            let mut front = iter
+                .by_ref()
+                .take(skip)
+                .map(|p| p.prefix)
+                .collect::<Vec<_>>();
+            let mut back = vec![];
+            while let Some(p) = iter.next_back() {
+                back.push(p.prefix);
+                let Some(p) = iter.next() else {
+                    break;
+                };
+                front.push(p.prefix);
+            }
+            assert!(iter.next().is_none());
+            assert!(iter.next_back().is_none());
+
+            front.extend(back.into_iter().rev());
+            assert_eq!(front, forward);
+        }
+
+        let mut iter = tree_bitmap.prefixes_iter_v4();
+        let last = iter.next_back().map(|p| p.prefix);
+        let first = iter.next().map(|p| p.prefix);
+        assert_eq!(first, Some(forward[0]));
+        assert_eq!(last, Some(forward[4]));
+        assert_eq!(iter.count(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prefixes_iter_with_guard_snapshot(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        crate::common::init();
+
+        let tree_bitmap = MultiThreadedStore::<PrefixAs>::new()?;
+        let pfxs = ["0.0.0.0/0", "10.0.0.0/8", "::/0", "2001:db8::/32"]
+            .map(|p| Prefix::from_str(p).unwrap());
+        for pfx in &pfxs {
+            tree_bitmap.insert(
+                pfx,
+                Record::new(1, 0, RouteStatus::Active, PrefixAs(1)),
+                None,
+            )?;
+        }
+
+        let guard = &epoch::pin();
+        let mut snapshot = tree_bitmap.prefixes_iter_with_guard(guard);
+        let first = snapshot.next().unwrap();
+        assert_eq!(first.prefix, pfxs[0]);
+
+        // Everything that happens after the snapshot was taken, also while
+        // it is being iterated over, is invisible to it.
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                for i in 0..=255_u8 {
+                    let pfx = Prefix::new(
+                        std::net::Ipv4Addr::new(10, i, 0, 0).into(),
+                        16,
+                    )
+                    .unwrap();
+                    tree_bitmap
+                        .insert(
+                            &pfx,
+                            Record::new(
+                                2,
+                                0,
+                                RouteStatus::Active,
+                                PrefixAs(2),
+                            ),
+                            None,
+                        )
+                        .unwrap();
+                }
+                tree_bitmap
+                    .insert(
+                        &pfxs[1],
+                        Record::new(2, 0, RouteStatus::Active, PrefixAs(2)),
+                        None,
+                    )
+                    .unwrap();
+                tree_bitmap.mark_mui_as_withdrawn(1).unwrap();
+            });
+        });
+
+        let rest = snapshot.collect::<Vec<_>>();
+        assert_eq!(
+            rest.iter().map(|p| p.prefix).collect::<Vec<_>>(),
+            pfxs[1..]
+        );
+        assert!(rest.iter().all(|p| p.meta.len() == 1
+            && p.meta[0].multi_uniq_id == 1
+            && p.meta[0].status == RouteStatus::Active));
+
+        // A new snapshot sees all of it.
+        let snapshot = tree_bitmap
+            .prefixes_iter_with_guard(guard)
+            .collect::<Vec<_>>();
+        assert_eq!(snapshot.len(), 4 + 256);
+        assert!(snapshot
+            .iter()
+            .flat_map(|p| p.meta.iter())
+            .all(|r| r.multi_uniq_id == 2
+                || r.status == RouteStatus::Withdrawn));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_more_specifics_within_stride_boundaries(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        crate::common::init();
+
+        let tree_bitmap = MultiThreadedStore::<PrefixAs>::new()?;
+        let record = Record::new(1, 0, RouteStatus::Active, PrefixAs(1));
+        let guard = &epoch::pin();
+
+        // Prefixes of every length below the search prefixes, so that
+        // there are prefixes right at, and right after, the start and the
+        // end of every stride.
+        let v4 = Prefix::from_str("10.0.0.0/8")?;
+        let v6 = Prefix::from_str("2001:db8::/32")?;
+        for len in 9..=32 {
+            for addr in [0x0a00_0000_u32, 0x0aff_ffff] {
+                let net = addr & (u32::MAX << (32 - len));
+                let pfx =
+                    Prefix::new(std::net::Ipv4Addr::from(net).into(), len)?;
+                tree_bitmap.insert(&pfx, record.clone(), None)?;
+            }
+        }
+        for len in 33..=64 {
+            let pfx = Prefix::new(v6.addr(), len)?;
+            tree_bitmap.insert(&pfx, record.clone(), None)?;
+        }
+
+        for (search_pfx, max) in [(v4, 32), (v6, 64)] {
+            let all = tree_bitmap
+                .more_specifics_iter_from(&search_pfx, None, false, guard)
+                .map(|p| p.prefix)
+                .collect::<Vec<_>>();
+
+            for max_len in search_pfx.len()..=max {
+                let mut within = tree_bitmap
+                    .more_specifics_within(
+                        &search_pfx,
+                        max_len,
+                        None,
+                        false,
+                        guard,
+                    )
+                    .map(|p| p.prefix)
+                    .collect::<Vec<_>>();
+                within.sort();
+                let mut expected = all
+                    .iter()
+                    .filter(|p| p.len() <= max_len)
+                    .copied()
+                    .collect::<Vec<_>>();
+                expected.sort();
+                assert_eq!(within, expected, "max_len {}", max_len);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_intern_meta() -> Result<(), Box<dyn std::error::Error>> {
+        crate::common::init();
+
+        let tree_bitmap =
+            MultiThreadedStore::<BytesMeta>::new_with_config(StoreConfig {
+                intern_meta: true,
+                ..Default::default()
+            })?;
+
+        let pfxs = [
+            Prefix::from_str("185.34.0.0/16")?,
+            Prefix::from_str("185.34.10.0/24")?,
+            Prefix::from_str("2a04:b900::/29")?,
+        ];
+        let shared = BytesMeta(vec![1, 2, 3]);
+
+        // The same meta-data for every mui of every prefix, across both
+        // address families.
+        for pfx in &pfxs {
+            for mui in 1..=4 {
+                tree_bitmap.insert(
+                    pfx,
+                    Record::new(mui, 0, RouteStatus::Active, shared.clone()),
+                    None,
+                )?;
+            }
+        }
+
+        assert_eq!(
+            tree_bitmap.interner_stats(),
+            Some(InternerStats {
+                hits: 11,
+                misses: 1,
+                interned: 1
+            })
+        );
+
+        // Other meta-data for the records of mui 4.
+        for (i, pfx) in pfxs.iter().enumerate() {
+            tree_bitmap.insert(
+                pfx,
+                Record::new(
+                    4,
+                    1,
+                    RouteStatus::Active,
+                    BytesMeta(vec![4, i as u8]),
+                ),
+                None,
+            )?;
+        }
+
+        assert_eq!(
+            tree_bitmap.interner_stats(),
+            Some(InternerStats {
+                hits: 11,
+                misses: 4,
+                interned: 4
+            })
+        );
+
+        let guard = &epoch::pin();
+        for (i, pfx) in pfxs.iter().enumerate() {
+            let res = tree_bitmap.match_prefix(
+                pfx,
+                &MatchOptions {
+                    match_type: MatchType::ExactMatch,
+                    include_withdrawn: false,
+                    include_less_specifics: false,
+                    include_more_specifics: false,
+                    mui: None,
+                    debug_path: false,
+                    stop_at_first_less_specific: false,
+                },
+                guard,
+            );
+            assert_eq!(res.prefix_meta.len(), 4);
+            for record in res.prefix_meta {
+                if record.multi_uniq_id == 4 {
+                    assert_eq!(record.meta, BytesMeta(vec![4, i as u8]));
+                } else {
+                    assert_eq!(record.meta, shared);
+                }
+            }
+        }
+
+        // The interned meta-data is dropped with the last record that
+        // references it.
+        for pfx in &pfxs[..2] {
+            tree_bitmap.insert(
+                pfx,
+                Record::new(4, 2, RouteStatus::Active, shared.clone()),
+                None,
+            )?;
+        }
+        assert_eq!(tree_bitmap.interner_stats().map(|s| s.interned), Some(2));
+
+        let tree_bitmap = MultiThreadedStore::<BytesMeta>::new()?;
+        tree_bitmap.insert(
+            &pfxs[0],
+            Record::new(1, 0, RouteStatus::Active, shared),
+            None,
+        )?;
+        assert_eq!(tree_bitmap.interner_stats(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prefix_ids_iter() -> Result<(), Box<dyn std::error::Error>> {
+        crate::common::init();
+
+        let tree_bitmap = MultiThreadedStore::<PrefixAs>::new()?;
+
+        let mut pfxs = vec![
+            Prefix::from_str("0.0.0.0/0")?,
+            Prefix::from_str("255.255.255.255/32")?,
+            Prefix::from_str("::/0")?,
+            Prefix::from_str("2001:db8::1/128")?,
+        ];
+        // Prefixes of every length, with the same first bits, so that
+        // they end up in the same nodes and collide in the buckets.
+        for len in 1..=32_u8 {
+            for addr in [0xb924_0a00_u32, 0xb925_0b00] {
+                let net = addr & (u32::MAX << (32 - len));
+                pfxs.push(Prefix::new_relaxed(
+                    std::net::Ipv4Addr::from(net).into(),
+                    len,
+                )?);
+            }
+        }
+        for len in (8..=128_u8).step_by(8) {
+            let net = 0x2a04_b900_0000_0000_0000_0000_0000_0001_u128
+                & (u128::MAX << (128 - len));
+            pfxs.push(Prefix::new_relaxed(
+                std::net::Ipv6Addr::from(net).into(),
+                len,
+            )?);
+        }
+        pfxs.sort();
+        pfxs.dedup();
+
+        for (i, pfx) in pfxs.iter().enumerate() {
+            // The statuses of the records don't matter.
+            let status = if i % 3 == 0 {
+                RouteStatus::Withdrawn
+            } else {
+                RouteStatus::Active
+            };
+            tree_bitmap.insert(
+                pfx,
+                Record::new(i as u32 % 2, 0, status, PrefixAs(i as u32)),
+                None,
+            )?;
+        }
+        tree_bitmap.mark_mui_as_withdrawn_v4(1)?;
+
+        let mut ids = tree_bitmap
+            .prefix_ids_iter_v4()
+            .map(|id| id.into_pub())
+            .chain(tree_bitmap.prefix_ids_iter_v6().map(|id| id.into_pub()))
+            .collect::<Vec<_>>();
+        ids.sort();
+
+        assert_eq!(ids, pfxs);
+        assert_eq!(
+            tree_bitmap.prefix_ids_iter_v4().count(),
+            tree_bitmap.prefixes_v4_count()
+        );
+        assert_eq!(
+            tree_bitmap.prefix_ids_iter_v6().count(),
+            tree_bitmap.prefixes_v6_count()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mui_withdrawn_at() -> Result<(), Box<dyn std::error::Error>> {
+        crate::common::init();
+
+        let tree_bitmap = MultiThreadedStore::<PrefixAs>::new()?;
+        let pfx = Prefix::from_str("185.34.0.0/16")?;
+        tree_bitmap.insert(
+            &pfx,
+            Record::new(1, 0, RouteStatus::Active, PrefixAs(65001)),
+            None,
+        )?;
+
+        assert_eq!(tree_bitmap.mui_withdrawn_at_v4(1), None);
+
+        // The ltimes are kept per address family.
+        tree_bitmap.mark_mui_as_withdrawn_at_v4(1, 100)?;
+        tree_bitmap.mark_mui_as_withdrawn_at_v6(1, 200)?;
+        assert!(tree_bitmap.mui_is_withdrawn_v4(1));
+        assert_eq!(tree_bitmap.mui_withdrawn_at_v4(1), Some(100));
+        assert_eq!(tree_bitmap.mui_withdrawn_at_v6(1), Some(200));
+
+        let guard = &epoch::pin();
+        let res = tree_bitmap.match_prefix(
+            &pfx,
+            &MatchOptions {
+                match_type: MatchType::ExactMatch,
+                include_withdrawn: true,
+                include_less_specifics: false,
+                include_more_specifics: false,
+                mui: None,
+                debug_path: false,
+                stop_at_first_less_specific: false,
+            },
+            guard,
+        );
+        assert_eq!(res.prefix_meta[0].status, RouteStatus::Withdrawn);
+
+        // A later withdrawal replaces the ltime.
+        tree_bitmap.mark_mui_as_withdrawn_at_v4_with_guard(1, 150, guard)?;
+        assert_eq!(tree_bitmap.mui_withdrawn_at_v4(1), Some(150));
+
+        // Activating the mui forgets the ltime, also when it is withdrawn
+        // again without one.
+        tree_bitmap.mark_mui_as_active_v4(1)?;
+        assert!(!tree_bitmap.mui_is_withdrawn_v4(1));
+        assert_eq!(tree_bitmap.mui_withdrawn_at_v4(1), None);
+        tree_bitmap.mark_mui_as_withdrawn_v4(1)?;
+        assert_eq!(tree_bitmap.mui_withdrawn_at_v4(1), None);
+        assert_eq!(tree_bitmap.mui_withdrawn_at_v6(1), Some(200));
+
+        let tree_bitmap =
+            MultiThreadedStore::<PrefixAs>::new_with_config(StoreConfig {
+                address_families: AfSet::Ipv4Only,
+                ..Default::default()
+            })?;
+        assert_eq!(
+            tree_bitmap.mark_mui_as_withdrawn_at_v6(1, 100),
+            Err(PrefixStoreError::AddressFamilyDisabled)
+        );
+        assert_eq!(tree_bitmap.mui_withdrawn_at_v6(1), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prefix_attr() -> Result<(), Box<dyn std::error::Error>> {
+        crate::common::init();
+
+        let tree_bitmap =
+            std::sync::Arc::new(MultiThreadedStore::<PrefixAs>::new()?);
+        let pfx = Prefix::from_str("185.34.0.0/16")?;
+        let more_specific = Prefix::from_str("185.34.10.0/24")?;
+        let pfx_v6 = Prefix::from_str("2a04:b900::/29")?;
+
+        for p in [&pfx, &more_specific, &pfx_v6] {
+            tree_bitmap.insert(
+                p,
+                Record::new(1, 0, RouteStatus::Active, PrefixAs(65001)),
+                None,
+            )?;
+        }
+
+        tree_bitmap.set_prefix_attr(&pfx, vec![1])?;
+        tree_bitmap.set_prefix_attr(&pfx_v6, vec![6])?;
+
+        // The attribute belongs to the prefix only.
+        assert_eq!(tree_bitmap.get_prefix_attr(&pfx), Some(vec![1]));
+        assert_eq!(tree_bitmap.get_prefix_attr(&more_specific), None);
+        assert_eq!(tree_bitmap.get_prefix_attr(&pfx_v6), Some(vec![6]));
+
+        // It survives changes to the records of the prefix.
+        tree_bitmap.insert(
+            &pfx,
+            Record::new(1, 1, RouteStatus::Active, PrefixAs(65002)),
+            None,
+        )?;
+        tree_bitmap.insert(
+            &pfx,
+            Record::new(2, 1, RouteStatus::Active, PrefixAs(65003)),
+            None,
+        )?;
+        tree_bitmap.mark_mui_as_withdrawn_for_prefix(&pfx, 1)?;
+        tree_bitmap.mark_mui_as_withdrawn_v4(2)?;
+        assert_eq!(tree_bitmap.get_prefix_attr(&pfx), Some(vec![1]));
+
+        // Concurrent writers replace each other's attribute, readers only
+        // ever see a complete one.
+        let _: Vec<_> = (0..4_u8)
+            .map(|n| {
+                let tree_bitmap = tree_bitmap.clone();
+                std::thread::spawn(move || {
+                    for i in 0..100_u8 {
+                        tree_bitmap
+                            .set_prefix_attr(&pfx, vec![n; i as usize + 1])
+                            .unwrap();
+                        let attr = tree_bitmap.get_prefix_attr(&pfx).unwrap();
+                        assert!(attr.iter().all(|b| *b == attr[0]));
+                    }
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|t| t.join().unwrap())
+            .collect();
+
+        let attr = tree_bitmap.get_prefix_attr(&pfx).unwrap();
+        assert_eq!(attr.len(), 100);
+        assert!(attr[0] < 4);
+
+        assert_eq!(
+            tree_bitmap
+                .set_prefix_attr(&Prefix::from_str("10.0.0.0/8")?, vec![]),
+            Err(PrefixStoreError::PrefixNotFound)
+        );
+        assert_eq!(
+            tree_bitmap.get_prefix_attr(&Prefix::from_str("10.0.0.0/8")?),
+            None
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_store_diff() -> Result<(), Box<dyn std::error::Error>> {
+        crate::common::init();
+
+        let store = MultiThreadedStore::<PrefixAs>::new()?;
+        let other = MultiThreadedStore::<PrefixAs>::new()?;
+
+        let pfx_v4 = Prefix::from_str("185.34.0.0/16")?;
+        let pfx_v6 = Prefix::from_str("2a04:b900::/29")?;
+        let records = [
+            (pfx_v4, Record::new(1, 0, RouteStatus::Active, PrefixAs(1))),
+            (pfx_v4, Record::new(2, 0, RouteStatus::Active, PrefixAs(2))),
+            (pfx_v6, Record::new(1, 0, RouteStatus::Active, PrefixAs(1))),
+        ];
+
+        // The order of the inserts doesn't matter.
+        for (pfx, rec) in records.iter() {
+            store.insert(pfx, rec.clone(), None)?;
+        }
+        for (pfx, rec) in records.iter().rev() {
+            other.insert(pfx, rec.clone(), None)?;
+        }
+        assert!(store.diff(&other).is_empty());
+
+        // Neither does the global status of a mui.
+        other.mark_mui_as_withdrawn(2)?;
+        assert!(store.diff(&other).is_empty());
+
+        // A prefix without records is absent.
+        let empty = Prefix::from_str("10.0.0.0/8")?;
+        store.insert(
+            &empty,
+            Record::new(3, 0, RouteStatus::Active, PrefixAs(3)),
+            None,
+        )?;
+        assert_eq!(store.retain(|pfx, _| *pfx != empty), 1);
+        assert!(store.diff(&other).is_empty());
+
+        // Differences in the ltime, the local status, the meta-data and
+        // the muis of the records.
+        other.insert(
+            &pfx_v4,
+            Record::new(1, 1, RouteStatus::Active, PrefixAs(1)),
+            None,
+        )?;
+        store.mark_mui_as_withdrawn_for_prefix(&pfx_v6, 1)?;
+        let only_in_other = Prefix::from_str("2001:db8::/32")?;
+        other.insert(
+            &only_in_other,
+            Record::new(4, 0, RouteStatus::Active, PrefixAs(4)),
+            None,
+        )?;
+
+        let diff = store.diff(&other);
+        assert!(diff.only_in_self.is_empty());
+        assert_eq!(diff.only_in_other.len(), 1);
+        assert_eq!(diff.only_in_other[0].prefix, only_in_other);
+        assert_eq!(
+            diff.differing.iter().map(|d| d.prefix).collect::<Vec<_>>(),
+            vec![pfx_v4, pfx_v6]
+        );
+        assert_eq!(diff.differing[0].in_self[0].ltime, 0);
+        assert_eq!(diff.differing[0].in_other[0].ltime, 1);
+        assert_eq!(
+            diff.differing[1].in_self[0].status,
+            RouteStatus::Withdrawn
+        );
+
+        // The diff in the other direction mirrors it.
+        let reverse = other.diff(&store);
+        assert_eq!(reverse.only_in_self.len(), 1);
+        assert!(reverse.only_in_other.is_empty());
+        assert_eq!(reverse.differing.len(), 2);
+
+        let meta_diff = MultiThreadedStore::<PrefixAs>::new()?;
+        let extra_mui = MultiThreadedStore::<PrefixAs>::new()?;
+        for s in [&meta_diff, &extra_mui] {
+            s.insert(
+                &pfx_v4,
+                Record::new(1, 0, RouteStatus::Active, PrefixAs(1)),
+                None,
+            )?;
+        }
+        let other_meta = MultiThreadedStore::<PrefixAs>::new()?;
+        other_meta.insert(
+            &pfx_v4,
+            Record::new(1, 0, RouteStatus::Active, PrefixAs(65001)),
+            None,
+        )?;
+        assert_eq!(meta_diff.diff(&other_meta).differing.len(), 1);
+
+        extra_mui.insert(
+            &pfx_v4,
+            Record::new(2, 0, RouteStatus::Active, PrefixAs(1)),
+            None,
+        )?;
+        let diff = meta_diff.diff(&extra_mui);
+        assert_eq!(diff.differing.len(), 1);
+        assert_eq!(diff.differing[0].in_other.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_overlaps() -> Result<(), Box<dyn std::error::Error>> {
+        crate::common::init();
+
+        let store = MultiThreadedStore::<PrefixAs>::new()?;
+        let guard = &epoch::pin();
+
+        for (pfx, mui, asn) in [
+            ("0.0.0.0/0", 1, 65000),
+            ("10.0.0.0/8", 1, 65001),
+            ("10.1.0.0/16", 1, 65002),
+            ("10.1.2.0/24", 1, 65003),
+            ("10.1.2.0/24", 2, 65002),
+            ("2001:db8::/32", 1, 65001),
+            ("2001:db8:1::/48", 1, 65001),
+            ("2001:db8:1:2::/64", 2, 65002),
+        ] {
+            store.insert(
+                &Prefix::from_str(pfx)?,
+                Record::new(mui, 0, RouteStatus::Active, PrefixAs(asn)),
+                None,
+            )?;
+        }
+
+        let overlaps = |store: &MultiThreadedStore<PrefixAs>| {
+            let mut overlaps = store
+                .find_overlaps(|l, m| l.meta != m.meta, guard)
+                .map(|(l, m)| (l.to_string(), m.to_string()))
+                .collect::<Vec<_>>();
+            overlaps.sort();
+            overlaps
+        };
+        let pair = |l: &str, m: &str| (l.to_string(), m.to_string());
+
+        // The default route is a less-specific as well, and one conflicting
+        // record is enough for a pair.
+        assert_eq!(
+            overlaps(&store),
+            vec![
+                pair("0.0.0.0/0", "10.0.0.0/8"),
+                pair("10.0.0.0/8", "10.1.0.0/16"),
+                pair("10.1.0.0/16", "10.1.2.0/24"),
+                pair("2001:db8:1::/48", "2001:db8:1:2::/64"),
+            ]
+        );
+
+        // Without a conflicting record there's no pair.
+        assert_eq!(store.find_overlaps(|_, _| false, guard).count(), 0);
+
+        // A prefix with only withdrawn records is skipped, as less-specific
+        // and as more-specific.
+        store.mark_mui_as_withdrawn_for_prefix(
+            &Prefix::from_str("10.1.0.0/16")?,
+            1,
+        )?;
+        assert_eq!(
+            overlaps(&store),
+            vec![
+                pair("0.0.0.0/0", "10.0.0.0/8"),
+                pair("10.0.0.0/8", "10.1.2.0/24"),
+                pair("2001:db8:1::/48", "2001:db8:1:2::/64"),
+            ]
+        );
+
+        // So are records of a globally withdrawn mui.
+        store.mark_mui_as_withdrawn(2)?;
+        assert_eq!(
+            overlaps(&store),
+            vec![
+                pair("0.0.0.0/0", "10.0.0.0/8"),
+                pair("10.0.0.0/8", "10.1.2.0/24"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exact_and_cover_withdrawn(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        crate::common::init();
+
+        let tree_bitmap = MultiThreadedStore::<PrefixAs>::new()?;
+        let guard = &epoch::pin();
+        let exact = Prefix::from_str("185.49.140.10/32")?;
+        let cover = Prefix::from_str("185.49.140.0/22")?;
+
+        for pfx in [&exact, &cover] {
+            tree_bitmap.insert(
+                pfx,
+                Record::new(1, 0, RouteStatus::Active, PrefixAs(211321)),
+                None,
+            )?;
+        }
+
+        let (found, found_cover) = tree_bitmap.exact_and_cover(&exact, guard);
+        assert_eq!(found.map(|p| p.prefix), Some(exact));
+        assert_eq!(found_cover.map(|p| p.prefix), Some(cover));
+
+        // With all its records withdrawn the prefix isn't an exact match
+        // anymore, but the cover still is.
+        tree_bitmap.mark_mui_as_withdrawn_for_prefix(&exact, 1)?;
+        let (found, found_cover) = tree_bitmap.exact_and_cover(&exact, guard);
+        assert!(found.is_none());
+        assert_eq!(found_cover.map(|p| p.prefix), Some(cover));
+
+        // A record for another mui makes it an exact match again, with
+        // only the active record.
+        tree_bitmap.insert(
+            &exact,
+            Record::new(2, 0, RouteStatus::Active, PrefixAs(211322)),
+            None,
+        )?;
+        let (found, _) = tree_bitmap.exact_and_cover(&exact, guard);
+        let found = found.ok_or("no exact match")?;
+        assert_eq!(found.prefix, exact);
+        assert_eq!(
+            found
+                .meta
+                .iter()
+                .map(|r| r.multi_uniq_id)
+                .collect::<Vec<_>>(),
+            vec![2]
+        );
+
+        Ok(())
+    }
 }
@@ -45,6 +45,8 @@ mod tests {
                 include_less_specifics: true,
                 include_more_specifics: false,
                 mui: None,
+                debug_path: false,
+                stop_at_first_less_specific: false,
             },
             guard,
         );
@@ -84,6 +86,8 @@ mod tests {
                 include_less_specifics: true,
                 include_more_specifics: false,
                 mui: None,
+                debug_path: false,
+                stop_at_first_less_specific: false,
             },
             guard,
         );
@@ -119,6 +123,8 @@ mod tests {
                 include_less_specifics: true,
                 include_more_specifics: false,
                 mui: None,
+                debug_path: false,
+                stop_at_first_less_specific: false,
             },
             guard,
         );
@@ -290,6 +296,8 @@ mod tests {
                     include_less_specifics: false,
                     include_more_specifics: false,
                     mui: None,
+                    debug_path: false,
+                    stop_at_first_less_specific: false,
                 },
                 guard,
             );
@@ -557,6 +565,8 @@ mod tests {
                     include_less_specifics: false,
                     include_more_specifics: false,
                     mui: None,
+                    debug_path: false,
+                    stop_at_first_less_specific: false,
                 },
                 guard,
             );
@@ -575,6 +585,8 @@ mod tests {
                 include_less_specifics: true,
                 include_more_specifics: false,
                 mui: None,
+                debug_path: false,
+                stop_at_first_less_specific: false,
             },
             guard,
         );
@@ -660,6 +672,8 @@ mod tests {
                             include_less_specifics: false,
                             include_more_specifics: false,
                             mui: None,
+                            debug_path: false,
+                            stop_at_first_less_specific: false,
                         },
                         guard,
                     );